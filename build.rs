@@ -0,0 +1,97 @@
+//! Compila as rotinas numéricas de `simd/` com SSE e as liga ao kernel.
+//!
+//! O kernel é `+soft-float`; `simd/` usa o target irmão
+//! `x86_64-rust_os-sse.json` (ver `src/simd.rs`). As duas bibliotecas têm
+//! seu próprio `core` e `compiler_builtins`, então a biblioteca estática
+//! de `simd/` passa por:
+//!
+//! ```text
+//! librust_os_simd.a ──rust-lld -r──→ rust_os_simd.o (um objeto só)
+//!                   ──llvm-objcopy──→ só simd_* globais (o resto vira local)
+//!                   ──llvm-ar──────→ librust_os_simd.a em OUT_DIR
+//! ```
+//!
+//! Requer o componente `llvm-tools-preview` (llvm-objcopy, llvm-ar).
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+const SIMD_TARGET: &str = "x86_64-rust_os-sse";
+
+fn main() {
+    let root = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap());
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    let simd = root.join("simd");
+    let target_spec = root.join(format!("{}.json", SIMD_TARGET));
+    println!("cargo:rerun-if-changed={}", simd.join("src").display());
+    println!(
+        "cargo:rerun-if-changed={}",
+        simd.join("Cargo.toml").display()
+    );
+    println!("cargo:rerun-if-changed={}", target_spec.display());
+
+    // Roda dentro de `simd/` para herdar o `.cargo/config.toml` (build-std);
+    // as flags do kernel não valem para o outro target
+    let target_dir = out_dir.join("simd-target");
+    let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    run(Command::new(cargo)
+        .current_dir(&simd)
+        .env_remove("CARGO_ENCODED_RUSTFLAGS")
+        .env_remove("RUSTFLAGS")
+        .env_remove("CARGO_BUILD_RUSTFLAGS")
+        .arg("build")
+        .arg("--release")
+        .arg("--target")
+        .arg(&target_spec)
+        .arg("--target-dir")
+        .arg(&target_dir));
+    let archive = target_dir
+        .join(SIMD_TARGET)
+        .join("release")
+        .join("librust_os_simd.a");
+
+    let tools = llvm_tools();
+    let object = out_dir.join("rust_os_simd.o");
+    run(Command::new(tools.join("rust-lld"))
+        .args(["-flavor", "gnu", "-r", "--whole-archive"])
+        .arg(&archive)
+        .arg("-o")
+        .arg(&object));
+    run(Command::new(tools.join("llvm-objcopy"))
+        .args(["--wildcard", "--keep-global-symbol=simd_*"])
+        .arg(&object));
+
+    let library = out_dir.join("librust_os_simd.a");
+    let _ = std::fs::remove_file(&library);
+    run(Command::new(tools.join("llvm-ar"))
+        .arg("crs")
+        .arg(&library)
+        .arg(&object));
+
+    println!("cargo:rustc-link-search=native={}", out_dir.display());
+    println!("cargo:rustc-link-lib=static=rust_os_simd");
+}
+
+/// Diretório com `rust-lld` e as ferramentas do `llvm-tools-preview`.
+fn llvm_tools() -> PathBuf {
+    let rustc = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let output = Command::new(rustc)
+        .args(["--print", "sysroot"])
+        .output()
+        .expect("failed to run rustc");
+    let sysroot = String::from_utf8(output.stdout).unwrap();
+    Path::new(sysroot.trim())
+        .join("lib/rustlib")
+        .join(env::var("HOST").unwrap())
+        .join("bin")
+}
+
+fn run(command: &mut Command) {
+    let status = command
+        .status()
+        .unwrap_or_else(|err| panic!("failed to run {:?}: {}", command, err));
+    assert!(status.success(), "{:?} failed with {}", command, status);
+}
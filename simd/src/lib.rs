@@ -0,0 +1,99 @@
+//! # Rotinas numéricas com SSE
+//!
+//! Crate compilado com o target `x86_64-rust_os-sse.json` (SSE2 e a ABI
+//! padrão de floats), separado do kernel, que é `+soft-float`. O
+//! `build.rs` do kernel gera uma biblioteca estática com ele e só deixa
+//! visíveis os símbolos `simd_*`; o kernel chama as rotinas por
+//! `src/simd.rs`, sempre dentro de `fpu::kernel_fpu_begin`.
+//!
+//! ## Interface
+//!
+//! As duas ABIs passam `f64` em lugares diferentes (XMM aqui, registrador
+//! inteiro no kernel), então a interface só usa inteiros e ponteiros:
+//! escalares vão como bits (`f64::to_bits`) em `u64`.
+//!
+//! As reduções recebem o acumulador inicial, para o kernel dividir um
+//! vetor grande em pedaços sem somar resultados parciais em soft-float.
+
+#![no_std]
+
+use core::{
+    arch::x86_64::{__m128d, _mm_add_pd, _mm_loadu_pd, _mm_mul_pd, _mm_set_sd, _mm_storeu_pd},
+    slice,
+};
+
+unsafe extern "C" {
+    /// Definida pelo kernel: entra no panic handler dele.
+    fn simd_panic() -> !;
+}
+
+/// Soma as duas metades de `lanes` e o elemento que sobrou, se houver.
+unsafe fn reduce(lanes: __m128d, rest: Option<f64>) -> f64 {
+    let mut halves = [0.0; 2];
+    _mm_storeu_pd(halves.as_mut_ptr(), lanes);
+    let total = halves[0] + halves[1];
+    match rest {
+        Some(value) => total + value,
+        None => total,
+    }
+}
+
+/// `acc + Σ a[i] * b[i]`, dois elementos por vez (`mulpd`/`addpd`).
+///
+/// # Safety
+///
+/// `a` e `b` precisam apontar para `len` valores válidos.
+#[no_mangle]
+pub unsafe extern "C" fn simd_dot(acc: u64, a: *const f64, b: *const f64, len: usize) -> u64 {
+    let (a, b) = (slice::from_raw_parts(a, len), slice::from_raw_parts(b, len));
+    let mut lanes = _mm_set_sd(f64::from_bits(acc));
+    let (pairs_a, pairs_b) = (a.chunks_exact(2), b.chunks_exact(2));
+    let rest = match (pairs_a.remainder(), pairs_b.remainder()) {
+        ([x], [y]) => Some(x * y),
+        _ => None,
+    };
+    for (x, y) in pairs_a.zip(pairs_b) {
+        let product = _mm_mul_pd(_mm_loadu_pd(x.as_ptr()), _mm_loadu_pd(y.as_ptr()));
+        lanes = _mm_add_pd(lanes, product);
+    }
+    reduce(lanes, rest).to_bits()
+}
+
+/// `acc + Σ values[i]`, dois elementos por vez (`addpd`).
+///
+/// # Safety
+///
+/// `values` precisa apontar para `len` valores válidos.
+#[no_mangle]
+pub unsafe extern "C" fn simd_sum(acc: u64, values: *const f64, len: usize) -> u64 {
+    let values = slice::from_raw_parts(values, len);
+    let mut lanes = _mm_set_sd(f64::from_bits(acc));
+    let pairs = values.chunks_exact(2);
+    let rest = pairs.remainder().first().copied();
+    for pair in pairs {
+        lanes = _mm_add_pd(lanes, _mm_loadu_pd(pair.as_ptr()));
+    }
+    reduce(lanes, rest).to_bits()
+}
+
+/// `y[i] += alpha * x[i]` (o compilador vetoriza o laço).
+///
+/// # Safety
+///
+/// `x` e `y` precisam apontar para `len` valores válidos, sem sobreposição.
+#[no_mangle]
+pub unsafe extern "C" fn simd_axpy(alpha: u64, x: *const f64, y: *mut f64, len: usize) {
+    let alpha = f64::from_bits(alpha);
+    let (x, y) = (
+        slice::from_raw_parts(x, len),
+        slice::from_raw_parts_mut(y, len),
+    );
+    for (y, x) in y.iter_mut().zip(x) {
+        *y += alpha * x;
+    }
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    unsafe { simd_panic() }
+}
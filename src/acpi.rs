@@ -0,0 +1,1030 @@
+//! # Tabelas ACPI
+//!
+//! ## Onde ficam?
+//!
+//! O firmware deixa na memória um conjunto de tabelas descrevendo o
+//! hardware. O ponto de partida é o **RSDP** (Root System Description
+//! Pointer), procurado por assinatura nos primeiros KB da EBDA ou na área
+//! de BIOS (`0xE0000`-`0xFFFFF`):
+//!
+//! ```text
+//! RSDP ("RSD PTR ") ─→ RSDT (ponteiros de 32 bits)
+//!                  └─→ XSDT (ponteiros de 64 bits, ACPI 2.0+)
+//!                          │
+//!                          ├─→ "APIC" (MADT): CPUs e controladores de IRQ
+//!                          ├─→ "FACP" (FADT): gerenciamento de energia, DSDT
+//!                          ├─→ "HPET": endereço do HPET
+//!                          ├─→ "MCFG": configuração PCIe por memória
+//!                          └─→ ...
+//! ```
+//!
+//! Toda tabela começa com o mesmo cabeçalho de 36 bytes:
+//!
+//! ```text
+//! 0   assinatura (4)   4   tamanho (4)     8   revisão   9   checksum
+//! 10  OEM ID (6)       16  OEM table ID (8)   24  OEM revision (4)
+//! 28  creator ID (4)   32  creator revision (4)
+//! ```
+//!
+//! ## Validação
+//!
+//! O firmware pode deixar tabelas quebradas. Antes de interpretar uma
+//! tabela, o kernel confere:
+//!
+//! - **Checksum**: a soma de todos os bytes da tabela (e do RSDP) deve ser
+//!   0 módulo 256.
+//! - **Tamanho**: pelo menos o mínimo do tipo e no máximo
+//!   `MAX_TABLE_LENGTH`; entradas do MADT e do MCFG precisam caber na
+//!   tabela.
+//!
+//! Problemas viram `AcpiError`, nunca panics: `smp` e `hpet` podem cair
+//! para um modo degradado.
+//!
+//! ## Uso
+//!
+//! Cada tabela tem uma função que a localiza e interpreta (`madt()`,
+//! `fadt()`, `hpet()`, `mcfg()`); `parse()` junta todas e implementa
+//! `Display` para o resumo impresso no boot.
+//!
+//! A interpretação em si (`parse_rsdp`, `parse_sdt`, `parse_madt`, ...)
+//! recebe os bytes da tabela (`&[u8]`); as funções acima só localizam
+//! esses bytes na memória física. Assim a validação pode ser testada com
+//! buffers corrompidos (ver `tests/acpi.rs`).
+//!
+//! As tabelas são lidas pelo offset mapping da memória física
+//! (`memory::phys_to_virt`), então requerem `memory::init`, e as listas
+//! usam o heap.
+//!
+//! ## Referências
+//!
+//! - [RSDP](https://wiki.osdev.org/RSDP) / [MADT](https://wiki.osdev.org/MADT) - OSDev Wiki
+//! - [FADT](https://wiki.osdev.org/FADT) / [PCIe (MCFG)](https://wiki.osdev.org/PCI_Express) - OSDev Wiki
+//! - [HPET](https://wiki.osdev.org/HPET) - OSDev Wiki
+//! - ACPI Specification 6.5, Capítulo 5
+
+use crate::memory;
+use alloc::vec::Vec;
+use core::{fmt, mem, slice, str};
+use x86_64::PhysAddr;
+
+/// Tamanho do cabeçalho comum das tabelas (SDT).
+const SDT_HEADER_SIZE: u32 = 36;
+/// Maior tamanho aceito para uma tabela (protege contra tamanhos lixo).
+pub const MAX_TABLE_LENGTH: u32 = 1 << 20;
+
+/// Tamanhos do RSDP: ACPI 1.0 e 2.0+.
+const RSDP_V1_LENGTH: usize = 20;
+const RSDP_V2_LENGTH: usize = 36;
+
+/// Tamanhos mínimos das tabelas interpretadas.
+const MADT_MIN_LENGTH: u32 = SDT_HEADER_SIZE + 8;
+const FADT_MIN_LENGTH: u32 = 116;
+const HPET_MIN_LENGTH: u32 = SDT_HEADER_SIZE + 20;
+const MCFG_MIN_LENGTH: u32 = SDT_HEADER_SIZE + 8;
+
+/// Tipos de entrada do MADT interpretados.
+const MADT_LOCAL_APIC: u8 = 0;
+const MADT_IO_APIC: u8 = 1;
+const MADT_INTERRUPT_OVERRIDE: u8 = 2;
+const MADT_LOCAL_APIC_NMI: u8 = 4;
+const MADT_LOCAL_APIC_ADDRESS: u8 = 5;
+const MADT_LOCAL_X2APIC: u8 = 9;
+/// Flags das entradas de CPU: habilitada / pode ser habilitada (MADT
+/// revisão 5 em diante).
+const CPU_ENABLED: u32 = 1 << 0;
+const CPU_ONLINE_CAPABLE: u32 = 1 << 1;
+/// Flag do MADT: há PICs 8259 que precisam ser mascarados.
+const MADT_PCAT_COMPAT: u32 = 1 << 0;
+
+/// Flag do FADT: `reset_register` é válido.
+const FADT_RESET_REG_SUP: u32 = 1 << 10;
+/// Bits de `boot_architecture` (IA-PC).
+const BOOT_ARCH_LEGACY_DEVICES: u16 = 1 << 0;
+const BOOT_ARCH_8042: u16 = 1 << 1;
+
+/// Espaço de endereçamento "memória" de uma Generic Address Structure.
+pub const ADDRESS_SPACE_MEMORY: u8 = 0;
+/// Espaço de endereçamento "I/O" de uma Generic Address Structure.
+pub const ADDRESS_SPACE_IO: u8 = 1;
+
+/// Assinatura de 4 bytes de uma tabela (ex: `APIC`).
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Signature(pub [u8; 4]);
+
+impl Signature {
+    pub const RSDT: Signature = Signature(*b"RSDT");
+    pub const XSDT: Signature = Signature(*b"XSDT");
+    pub const MADT: Signature = Signature(*b"APIC");
+    pub const FADT: Signature = Signature(*b"FACP");
+    pub const HPET: Signature = Signature(*b"HPET");
+    pub const MCFG: Signature = Signature(*b"MCFG");
+
+    pub fn as_str(&self) -> &str {
+        str::from_utf8(&self.0).unwrap_or("????")
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\"{}\"", self.as_str())
+    }
+}
+
+/// Erros ao localizar ou interpretar as tabelas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcpiError {
+    /// RSDP não encontrado na EBDA nem na área de BIOS.
+    NoRsdp,
+    /// RSDP encontrado, mas com checksum inválido.
+    BadRsdpChecksum,
+    /// A tabela no endereço não tem a assinatura esperada.
+    BadSignature {
+        expected: Signature,
+        found: Signature,
+    },
+    /// A soma dos bytes da tabela não é 0.
+    BadChecksum(Signature),
+    /// Tamanho declarado menor que o mínimo ou maior que `MAX_TABLE_LENGTH`.
+    BadLength { signature: Signature, length: u32 },
+    /// Entrada que não cabe na tabela (offset a partir do início da tabela).
+    BadEntry { signature: Signature, offset: u32 },
+    /// Endereço físico inválido (acima de 52 bits) lido da tabela, ou uma
+    /// tabela fora da memória física mapeada.
+    BadAddress { signature: Signature, address: u64 },
+    /// Endereço num espaço não suportado (ex: HPET em portas de I/O).
+    UnsupportedAddressSpace { signature: Signature, space: u8 },
+    /// Tabela não listada na RSDT/XSDT.
+    NotFound(Signature),
+}
+
+impl fmt::Display for AcpiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AcpiError::NoRsdp => f.write_str("RSDP not found"),
+            AcpiError::BadRsdpChecksum => f.write_str("RSDP has a bad checksum"),
+            AcpiError::BadSignature { expected, found } => {
+                write!(f, "expected {}, found {}", expected, found)
+            }
+            AcpiError::BadChecksum(signature) => write!(f, "{} has a bad checksum", signature),
+            AcpiError::BadLength { signature, length } => {
+                write!(f, "{} has a bad length ({} bytes)", signature, length)
+            }
+            AcpiError::BadEntry { signature, offset } => {
+                write!(
+                    f,
+                    "{} has a malformed entry at offset {}",
+                    signature, offset
+                )
+            }
+            AcpiError::BadAddress { signature, address } => {
+                write!(f, "{} points to invalid address {:#x}", signature, address)
+            }
+            AcpiError::UnsupportedAddressSpace { signature, space } => {
+                write!(f, "{} uses unsupported address space {}", signature, space)
+            }
+            AcpiError::NotFound(signature) => write!(f, "{} not present", signature),
+        }
+    }
+}
+
+/// Lê um campo de `bytes` no `offset` (sem exigir alinhamento).
+///
+/// # Panics
+/// Se o campo não couber em `bytes`; os parsers conferem os tamanhos antes.
+fn field<T: Copy>(bytes: &[u8], offset: usize) -> T {
+    let bytes = &bytes[offset..offset + mem::size_of::<T>()];
+    unsafe { bytes.as_ptr().cast::<T>().read_unaligned() }
+}
+
+/// Bytes da memória física em `[addr, addr + length)`, ou `None` se a
+/// faixa não estiver no offset mapping (ver `memory::is_phys_mapped`).
+fn phys_bytes(addr: u64, length: usize) -> Option<&'static [u8]> {
+    let start = PhysAddr::try_new(addr).ok()?;
+    if !memory::is_phys_mapped(start, length as u64) {
+        return None;
+    }
+    let ptr = memory::phys_to_virt(start).as_ptr::<u8>();
+    Some(unsafe { slice::from_raw_parts(ptr, length) })
+}
+
+/// Converte um endereço lido da tabela `signature`.
+///
+/// Só confere o limite de 52 bits: esses endereços (DSDT, MMIO do HPET e
+/// do PCIe) não são lidos aqui e podem ficar fora da memória mapeada.
+fn phys_addr(signature: Signature, address: u64) -> Result<PhysAddr, AcpiError> {
+    PhysAddr::try_new(address).map_err(|_| AcpiError::BadAddress { signature, address })
+}
+
+/// Bytes da tabela em `addr`, com o tamanho declarado no cabeçalho
+/// (limitado a `MAX_TABLE_LENGTH`). Quem chama valida com `parse_sdt`.
+///
+/// `signature` é a tabela de onde o endereço veio, para o erro.
+fn table_bytes(signature: Signature, addr: u64) -> Result<&'static [u8], AcpiError> {
+    let bad_address = AcpiError::BadAddress {
+        signature,
+        address: addr,
+    };
+    let header = phys_bytes(addr, SDT_HEADER_SIZE as usize).ok_or(bad_address)?;
+    let length = field::<u32>(header, 4).clamp(SDT_HEADER_SIZE, MAX_TABLE_LENGTH);
+    phys_bytes(addr, length as usize).ok_or(bad_address)
+}
+
+/// Soma dos bytes módulo 256 (0 = checksum válido).
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |sum, &byte| sum.wrapping_add(byte))
+}
+
+/// Endereço em um espaço de endereçamento (Generic Address Structure).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GenericAddress {
+    /// `ADDRESS_SPACE_MEMORY`, `ADDRESS_SPACE_IO`, ...
+    pub address_space: u8,
+    pub bit_width: u8,
+    pub bit_offset: u8,
+    pub access_size: u8,
+    pub address: u64,
+}
+
+impl GenericAddress {
+    /// Lê a estrutura de 12 bytes em `offset`.
+    fn parse(bytes: &[u8], offset: usize) -> GenericAddress {
+        GenericAddress {
+            address_space: field(bytes, offset),
+            bit_width: field(bytes, offset + 1),
+            bit_offset: field(bytes, offset + 2),
+            access_size: field(bytes, offset + 3),
+            address: field(bytes, offset + 4),
+        }
+    }
+}
+
+/// O Root System Description Pointer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rsdp {
+    /// Onde o RSDP foi encontrado.
+    pub address: PhysAddr,
+    /// 0 para ACPI 1.0, 2 para ACPI 2.0+.
+    pub revision: u8,
+    oem_id: [u8; 6],
+    pub rsdt_address: PhysAddr,
+    /// Só existe a partir do ACPI 2.0; tem preferência sobre a RSDT.
+    pub xsdt_address: Option<PhysAddr>,
+}
+
+impl Rsdp {
+    pub fn oem_id(&self) -> &str {
+        str::from_utf8(&self.oem_id).unwrap_or("").trim_end()
+    }
+
+    /// Tabela raiz usada: XSDT se existir, senão RSDT.
+    pub fn root(&self) -> (Signature, PhysAddr) {
+        match self.xsdt_address {
+            Some(xsdt) => (Signature::XSDT, xsdt),
+            None => (Signature::RSDT, self.rsdt_address),
+        }
+    }
+}
+
+/// Interpreta um RSDP candidato no início de `bytes`, encontrado em
+/// `address`.
+///
+/// Retorna `Err(NoRsdp)` se não houver assinatura ou bytes suficientes
+/// para a revisão (20 ou 36), e `Err(BadRsdpChecksum)` se algum dos
+/// checksums falhar.
+pub fn parse_rsdp(address: PhysAddr, bytes: &[u8]) -> Result<Rsdp, AcpiError> {
+    if bytes.len() < RSDP_V1_LENGTH || bytes[..8] != *b"RSD PTR " {
+        return Err(AcpiError::NoRsdp);
+    }
+    let revision: u8 = field(bytes, 15);
+    let length = if revision >= 2 {
+        RSDP_V2_LENGTH
+    } else {
+        RSDP_V1_LENGTH
+    };
+    if bytes.len() < length {
+        return Err(AcpiError::NoRsdp);
+    }
+    if checksum(&bytes[..RSDP_V1_LENGTH]) != 0 || checksum(&bytes[..length]) != 0 {
+        return Err(AcpiError::BadRsdpChecksum);
+    }
+
+    let xsdt = if revision >= 2 {
+        field::<u64>(bytes, 24)
+    } else {
+        0
+    };
+    let xsdt_address = match xsdt {
+        0 => None,
+        xsdt => Some(phys_addr(Signature::XSDT, xsdt)?),
+    };
+    Ok(Rsdp {
+        address,
+        revision,
+        oem_id: field(bytes, 9),
+        rsdt_address: PhysAddr::new(u64::from(field::<u32>(bytes, 16))),
+        xsdt_address,
+    })
+}
+
+/// Procura um RSDP válido em `[start, end)`, em passos de 16 bytes.
+///
+/// Retorna `Err(BadRsdpChecksum)` se só achou assinaturas com checksum
+/// inválido.
+fn scan_rsdp(start: u64, end: u64) -> Result<Rsdp, AcpiError> {
+    let size = (end - start) as usize;
+    // Espaço para um RSDP 2.0 começando no fim da área
+    let area = phys_bytes(start, size + RSDP_V2_LENGTH).ok_or(AcpiError::NoRsdp)?;
+    let mut result = Err(AcpiError::NoRsdp);
+    for offset in (0..size).step_by(16) {
+        match parse_rsdp(PhysAddr::new(start + offset as u64), &area[offset..]) {
+            Err(AcpiError::NoRsdp) => {}
+            Err(AcpiError::BadRsdpChecksum) => result = Err(AcpiError::BadRsdpChecksum),
+            found => return found,
+        }
+    }
+    result
+}
+
+/// Localiza e valida o RSDP na EBDA ou na área de BIOS.
+pub fn rsdp() -> Result<Rsdp, AcpiError> {
+    // O segmento da EBDA fica na BDA, em 0x40E
+    let bda = phys_bytes(0x40E, 2).ok_or(AcpiError::NoRsdp)?;
+    let ebda = u64::from(field::<u16>(bda, 0)) << 4;
+    let in_ebda = if ebda != 0 {
+        scan_rsdp(ebda, ebda + 1024)
+    } else {
+        Err(AcpiError::NoRsdp)
+    };
+    match in_ebda {
+        Ok(rsdp) => Ok(rsdp),
+        Err(ebda_err) => scan_rsdp(0xE0000, 0x100000).map_err(|bios_err| {
+            if bios_err == AcpiError::NoRsdp {
+                ebda_err
+            } else {
+                bios_err
+            }
+        }),
+    }
+}
+
+/// Cabeçalho comum das tabelas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SdtHeader {
+    pub signature: Signature,
+    pub length: u32,
+    pub revision: u8,
+    oem_id: [u8; 6],
+    oem_table_id: [u8; 8],
+    pub oem_revision: u32,
+    pub creator_id: Signature,
+    pub creator_revision: u32,
+}
+
+impl SdtHeader {
+    /// Lê o cabeçalho do início de `bytes` (pelo menos `SDT_HEADER_SIZE` bytes).
+    fn parse(bytes: &[u8]) -> SdtHeader {
+        SdtHeader {
+            signature: Signature(field(bytes, 0)),
+            length: field(bytes, 4),
+            revision: field(bytes, 8),
+            oem_id: field(bytes, 10),
+            oem_table_id: field(bytes, 16),
+            oem_revision: field(bytes, 24),
+            creator_id: Signature(field(bytes, 28)),
+            creator_revision: field(bytes, 32),
+        }
+    }
+
+    /// Cabeçalho de uma tabela que não pôde ser lida: assinatura `????`,
+    /// o resto zerado.
+    fn unknown() -> SdtHeader {
+        SdtHeader {
+            signature: Signature(*b"????"),
+            length: 0,
+            revision: 0,
+            oem_id: [0; 6],
+            oem_table_id: [0; 8],
+            oem_revision: 0,
+            creator_id: Signature([0; 4]),
+            creator_revision: 0,
+        }
+    }
+
+    pub fn oem_id(&self) -> &str {
+        str::from_utf8(&self.oem_id).unwrap_or("").trim_end()
+    }
+
+    pub fn oem_table_id(&self) -> &str {
+        str::from_utf8(&self.oem_table_id).unwrap_or("").trim_end()
+    }
+
+    /// Confere o tamanho (entre `min_length` e `MAX_TABLE_LENGTH`, e dentro
+    /// de `bytes`) e o checksum da tabela que começa em `bytes`.
+    fn validate(&self, bytes: &[u8], min_length: u32) -> Result<(), AcpiError> {
+        if self.length < min_length
+            || self.length > MAX_TABLE_LENGTH
+            || self.length as usize > bytes.len()
+        {
+            return Err(AcpiError::BadLength {
+                signature: self.signature,
+                length: self.length,
+            });
+        }
+        if checksum(&bytes[..self.length as usize]) != 0 {
+            return Err(AcpiError::BadChecksum(self.signature));
+        }
+        Ok(())
+    }
+}
+
+/// Valida a tabela `signature` que começa em `bytes` (assinatura, tamanho
+/// de pelo menos `min_length` e checksum) e retorna o cabeçalho.
+pub fn parse_sdt(
+    bytes: &[u8],
+    signature: Signature,
+    min_length: u32,
+) -> Result<SdtHeader, AcpiError> {
+    if bytes.len() < SDT_HEADER_SIZE as usize {
+        return Err(AcpiError::BadLength {
+            signature,
+            length: bytes.len() as u32,
+        });
+    }
+    let header = SdtHeader::parse(bytes);
+    if header.signature != signature {
+        return Err(AcpiError::BadSignature {
+            expected: signature,
+            found: header.signature,
+        });
+    }
+    header.validate(bytes, min_length)?;
+    Ok(header)
+}
+
+/// Uma tabela listada na RSDT/XSDT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Table {
+    pub address: PhysAddr,
+    pub header: SdtHeader,
+    /// Resultado da validação de tamanho e checksum, ou `BadAddress` se a
+    /// tabela está fora da memória mapeada (com `SdtHeader::unknown`).
+    pub status: Result<(), AcpiError>,
+}
+
+impl Table {
+    /// Lê o cabeçalho da tabela em `addr`, listada na tabela raiz `root`.
+    fn read(root: Signature, addr: u64) -> Table {
+        // Endereços acima de 52 bits são truncados aqui; o exato fica no erro
+        let address = PhysAddr::new_truncate(addr);
+        match table_bytes(root, addr) {
+            Ok(bytes) => {
+                let header = SdtHeader::parse(bytes);
+                Table {
+                    address,
+                    header,
+                    status: header.validate(bytes, SDT_HEADER_SIZE),
+                }
+            }
+            Err(err) => Table {
+                address,
+                header: SdtHeader::unknown(),
+                status: Err(err),
+            },
+        }
+    }
+}
+
+/// Lê e valida a tabela raiz (RSDT/XSDT) e lista as tabelas apontadas.
+///
+/// Tabelas ilegíveis ou com tamanho ou checksum inválidos aparecem com
+/// `status` de erro; só um problema no RSDP ou na tabela raiz faz a
+/// função falhar.
+pub fn tables() -> Result<Vec<Table>, AcpiError> {
+    tables_from(&rsdp()?)
+}
+
+/// `tables()` a partir de um RSDP já localizado.
+fn tables_from(rsdp: &Rsdp) -> Result<Vec<Table>, AcpiError> {
+    let (signature, root) = rsdp.root();
+    let bytes = table_bytes(signature, root.as_u64())?;
+    let header = parse_sdt(bytes, signature, SDT_HEADER_SIZE)?;
+
+    let entry_size = if signature == Signature::XSDT { 8 } else { 4 };
+    let tables = bytes[SDT_HEADER_SIZE as usize..header.length as usize]
+        .chunks_exact(entry_size)
+        .map(|entry| {
+            if entry_size == 8 {
+                field::<u64>(entry, 0)
+            } else {
+                u64::from(field::<u32>(entry, 0))
+            }
+        })
+        .filter(|&addr| addr != 0)
+        .map(|addr| Table::read(signature, addr))
+        .collect();
+    Ok(tables)
+}
+
+/// Procura uma tabela pela assinatura em `tables` e retorna seus bytes
+/// (ainda não validados).
+fn find(tables: &[Table], signature: Signature) -> Result<(PhysAddr, &'static [u8]), AcpiError> {
+    let table = tables
+        .iter()
+        .find(|table| table.header.signature == signature)
+        .ok_or(AcpiError::NotFound(signature))?;
+    let bytes = table_bytes(signature, table.address.as_u64())?;
+    Ok((table.address, bytes))
+}
+
+/// Procura uma tabela válida pela assinatura (ex: `b"APIC"`).
+pub fn find_table(signature: &[u8; 4]) -> Result<PhysAddr, AcpiError> {
+    let signature = Signature(*signature);
+    let (address, bytes) = find(&tables()?, signature)?;
+    parse_sdt(bytes, signature, SDT_HEADER_SIZE)?;
+    Ok(address)
+}
+
+/// Uma CPU listada no MADT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalApic {
+    /// ID de processador ACPI.
+    pub processor_id: u32,
+    /// ID do Local APIC (usado para enviar IPIs).
+    pub apic_id: u32,
+}
+
+/// Um I/O APIC listado no MADT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoApic {
+    pub id: u8,
+    pub address: PhysAddr,
+    /// Primeira Global System Interrupt atendida por este I/O APIC.
+    pub gsi_base: u32,
+}
+
+/// IRQ legada ligada a uma GSI diferente do seu número (ex: IRQ 0 → GSI 2).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterruptOverride {
+    pub bus: u8,
+    /// IRQ ISA de origem.
+    pub source: u8,
+    pub gsi: u32,
+    /// Polaridade (bits 0-1) e modo de disparo (bits 2-3).
+    pub flags: u16,
+}
+
+/// Entrada LINT de um Local APIC ligada à NMI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalApicNmi {
+    /// ID de processador ACPI (`0xFF` = todos).
+    pub processor_id: u8,
+    pub flags: u16,
+    /// Pino LINT (0 ou 1).
+    pub lint: u8,
+}
+
+/// Conteúdo do MADT ("APIC").
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Madt {
+    /// Endereço físico dos Local APICs (já com o override de 64 bits).
+    pub local_apic_address: PhysAddr,
+    /// Há PICs 8259 no sistema.
+    pub pcat_compatible: bool,
+    /// CPUs habilitadas ou que podem ser ligadas.
+    pub local_apics: Vec<LocalApic>,
+    pub io_apics: Vec<IoApic>,
+    pub interrupt_overrides: Vec<InterruptOverride>,
+    pub local_apic_nmis: Vec<LocalApicNmi>,
+}
+
+/// Localiza e interpreta o MADT.
+pub fn madt() -> Result<Madt, AcpiError> {
+    parse_madt(find(&tables()?, Signature::MADT)?.1)
+}
+
+/// Valida e interpreta o MADT que começa em `bytes`.
+pub fn parse_madt(bytes: &[u8]) -> Result<Madt, AcpiError> {
+    let header = parse_sdt(bytes, Signature::MADT, MADT_MIN_LENGTH)?;
+    let table = &bytes[..header.length as usize];
+    let mut madt = Madt {
+        local_apic_address: PhysAddr::new(u64::from(field::<u32>(table, 36))),
+        pcat_compatible: field::<u32>(table, 40) & MADT_PCAT_COMPAT != 0,
+        local_apics: Vec::new(),
+        io_apics: Vec::new(),
+        interrupt_overrides: Vec::new(),
+        local_apic_nmis: Vec::new(),
+    };
+
+    let mut offset = MADT_MIN_LENGTH as usize;
+    while offset < table.len() {
+        let bad_entry = AcpiError::BadEntry {
+            signature: Signature::MADT,
+            offset: offset as u32,
+        };
+        if offset + 2 > table.len() {
+            return Err(bad_entry);
+        }
+        let kind: u8 = field(table, offset);
+        let length = usize::from(field::<u8>(table, offset + 1));
+        let min_length = match kind {
+            MADT_LOCAL_APIC => 8,
+            MADT_IO_APIC => 12,
+            MADT_INTERRUPT_OVERRIDE => 10,
+            MADT_LOCAL_APIC_NMI => 6,
+            MADT_LOCAL_APIC_ADDRESS => 12,
+            MADT_LOCAL_X2APIC => 16,
+            _ => 2,
+        };
+        if length < min_length || offset + length > table.len() {
+            return Err(bad_entry);
+        }
+
+        let entry = &table[offset..offset + length];
+        match kind {
+            MADT_LOCAL_APIC | MADT_LOCAL_X2APIC => {
+                let (cpu, flags) = if kind == MADT_LOCAL_APIC {
+                    let cpu = LocalApic {
+                        processor_id: u32::from(field::<u8>(entry, 2)),
+                        apic_id: u32::from(field::<u8>(entry, 3)),
+                    };
+                    (cpu, field::<u32>(entry, 4))
+                } else {
+                    let cpu = LocalApic {
+                        processor_id: field(entry, 12),
+                        apic_id: field(entry, 4),
+                    };
+                    (cpu, field::<u32>(entry, 8))
+                };
+                // "Online capable" só existe a partir da revisão 5
+                let online_capable = header.revision >= 5 && flags & CPU_ONLINE_CAPABLE != 0;
+                if flags & CPU_ENABLED != 0 || online_capable {
+                    madt.local_apics.push(cpu);
+                }
+            }
+            MADT_IO_APIC => madt.io_apics.push(IoApic {
+                id: field(entry, 2),
+                address: PhysAddr::new(u64::from(field::<u32>(entry, 4))),
+                gsi_base: field(entry, 8),
+            }),
+            MADT_INTERRUPT_OVERRIDE => madt.interrupt_overrides.push(InterruptOverride {
+                bus: field(entry, 2),
+                source: field(entry, 3),
+                gsi: field(entry, 4),
+                flags: field(entry, 8),
+            }),
+            MADT_LOCAL_APIC_NMI => madt.local_apic_nmis.push(LocalApicNmi {
+                processor_id: field(entry, 2),
+                flags: field(entry, 3),
+                lint: field(entry, 5),
+            }),
+            MADT_LOCAL_APIC_ADDRESS => {
+                madt.local_apic_address = phys_addr(Signature::MADT, field(entry, 4))?;
+            }
+            _ => {}
+        }
+        offset += length;
+    }
+    Ok(madt)
+}
+
+/// Campos usados do FADT ("FACP").
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fadt {
+    pub revision: u8,
+    /// DSDT (código AML), preferindo o endereço de 64 bits.
+    pub dsdt: PhysAddr,
+    /// IRQ legada da SCI (System Control Interrupt).
+    pub sci_interrupt: u16,
+    /// Porta para ligar/desligar o modo ACPI (0 = já em modo ACPI).
+    pub smi_command: u32,
+    pub acpi_enable: u8,
+    pub acpi_disable: u8,
+    /// Portas dos registradores de controle PM1 (usados no desligamento).
+    pub pm1a_control_block: u32,
+    pub pm1b_control_block: u32,
+    /// Porta do timer PM de 3,579545 MHz (0 = ausente).
+    pub pm_timer_block: u32,
+    /// Registrador da CMOS com o século (0 = ausente).
+    pub century_register: u8,
+    pub boot_architecture: u16,
+    pub flags: u32,
+    /// Registrador de reset e o valor a escrever nele.
+    pub reset_register: Option<(GenericAddress, u8)>,
+}
+
+impl Fadt {
+    /// Há dispositivos legados (ISA, portas fixas) na placa.
+    pub fn has_legacy_devices(&self) -> bool {
+        self.boot_architecture & BOOT_ARCH_LEGACY_DEVICES != 0
+    }
+
+    /// Há um controlador de teclado 8042.
+    pub fn has_8042(&self) -> bool {
+        self.boot_architecture & BOOT_ARCH_8042 != 0
+    }
+}
+
+/// Localiza e interpreta o FADT.
+pub fn fadt() -> Result<Fadt, AcpiError> {
+    parse_fadt(find(&tables()?, Signature::FADT)?.1)
+}
+
+/// Valida e interpreta o FADT que começa em `bytes`.
+pub fn parse_fadt(bytes: &[u8]) -> Result<Fadt, AcpiError> {
+    let header = parse_sdt(bytes, Signature::FADT, FADT_MIN_LENGTH)?;
+    let length = header.length;
+    let table = &bytes[..length as usize];
+    let flags = field::<u32>(table, 112);
+
+    // Campos de versões posteriores só existem se a tabela for longa o bastante
+    let x_dsdt = if length >= 148 {
+        field::<u64>(table, 140)
+    } else {
+        0
+    };
+    let dsdt = if x_dsdt != 0 {
+        x_dsdt
+    } else {
+        u64::from(field::<u32>(table, 40))
+    };
+    let reset_register = (length >= 129 && flags & FADT_RESET_REG_SUP != 0)
+        .then(|| (GenericAddress::parse(table, 116), field::<u8>(table, 128)));
+
+    Ok(Fadt {
+        revision: header.revision,
+        dsdt: phys_addr(Signature::FADT, dsdt)?,
+        sci_interrupt: field(table, 46),
+        smi_command: field(table, 48),
+        acpi_enable: field(table, 52),
+        acpi_disable: field(table, 53),
+        pm1a_control_block: field(table, 64),
+        pm1b_control_block: field(table, 68),
+        pm_timer_block: field(table, 76),
+        century_register: field(table, 108),
+        boot_architecture: if header.revision >= 2 {
+            field(table, 109)
+        } else {
+            0
+        },
+        flags,
+        reset_register,
+    })
+}
+
+/// Bloco de timers HPET descrito na tabela "HPET".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HpetTable {
+    /// Endereço físico dos registradores (MMIO).
+    pub base_address: PhysAddr,
+    /// Número do bloco (0 para o primeiro HPET).
+    pub hpet_number: u8,
+    /// Comparadores do bloco.
+    pub comparators: u8,
+    /// Menor período (em ticks do contador) suportado no modo periódico.
+    pub minimum_tick: u16,
+}
+
+/// Localiza e interpreta a tabela do HPET (só endereços de memória).
+pub fn hpet() -> Result<HpetTable, AcpiError> {
+    parse_hpet(find(&tables()?, Signature::HPET)?.1)
+}
+
+/// Valida e interpreta a tabela do HPET que começa em `bytes`.
+pub fn parse_hpet(bytes: &[u8]) -> Result<HpetTable, AcpiError> {
+    parse_sdt(bytes, Signature::HPET, HPET_MIN_LENGTH)?;
+    // Após o cabeçalho: ID do bloco (u32) e o endereço base (GAS, 12 bytes)
+    let block_id = field::<u32>(bytes, 36);
+    let address = GenericAddress::parse(bytes, 40);
+    if address.address_space != ADDRESS_SPACE_MEMORY {
+        return Err(AcpiError::UnsupportedAddressSpace {
+            signature: Signature::HPET,
+            space: address.address_space,
+        });
+    }
+    Ok(HpetTable {
+        base_address: phys_addr(Signature::HPET, address.address)?,
+        hpet_number: field(bytes, 52),
+        comparators: ((block_id >> 8) & 0x1F) as u8 + 1,
+        minimum_tick: field(bytes, 53),
+    })
+}
+
+/// Região de configuração PCIe (ECAM) de um segmento.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct McfgEntry {
+    /// Endereço do espaço de configuração do barramento 0 do segmento.
+    pub base_address: PhysAddr,
+    pub segment_group: u16,
+    pub start_bus: u8,
+    pub end_bus: u8,
+}
+
+/// Localiza e interpreta o MCFG (ausente em máquinas só com PCI legado,
+/// como o `pc` padrão do QEMU).
+pub fn mcfg() -> Result<Vec<McfgEntry>, AcpiError> {
+    parse_mcfg(find(&tables()?, Signature::MCFG)?.1)
+}
+
+/// Valida e interpreta o MCFG que começa em `bytes`.
+pub fn parse_mcfg(bytes: &[u8]) -> Result<Vec<McfgEntry>, AcpiError> {
+    let header = parse_sdt(bytes, Signature::MCFG, MCFG_MIN_LENGTH)?;
+    let entries = &bytes[MCFG_MIN_LENGTH as usize..header.length as usize];
+    if !entries.len().is_multiple_of(16) {
+        return Err(AcpiError::BadEntry {
+            signature: Signature::MCFG,
+            offset: MCFG_MIN_LENGTH + (entries.len() / 16 * 16) as u32,
+        });
+    }
+    entries
+        .chunks_exact(16)
+        .map(|entry| {
+            Ok(McfgEntry {
+                base_address: phys_addr(Signature::MCFG, field(entry, 0))?,
+                segment_group: field(entry, 8),
+                start_bus: field(entry, 10),
+                end_bus: field(entry, 11),
+            })
+        })
+        .collect()
+}
+
+/// Todas as tabelas interpretadas, para o resumo do boot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AcpiTables {
+    pub rsdp: Rsdp,
+    pub tables: Vec<Table>,
+    pub madt: Result<Madt, AcpiError>,
+    pub fadt: Result<Fadt, AcpiError>,
+    pub hpet: Result<HpetTable, AcpiError>,
+    pub mcfg: Result<Vec<McfgEntry>, AcpiError>,
+}
+
+/// Lê o RSDP, a tabela raiz e todas as tabelas conhecidas, percorrendo a
+/// lista uma única vez.
+///
+/// Só falha se o RSDP ou a tabela raiz forem inválidos; erros de uma
+/// tabela ficam no campo correspondente.
+pub fn parse() -> Result<AcpiTables, AcpiError> {
+    let rsdp = rsdp()?;
+    let tables = tables_from(&rsdp)?;
+    let bytes = |signature| find(&tables, signature).map(|(_, bytes)| bytes);
+    Ok(AcpiTables {
+        madt: bytes(Signature::MADT).and_then(parse_madt),
+        fadt: bytes(Signature::FADT).and_then(parse_fadt),
+        hpet: bytes(Signature::HPET).and_then(parse_hpet),
+        mcfg: bytes(Signature::MCFG).and_then(parse_mcfg),
+        rsdp,
+        tables,
+    })
+}
+
+/// Resumo em várias linhas: tabela raiz, tabelas listadas e o que foi
+/// interpretado de cada uma.
+impl fmt::Display for AcpiTables {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (root, root_address) = self.rsdp.root();
+        writeln!(
+            f,
+            "ACPI {}: OEM {}, {} at {:#x}",
+            if self.rsdp.revision >= 2 {
+                "2.0+"
+            } else {
+                "1.0"
+            },
+            self.rsdp.oem_id(),
+            root,
+            root_address.as_u64()
+        )?;
+        for table in &self.tables {
+            write!(
+                f,
+                "  {} at {:#010x}, {} bytes, rev {}, {} {}",
+                table.header.signature,
+                table.address.as_u64(),
+                table.header.length,
+                table.header.revision,
+                table.header.oem_id(),
+                table.header.oem_table_id()
+            )?;
+            match table.status {
+                Ok(()) => writeln!(f)?,
+                Err(err) => writeln!(f, " [{}]", err)?,
+            }
+        }
+
+        match &self.madt {
+            Ok(madt) => writeln!(
+                f,
+                "  MADT: {} CPUs, {} I/O APICs, {} IRQ overrides, LAPIC at {:#x}",
+                madt.local_apics.len(),
+                madt.io_apics.len(),
+                madt.interrupt_overrides.len(),
+                madt.local_apic_address.as_u64()
+            )?,
+            Err(err) => writeln!(f, "  MADT: {}", err)?,
+        }
+        match &self.fadt {
+            Ok(fadt) => writeln!(
+                f,
+                "  FADT: SCI IRQ {}, PM timer {:#x}, DSDT at {:#x}, reset {}",
+                fadt.sci_interrupt,
+                fadt.pm_timer_block,
+                fadt.dsdt.as_u64(),
+                if fadt.reset_register.is_some() {
+                    "supported"
+                } else {
+                    "unsupported"
+                }
+            )?,
+            Err(err) => writeln!(f, "  FADT: {}", err)?,
+        }
+        match &self.hpet {
+            Ok(hpet) => writeln!(
+                f,
+                "  HPET: at {:#x}, {} comparators",
+                hpet.base_address.as_u64(),
+                hpet.comparators
+            )?,
+            Err(err) => writeln!(f, "  HPET: {}", err)?,
+        }
+        match &self.mcfg {
+            Ok(entries) => {
+                for entry in entries {
+                    writeln!(
+                        f,
+                        "  MCFG: segment {}, buses {}-{} at {:#x}",
+                        entry.segment_group,
+                        entry.start_bus,
+                        entry.end_bus,
+                        entry.base_address.as_u64()
+                    )?;
+                }
+                Ok(())
+            }
+            Err(err) => write!(f, "  MCFG: {}", err),
+        }
+    }
+}
+
+/// Testa o checksum de bytes, incluindo a volta módulo 256.
+#[test_case]
+fn test_checksum() {
+    assert_eq!(checksum(&[]), 0);
+    assert_eq!(checksum(&[0x10, 0xF0]), 0);
+    assert_eq!(checksum(&[0xFF, 0xFF, 0x02]), 0);
+    assert_eq!(checksum(b"RSD PTR "), 0x1F);
+}
+
+/// Testa que uma entrada fora da memória mapeada vira um `Table` com
+/// `BadAddress` (os testes da lib rodam sem o offset mapping).
+#[test_case]
+fn test_unreadable_table_entry() {
+    for &addr in &[0x1000, 1 << 60] {
+        let table = Table::read(Signature::XSDT, addr);
+        assert_eq!(
+            table.status,
+            Err(AcpiError::BadAddress {
+                signature: Signature::XSDT,
+                address: addr
+            })
+        );
+        assert_eq!(table.header, SdtHeader::unknown());
+    }
+}
+
+/// Testa a formatação de assinaturas e de erros.
+#[test_case]
+fn test_signature_display() {
+    use core::fmt::Write;
+
+    // Os testes da lib rodam sem heap, então formatamos num buffer fixo
+    struct Buffer {
+        bytes: [u8; 64],
+        len: usize,
+    }
+    impl Write for Buffer {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            let end = self.len + s.len();
+            self.bytes
+                .get_mut(self.len..end)
+                .ok_or(fmt::Error)?
+                .copy_from_slice(s.as_bytes());
+            self.len = end;
+            Ok(())
+        }
+    }
+
+    let mut buffer = Buffer {
+        bytes: [0; 64],
+        len: 0,
+    };
+    write!(buffer, "{}", AcpiError::BadChecksum(Signature::MADT)).unwrap();
+    assert_eq!(&buffer.bytes[..buffer.len], b"APIC has a bad checksum");
+    assert_eq!(Signature(*b"\xFF\xFF\xFF\xFF").as_str(), "????");
+}
@@ -26,8 +26,13 @@
 //!
 //! [Heap Allocation](https://os.phil-opp.com/heap-allocation/) - Blog OS
 
+use core::{
+    mem::ManuallyDrop,
+    ops::{Deref, DerefMut},
+};
 use fixed_size_block::FixedSizeBlockAllocator;
 use x86_64::{
+    instructions::interrupts,
     structures::paging::{
         mapper::MapToError, FrameAllocator, Mapper, Page, PageTableFlags, Size4KiB,
     },
@@ -83,6 +88,10 @@ pub fn init_heap(
 }
 
 /// Wrapper com spinlock para allocators.
+///
+/// O lock desabilita interrupções enquanto é segurado: assim um handler
+/// de interrupção pode alocar (ex: `Spawner::spawn`) sem deadlock com o
+/// código que ele interrompeu.
 pub struct Locked<A> {
     inner: spin::Mutex<A>,
 }
@@ -94,9 +103,43 @@ impl<A> Locked<A> {
         }
     }
 
-    #[allow(mismatched_lifetime_syntaxes)]
-    pub fn lock(&self) -> spin::MutexGuard<A> {
-        self.inner.lock()
+    pub fn lock(&self) -> LockedGuard<'_, A> {
+        let interrupts_enabled = interrupts::are_enabled();
+        interrupts::disable();
+        LockedGuard {
+            guard: ManuallyDrop::new(self.inner.lock()),
+            interrupts_enabled,
+        }
+    }
+}
+
+/// Guard de `Locked`: libera o lock e restaura as interrupções ao sair.
+pub struct LockedGuard<'a, A> {
+    guard: ManuallyDrop<spin::MutexGuard<'a, A>>,
+    interrupts_enabled: bool,
+}
+
+impl<A> Deref for LockedGuard<'_, A> {
+    type Target = A;
+
+    fn deref(&self) -> &A {
+        &self.guard
+    }
+}
+
+impl<A> DerefMut for LockedGuard<'_, A> {
+    fn deref_mut(&mut self) -> &mut A {
+        &mut self.guard
+    }
+}
+
+impl<A> Drop for LockedGuard<'_, A> {
+    fn drop(&mut self) {
+        // Libera o lock antes de reabilitar as interrupções
+        unsafe { ManuallyDrop::drop(&mut self.guard) };
+        if self.interrupts_enabled {
+            interrupts::enable();
+        }
     }
 }
 
@@ -0,0 +1,238 @@
+//! # Local APIC (Advanced Programmable Interrupt Controller)
+//!
+//! ## O que é o Local APIC?
+//!
+//! Cada CPU x86_64 tem um Local APIC próprio, responsável por receber
+//! interrupções (do I/O APIC, de outras CPUs ou de fontes locais como o
+//! timer e os contadores de performance) e entregá-las ao núcleo.
+//!
+//! Os PICs 8259 continuam entregando as IRQs legadas através da linha
+//! LINT0 (modo "virtual wire" configurado pela BIOS); aqui só habilitamos
+//! o APIC e usamos suas fontes locais.
+//!
+//! ## Acesso aos registradores (xAPIC)
+//!
+//! Os registradores ficam em uma página MMIO (normalmente `0xFEE0_0000`,
+//! informada pela MSR `IA32_APIC_BASE`). Cada registrador tem 32 bits
+//! e fica alinhado em 16 bytes:
+//!
+//! ```text
+//! 0x020  ID           0x0B0  EOI          0x0F0  Spurious Vector
+//! 0x300  ICR (low)    0x310  ICR (high)   0x320  LVT Timer
+//! 0x340  LVT Perf Counter                 0x380  Timer Initial Count
+//! 0x390  Timer Current Count              0x3E0  Timer Divide
+//! ```
+//!
+//! ## IPIs
+//!
+//! O ICR (Interrupt Command Register) envia interrupções para outras
+//! CPUs: NMIs, as sequências INIT/STARTUP usadas por `smp` para ligar
+//! as application processors, e interrupções comuns como a de
+//! `WAKEUP_VECTOR`, que só tira uma CPU do HLT.
+//!
+//! ## LVT (Local Vector Table)
+//!
+//! Cada fonte local tem uma entrada na LVT com vetor, modo de entrega
+//! (fixed, NMI, ...) e bit de máscara. O watchdog usa a entrada do
+//! contador de performance em modo **NMI**.
+//!
+//! ## Timer
+//!
+//! O timer do APIC conta para baixo a partir de um valor inicial, numa
+//! frequência derivada do barramento (dividida por `TIMER_DIVIDE`) que
+//! varia de máquina para máquina. `calibrate_timer()` a mede contra o
+//! HPET.
+//!
+//! ## Referências
+//!
+//! - [APIC](https://wiki.osdev.org/APIC) - OSDev Wiki
+//! - Intel SDM Vol. 3A, Capítulo 11
+
+use crate::memory;
+use core::sync::atomic::{AtomicU64, Ordering};
+use x86_64::{registers::model_specific::Msr, PhysAddr};
+
+/// MSR com o endereço físico base do Local APIC.
+const IA32_APIC_BASE: u32 = 0x1B;
+/// Bit de habilitação global na `IA32_APIC_BASE`.
+const APIC_BASE_ENABLE: u64 = 1 << 11;
+
+/// Offsets dos registradores do Local APIC.
+mod reg {
+    pub const ID: usize = 0x020;
+    pub const EOI: usize = 0x0B0;
+    pub const SPURIOUS: usize = 0x0F0;
+    pub const ICR_LOW: usize = 0x300;
+    pub const ICR_HIGH: usize = 0x310;
+    pub const LVT_TIMER: usize = 0x320;
+    pub const LVT_PERF_COUNTER: usize = 0x340;
+    pub const TIMER_INITIAL_COUNT: usize = 0x380;
+    pub const TIMER_CURRENT_COUNT: usize = 0x390;
+    pub const TIMER_DIVIDE: usize = 0x3E0;
+}
+
+/// Vetor usado para interrupções espúrias do APIC.
+pub const SPURIOUS_VECTOR: u8 = 0xFF;
+/// Vetor da IPI que acorda uma CPU parada em HLT.
+pub const WAKEUP_VECTOR: u8 = 0xF0;
+/// Bit de habilitação por software no registrador Spurious Vector.
+const SPURIOUS_ENABLE: u32 = 1 << 8;
+
+/// Modo de entrega NMI nas entradas da LVT e no ICR.
+pub const DELIVERY_MODE_NMI: u32 = 0b100 << 8;
+/// Modo de entrega "fixed" do ICR: interrupção comum no vetor informado.
+const DELIVERY_MODE_FIXED: u32 = 0b000 << 8;
+/// Modos de entrega INIT e STARTUP (SIPI) do ICR.
+const DELIVERY_MODE_INIT: u32 = 0b101 << 8;
+const DELIVERY_MODE_STARTUP: u32 = 0b110 << 8;
+/// Nível "assert" do ICR (exigido por INIT e SIPI).
+const ICR_LEVEL_ASSERT: u32 = 1 << 14;
+/// Bit de máscara das entradas da LVT.
+pub const LVT_MASKED: u32 = 1 << 16;
+/// Bit "delivery status" do ICR (1 = IPI ainda não aceita).
+const ICR_SEND_PENDING: u32 = 1 << 12;
+
+/// Divisor do clock do timer usado pelo kernel.
+pub const TIMER_DIVIDE: u32 = 16;
+/// Codificação de `TIMER_DIVIDE` no registrador Timer Divide.
+const TIMER_DIVIDE_BY_16: u32 = 0b0011;
+/// Janela de medida da calibração do timer.
+const TIMER_CALIBRATION_US: u64 = 10_000;
+
+/// Tamanho da página de registradores.
+const REGISTERS_SIZE: u64 = 4096;
+
+/// Erros da inicialização do Local APIC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApicError {
+    /// A página de registradores está fora do offset mapping (ver
+    /// `memory::is_phys_mapped`).
+    Unmapped(PhysAddr),
+}
+
+/// Endereço virtual dos registradores (0 = APIC não inicializado).
+static BASE: AtomicU64 = AtomicU64::new(0);
+
+fn base() -> u64 {
+    let base = BASE.load(Ordering::Relaxed);
+    assert!(base != 0, "Local APIC not initialized");
+    base
+}
+
+/// Lê um registrador de 32 bits do Local APIC.
+fn read(offset: usize) -> u32 {
+    let ptr = (base() + offset as u64) as *const u32;
+    unsafe { ptr.read_volatile() }
+}
+
+/// Escreve um registrador de 32 bits do Local APIC.
+fn write(offset: usize, value: u32) {
+    let ptr = (base() + offset as u64) as *mut u32;
+    unsafe { ptr.write_volatile(value) }
+}
+
+/// Verifica se o CPU possui Local APIC (CPUID.01h:EDX bit 9).
+pub fn is_supported() -> bool {
+    crate::cpu::features().apic
+}
+
+/// Retorna se o Local APIC já foi inicializado.
+pub fn is_initialized() -> bool {
+    BASE.load(Ordering::Relaxed) != 0
+}
+
+/// Habilita o Local APIC da CPU atual.
+///
+/// Requer `memory::init` e `BootInfoFrameAllocator::init`, pois os
+/// registradores são acessados pelo offset mapping da memória física.
+pub fn init() -> Result<(), ApicError> {
+    let mut apic_base = Msr::new(IA32_APIC_BASE);
+    let value = unsafe { apic_base.read() };
+    let phys = PhysAddr::new(value & 0x000F_FFFF_FFFF_F000);
+    if !memory::is_phys_mapped(phys, REGISTERS_SIZE) {
+        return Err(ApicError::Unmapped(phys));
+    }
+    if value & APIC_BASE_ENABLE == 0 {
+        unsafe { apic_base.write(value | APIC_BASE_ENABLE) };
+    }
+
+    BASE.store(memory::phys_to_virt(phys).as_u64(), Ordering::Relaxed);
+
+    write(reg::SPURIOUS, SPURIOUS_ENABLE | SPURIOUS_VECTOR as u32);
+    Ok(())
+}
+
+/// Retorna o ID do Local APIC da CPU atual.
+pub fn id() -> u32 {
+    read(reg::ID) >> 24
+}
+
+/// Sinaliza fim de interrupção para interrupções entregues pelo APIC.
+pub fn end_of_interrupt() {
+    write(reg::EOI, 0);
+}
+
+/// Configura a entrada do contador de performance na LVT.
+pub fn set_lvt_perf_counter(entry: u32) {
+    write(reg::LVT_PERF_COUNTER, entry);
+}
+
+/// Frequência do timer em Hz, já dividida (0 = não calibrado).
+static TIMER_FREQUENCY: AtomicU64 = AtomicU64::new(0);
+
+/// Mede a frequência do timer do APIC contra o HPET e a armazena.
+///
+/// O timer fica mascarado, contando de `u32::MAX` para baixo durante a
+/// medida, e parado no fim. Retorna `None` sem HPET (`hpet::init`).
+pub fn calibrate_timer() -> Option<u64> {
+    if !crate::hpet::is_initialized() {
+        return None;
+    }
+    write(reg::TIMER_DIVIDE, TIMER_DIVIDE_BY_16);
+    write(reg::LVT_TIMER, LVT_MASKED);
+    write(reg::TIMER_INITIAL_COUNT, u32::MAX);
+    let frequency = crate::hpet::measure_frequency(TIMER_CALIBRATION_US, || {
+        u64::from(u32::MAX - read(reg::TIMER_CURRENT_COUNT))
+    });
+    write(reg::TIMER_INITIAL_COUNT, 0);
+    TIMER_FREQUENCY.store(frequency, Ordering::Relaxed);
+    Some(frequency)
+}
+
+/// Frequência do timer do APIC em Hz, se já calibrada.
+pub fn timer_frequency() -> Option<u64> {
+    match TIMER_FREQUENCY.load(Ordering::Relaxed) {
+        0 => None,
+        frequency => Some(frequency),
+    }
+}
+
+/// Escreve o ICR e espera o APIC aceitar a IPI.
+fn send_ipi(apic_id: u32, icr_low: u32) {
+    write(reg::ICR_HIGH, apic_id << 24);
+    write(reg::ICR_LOW, icr_low);
+    while read(reg::ICR_LOW) & ICR_SEND_PENDING != 0 {
+        core::hint::spin_loop();
+    }
+}
+
+/// Envia uma NMI para o APIC com o ID informado.
+pub fn send_nmi(apic_id: u32) {
+    send_ipi(apic_id, DELIVERY_MODE_NMI);
+}
+
+/// Envia uma interrupção comum no vetor `vector` para o APIC com o ID informado.
+pub fn send_fixed(apic_id: u32, vector: u8) {
+    send_ipi(apic_id, DELIVERY_MODE_FIXED | u32::from(vector));
+}
+
+/// Envia uma IPI INIT, que reinicia a CPU e a deixa esperando um SIPI.
+pub fn send_init(apic_id: u32) {
+    send_ipi(apic_id, DELIVERY_MODE_INIT | ICR_LEVEL_ASSERT);
+}
+
+/// Envia uma IPI STARTUP (SIPI): a CPU começa em modo real no endereço
+/// físico `page << 12`.
+pub fn send_startup(apic_id: u32, page: u8) {
+    send_ipi(apic_id, DELIVERY_MODE_STARTUP | ICR_LEVEL_ASSERT | u32::from(page));
+}
@@ -0,0 +1,237 @@
+//! # Backtraces com Resolução de Símbolos
+//!
+//! ## Frame pointers
+//!
+//! O target é compilado com `"frame-pointer": "always"`, então toda função
+//! começa com `push rbp; mov rbp, rsp`. Isso forma uma lista encadeada de
+//! frames na stack:
+//!
+//! ```text
+//!        stack (cresce para baixo)
+//!   ┌──────────────────────┐
+//!   │ endereço de retorno  │ ← [rbp + 8] do frame do chamador
+//!   │ rbp do chamador      │ ← [rbp]     (aponta para o frame acima)
+//!   ├──────────────────────┤
+//!   │ variáveis locais     │
+//!   └──────────────────────┘
+//! ```
+//!
+//! Seguindo `rbp → [rbp]` coletamos os endereços de retorno de cada frame.
+//!
+//! ## Tabela de símbolos
+//!
+//! O bootloader só carrega os segmentos do ELF, então a `.symtab` não
+//! existe em memória. Reservamos a seção `.ksyms` e, depois do link, o
+//! runner `tools/ksyms-runner.sh` grava nela a saída ordenada do `llvm-nm`:
+//!
+//! ```text
+//! # ksyms
+//! 0000000000201000 T _start
+//! 0000000000201040 T rust_os::init
+//! ...
+//! ```
+//!
+//! Para resolver um endereço procuramos o maior símbolo `<=` a ele e
+//! imprimimos `função+offset`. O parser não aloca, então funciona dentro
+//! de panics e handlers de exceção.
+
+use core::{
+    fmt,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use x86_64::{structures::idt::InterruptStackFrame, VirtAddr};
+
+/// Tamanho reservado para a tabela de símbolos (preenchida após o link).
+const KSYMS_SIZE: usize = 256 * 1024;
+/// Cabeçalho da tabela; o runner o mantém ao gravar os símbolos.
+const KSYMS_MAGIC: &[u8] = b"# ksyms\n";
+/// Limite de frames percorridos (protege contra cadeias corrompidas).
+const MAX_FRAMES: usize = 64;
+/// Maior distância aceita entre dois frames consecutivos.
+const MAX_FRAME_SIZE: u64 = 1024 * 1024;
+
+/// Cria o conteúdo inicial da seção: só o cabeçalho, seguido de zeros.
+///
+/// Um valor não-nulo faz a seção ir para o arquivo (PROGBITS) em vez de
+/// ser tratada como `.bss`, permitindo que o runner a sobrescreva.
+const fn ksyms_placeholder() -> [u8; KSYMS_SIZE] {
+    let mut table = [0; KSYMS_SIZE];
+    let mut i = 0;
+    while i < KSYMS_MAGIC.len() {
+        table[i] = KSYMS_MAGIC[i];
+        i += 1;
+    }
+    table
+}
+
+/// Tabela de símbolos embutida no kernel.
+///
+/// `static mut` impede que o compilador assuma o conteúdo inicial ao ler.
+#[used]
+#[unsafe(link_section = ".ksyms")]
+static mut KSYMS: [u8; KSYMS_SIZE] = ksyms_placeholder();
+
+/// Evita backtraces recursivos (ex: page fault durante o percurso).
+static TRACING: AtomicBool = AtomicBool::new(false);
+
+fn ksyms() -> &'static [u8] {
+    unsafe { core::slice::from_raw_parts((&raw const KSYMS).cast::<u8>(), KSYMS_SIZE) }
+}
+
+/// Itera sobre as entradas `(endereço, nome)` da tabela de símbolos.
+fn symbols() -> impl Iterator<Item = (u64, &'static str)> {
+    let table = ksyms();
+    let end = table.iter().position(|&b| b == 0).unwrap_or(table.len());
+    table[..end].split(|&b| b == b'\n').filter_map(parse_line)
+}
+
+/// Interpreta uma linha `<endereço hex> <tipo> <nome>`.
+fn parse_line(line: &[u8]) -> Option<(u64, &str)> {
+    let line = core::str::from_utf8(line).ok()?;
+    let mut parts = line.splitn(3, ' ');
+    let address = u64::from_str_radix(parts.next()?, 16).ok()?;
+    let _kind = parts.next()?;
+    let name = parts.next()?;
+    Some((address, name))
+}
+
+/// Retorna se a tabela de símbolos foi preenchida pelo runner.
+pub fn has_symbols() -> bool {
+    symbols().next().is_some()
+}
+
+/// Encontra a função que contém `address`, retornando nome e offset.
+pub fn resolve(address: u64) -> Option<(&'static str, u64)> {
+    // A tabela está ordenada por endereço (llvm-nm -n)
+    symbols()
+        .take_while(|&(start, _)| start <= address)
+        .last()
+        .map(|(start, name)| (name, address - start))
+}
+
+/// Um frame do backtrace.
+#[derive(Debug, Clone, Copy)]
+pub struct Frame {
+    /// Endereço da instrução (RIP ou endereço de retorno).
+    pub address: u64,
+    /// Função e offset, se a tabela de símbolos estiver disponível.
+    pub symbol: Option<(&'static str, u64)>,
+}
+
+impl Frame {
+    /// Frame para o endereço exato de uma instrução (ex: RIP de exceção).
+    fn at_instruction(address: u64) -> Self {
+        Frame {
+            address,
+            symbol: resolve(address),
+        }
+    }
+
+    /// Frame para um endereço de retorno.
+    ///
+    /// Resolve `address - 1` (a instrução `call`), pois o retorno de uma
+    /// chamada que não retorna pode cair já na função seguinte.
+    fn at_return_address(address: u64) -> Self {
+        Frame {
+            address,
+            symbol: resolve(address - 1).map(|(name, offset)| (name, offset + 1)),
+        }
+    }
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.symbol {
+            Some((name, offset)) => write!(f, "{:#018x}  {}+{:#x}", self.address, name, offset),
+            None => write!(f, "{:#018x}  <unknown>", self.address),
+        }
+    }
+}
+
+/// Lê o registrador RBP (frame pointer) da função que chama.
+#[inline(always)]
+pub fn frame_pointer() -> u64 {
+    let rbp: u64;
+    unsafe { core::arch::asm!("mov {}, rbp", out(reg) rbp, options(nomem, nostack)) };
+    rbp
+}
+
+/// Verifica se `rbp` parece um frame pointer válido.
+fn is_plausible_frame(rbp: u64) -> bool {
+    rbp != 0 && rbp.is_multiple_of(8) && VirtAddr::try_new(rbp).is_ok()
+}
+
+/// Percorre a cadeia de frames a partir de `rbp`, chamando `f` para cada um.
+fn walk(mut rbp: u64, mut f: impl FnMut(Frame)) {
+    for _ in 0..MAX_FRAMES {
+        if !is_plausible_frame(rbp) {
+            break;
+        }
+        let (next, return_address) = unsafe {
+            let frame = rbp as *const u64;
+            (frame.read(), frame.add(1).read())
+        };
+        if return_address == 0 {
+            break;
+        }
+        f(Frame::at_return_address(return_address));
+
+        // Frames dos chamadores estão em endereços maiores
+        if next <= rbp || next - rbp > MAX_FRAME_SIZE {
+            break;
+        }
+        rbp = next;
+    }
+}
+
+/// Executa `f` com a proteção contra backtraces recursivos.
+fn guarded(f: impl FnOnce()) {
+    if TRACING.swap(true, Ordering::Acquire) {
+        return;
+    }
+    f();
+    TRACING.store(false, Ordering::Release);
+}
+
+/// Gera o backtrace a partir do frame pointer `rbp`.
+///
+/// Normalmente chamado com `backtrace::frame_pointer()`.
+pub fn trace(rbp: u64, f: impl FnMut(Frame)) {
+    guarded(|| walk(rbp, f));
+}
+
+/// Gera o backtrace do código interrompido por uma exceção.
+///
+/// `handler_rbp` é o `frame_pointer()` lido no início do handler: o
+/// prólogo do handler salvou ali o RBP do código interrompido.
+pub fn trace_exception(stack_frame: &InterruptStackFrame, handler_rbp: u64, mut f: impl FnMut(Frame)) {
+    guarded(|| {
+        f(Frame::at_instruction(stack_frame.instruction_pointer.as_u64()));
+        if is_plausible_frame(handler_rbp) {
+            let interrupted_rbp = unsafe { (handler_rbp as *const u64).read() };
+            walk(interrupted_rbp, f);
+        }
+    });
+}
+
+/// Testa que o backtrace atual encontra pelo menos um frame.
+#[test_case]
+fn test_trace_current_stack() {
+    let mut frames = 0;
+    trace(frame_pointer(), |_| frames += 1);
+    assert!(frames > 0);
+}
+
+/// Testa a resolução de símbolos quando a tabela foi embutida.
+#[test_case]
+fn test_resolve_function_address() {
+    #[inline(never)]
+    fn resolve_target() {}
+
+    if has_symbols() {
+        let address = resolve_target as fn() as u64;
+        let (name, offset) = resolve(address).expect("symbol not found");
+        assert!(name.ends_with("resolve_target"));
+        assert_eq!(offset, 0);
+    }
+}
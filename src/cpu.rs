@@ -0,0 +1,325 @@
+//! # Identificação do Processador (CPUID)
+//!
+//! ## O problema
+//!
+//! O kernel é compilado para o mínimo comum (sem SSE, ver
+//! `x86_64-rust_os.json`), mas o hardware real pode ter muito mais:
+//! x2APIC, NX, PCID, TSC invariante, RDRAND... Cada subsistema que depende
+//! de um recurso precisa perguntar ao processador antes de usá-lo.
+//!
+//! ## CPUID
+//!
+//! A instrução `cpuid` recebe uma "leaf" em EAX (e às vezes uma sub-leaf
+//! em ECX) e devolve bits de recursos em EAX/EBX/ECX/EDX:
+//!
+//! ```text
+//! Leaf          Conteúdo
+//! 0x0           leaf máxima + vendor ("GenuineIntel", "AuthenticAMD")
+//! 0x1           família/modelo + recursos básicos (APIC, SSE, x2APIC, XSAVE...)
+//! 0x7           recursos estendidos (AVX2, SMEP, SMAP, RDSEED, INVPCID...)
+//! 0xA           contadores de performance arquiteturais
+//! 0x8000_0001   NX, páginas de 1 GiB, RDTSCP
+//! 0x8000_0002-4 nome do processador (48 bytes)
+//! 0x8000_0007   TSC invariante
+//! ```
+//!
+//! ## Uso
+//!
+//! `features()` lê o CPUID uma vez (na primeira chamada) e devolve uma
+//! `CpuFeatures` que o resto do kernel consulta:
+//!
+//! ```ignore
+//! if cpu::features().x2apic {
+//!     // ...
+//! }
+//! ```
+//!
+//! Os recursos são os da BSP; o kernel assume que todas as CPUs são
+//! iguais, o que vale para as máquinas SMP suportadas.
+
+use core::{
+    arch::x86_64::{__cpuid, __cpuid_count, CpuidResult},
+    fmt, str,
+};
+use spin::Once;
+
+/// Fabricante do processador.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Vendor {
+    Intel,
+    Amd,
+    Other,
+}
+
+/// Recursos do processador lidos via CPUID.
+#[derive(Debug, Clone, Copy)]
+pub struct CpuFeatures {
+    pub vendor: Vendor,
+    vendor_id: [u8; 12],
+    brand: [u8; 48],
+    pub family: u32,
+    pub model: u32,
+    pub stepping: u32,
+    /// Maior leaf básica suportada.
+    pub max_leaf: u32,
+    /// Maior leaf estendida suportada (0x8000_xxxx).
+    pub max_extended_leaf: u32,
+
+    // Leaf 0x1
+    pub fpu: bool,
+    pub tsc: bool,
+    pub msr: bool,
+    pub apic: bool,
+    pub pge: bool,
+    pub pat: bool,
+    pub fxsr: bool,
+    pub sse: bool,
+    pub sse2: bool,
+    pub sse3: bool,
+    pub ssse3: bool,
+    pub sse4_1: bool,
+    pub sse4_2: bool,
+    pub pcid: bool,
+    pub x2apic: bool,
+    pub tsc_deadline: bool,
+    pub xsave: bool,
+    /// CR4.OSXSAVE estava ligado na detecção (antes de `fpu::init_cpu`).
+    pub osxsave: bool,
+    pub avx: bool,
+    pub rdrand: bool,
+    /// Rodando sobre um hypervisor (QEMU, KVM...).
+    pub hypervisor: bool,
+
+    // Leaf 0x7
+    pub fsgsbase: bool,
+    pub avx2: bool,
+    pub smep: bool,
+    pub smap: bool,
+    pub invpcid: bool,
+    pub rdseed: bool,
+
+    // Leaf 0xA
+    /// Versão dos contadores de performance arquiteturais (0 = nenhum).
+    pub perfmon_version: u8,
+    /// Contadores de propósito geral por CPU.
+    pub perfmon_counters: u8,
+    /// O evento "ciclos do processador" pode ser contado.
+    pub perfmon_cycles: bool,
+
+    // Leaves estendidas
+    pub nx: bool,
+    pub huge_pages_1gib: bool,
+    pub rdtscp: bool,
+    /// TSC com frequência constante em qualquer estado de energia.
+    pub invariant_tsc: bool,
+}
+
+static FEATURES: Once<CpuFeatures> = Once::new();
+
+/// Recursos do processador (lidos na primeira chamada).
+pub fn features() -> &'static CpuFeatures {
+    FEATURES.call_once(detect)
+}
+
+fn bit(value: u32, bit: u32) -> bool {
+    value & (1 << bit) != 0
+}
+
+/// Lê uma leaf, ou zeros se ela não existir.
+fn leaf(max: u32, index: u32) -> CpuidResult {
+    if index <= max {
+        __cpuid_count(index, 0)
+    } else {
+        CpuidResult {
+            eax: 0,
+            ebx: 0,
+            ecx: 0,
+            edx: 0,
+        }
+    }
+}
+
+fn detect() -> CpuFeatures {
+    let vendor_leaf = __cpuid(0);
+    let max_leaf = vendor_leaf.eax;
+    let mut vendor_id = [0; 12];
+    vendor_id[0..4].copy_from_slice(&vendor_leaf.ebx.to_le_bytes());
+    vendor_id[4..8].copy_from_slice(&vendor_leaf.edx.to_le_bytes());
+    vendor_id[8..12].copy_from_slice(&vendor_leaf.ecx.to_le_bytes());
+    let vendor = match &vendor_id {
+        b"GenuineIntel" => Vendor::Intel,
+        b"AuthenticAMD" => Vendor::Amd,
+        _ => Vendor::Other,
+    };
+
+    let max_extended_leaf = __cpuid(0x8000_0000).eax;
+    let extended = |index: u32| leaf(max_extended_leaf, index);
+
+    let mut brand = [0; 48];
+    for (i, index) in (0x8000_0002..=0x8000_0004).enumerate() {
+        let regs = extended(index);
+        for (j, reg) in [regs.eax, regs.ebx, regs.ecx, regs.edx].iter().enumerate() {
+            let offset = i * 16 + j * 4;
+            brand[offset..offset + 4].copy_from_slice(&reg.to_le_bytes());
+        }
+    }
+
+    // Família e modelo: os campos "estendidos" só valem para algumas famílias
+    let basic = leaf(max_leaf, 1);
+    let base_family = (basic.eax >> 8) & 0xF;
+    let base_model = (basic.eax >> 4) & 0xF;
+    let family = match base_family {
+        0xF => base_family + ((basic.eax >> 20) & 0xFF),
+        _ => base_family,
+    };
+    let model = match base_family {
+        0x6 | 0xF => base_model | ((basic.eax >> 16) & 0xF) << 4,
+        _ => base_model,
+    };
+
+    let structured = leaf(max_leaf, 7);
+    let perfmon = leaf(max_leaf, 0xA);
+    let extended_features = extended(0x8000_0001);
+    let power = extended(0x8000_0007);
+
+    CpuFeatures {
+        vendor,
+        vendor_id,
+        brand,
+        family,
+        model,
+        stepping: basic.eax & 0xF,
+        max_leaf,
+        max_extended_leaf,
+
+        fpu: bit(basic.edx, 0),
+        tsc: bit(basic.edx, 4),
+        msr: bit(basic.edx, 5),
+        apic: bit(basic.edx, 9),
+        pge: bit(basic.edx, 13),
+        pat: bit(basic.edx, 16),
+        fxsr: bit(basic.edx, 24),
+        sse: bit(basic.edx, 25),
+        sse2: bit(basic.edx, 26),
+        sse3: bit(basic.ecx, 0),
+        ssse3: bit(basic.ecx, 9),
+        sse4_1: bit(basic.ecx, 19),
+        sse4_2: bit(basic.ecx, 20),
+        pcid: bit(basic.ecx, 17),
+        x2apic: bit(basic.ecx, 21),
+        tsc_deadline: bit(basic.ecx, 24),
+        xsave: bit(basic.ecx, 26),
+        osxsave: bit(basic.ecx, 27),
+        avx: bit(basic.ecx, 28),
+        rdrand: bit(basic.ecx, 30),
+        hypervisor: bit(basic.ecx, 31),
+
+        fsgsbase: bit(structured.ebx, 0),
+        avx2: bit(structured.ebx, 5),
+        smep: bit(structured.ebx, 7),
+        invpcid: bit(structured.ebx, 10),
+        rdseed: bit(structured.ebx, 18),
+        smap: bit(structured.ebx, 20),
+
+        perfmon_version: perfmon.eax as u8,
+        perfmon_counters: (perfmon.eax >> 8) as u8,
+        // EBX bit 0 = 1 significa que o evento de ciclos NÃO está disponível
+        perfmon_cycles: perfmon.eax as u8 > 0 && !bit(perfmon.ebx, 0),
+
+        nx: bit(extended_features.edx, 20),
+        huge_pages_1gib: bit(extended_features.edx, 26),
+        rdtscp: bit(extended_features.edx, 27),
+        invariant_tsc: bit(power.edx, 8),
+    }
+}
+
+impl CpuFeatures {
+    /// Vendor ID como retornado pelo CPUID (ex: "GenuineIntel").
+    pub fn vendor_id(&self) -> &str {
+        str::from_utf8(&self.vendor_id).unwrap_or("unknown")
+    }
+
+    /// Nome do processador (ex: "QEMU Virtual CPU version 2.5+").
+    pub fn brand(&self) -> &str {
+        let end = self
+            .brand
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(self.brand.len());
+        str::from_utf8(&self.brand[..end]).unwrap_or("").trim()
+    }
+
+    /// Nomes dos recursos presentes, na ordem de `Display`.
+    pub fn flags(&self) -> impl Iterator<Item = &'static str> {
+        let flags = [
+            ("fpu", self.fpu),
+            ("tsc", self.tsc),
+            ("msr", self.msr),
+            ("apic", self.apic),
+            ("x2apic", self.x2apic),
+            ("tsc-deadline", self.tsc_deadline),
+            ("invariant-tsc", self.invariant_tsc),
+            ("rdtscp", self.rdtscp),
+            ("pge", self.pge),
+            ("pat", self.pat),
+            ("nx", self.nx),
+            ("pcid", self.pcid),
+            ("invpcid", self.invpcid),
+            ("1g-pages", self.huge_pages_1gib),
+            ("smep", self.smep),
+            ("smap", self.smap),
+            ("fsgsbase", self.fsgsbase),
+            ("fxsr", self.fxsr),
+            ("sse", self.sse),
+            ("sse2", self.sse2),
+            ("sse3", self.sse3),
+            ("ssse3", self.ssse3),
+            ("sse4.1", self.sse4_1),
+            ("sse4.2", self.sse4_2),
+            ("xsave", self.xsave),
+            ("avx", self.avx),
+            ("avx2", self.avx2),
+            ("rdrand", self.rdrand),
+            ("rdseed", self.rdseed),
+            ("perfmon", self.perfmon_version > 0),
+            ("hypervisor", self.hypervisor),
+        ];
+        IntoIterator::into_iter(flags)
+            .filter(|&(_, present)| present)
+            .map(|(name, _)| name)
+    }
+}
+
+/// Lista os recursos presentes separados por espaço.
+impl fmt::Display for CpuFeatures {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, flag) in self.flags().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            f.write_str(flag)?;
+        }
+        Ok(())
+    }
+}
+
+/// Testa que os recursos básicos de qualquer x86_64 aparecem.
+#[test_case]
+fn test_baseline_features() {
+    let features = features();
+    assert!(features.max_leaf >= 1);
+    assert!(features.fpu && features.tsc && features.msr && features.apic);
+    assert!(features.sse && features.sse2 && features.fxsr);
+    assert!(features.family > 0);
+}
+
+/// Testa que o vendor é reconhecido e bate com o vendor ID.
+#[test_case]
+fn test_vendor() {
+    let features = features();
+    match features.vendor {
+        Vendor::Intel => assert_eq!(features.vendor_id(), "GenuineIntel"),
+        Vendor::Amd => assert_eq!(features.vendor_id(), "AuthenticAMD"),
+        Vendor::Other => assert_eq!(features.vendor_id().len(), 12),
+    }
+}
@@ -0,0 +1,369 @@
+//! # FPU, SSE e AVX
+//!
+//! ## O problema
+//!
+//! O kernel é compilado com `+soft-float` e sem SSE (ver
+//! `x86_64-rust_os.json`): o compilador nunca gera instruções de FPU/SIMD
+//! no kernel, então interrupções e trocas de contexto não precisam salvar
+//! esses registradores. Código que usa SIMD de verdade (as rotinas de
+//! `simd/`, ver o módulo `simd`) precisa de:
+//!
+//! 1. FPU e SSE habilitados (`CR0`, `CR4`, `XCR0`);
+//! 2. os registradores (x87, XMM, YMM) preservados entre threads.
+//!
+//! O kernel em si continua `+soft-float` de propósito: com SSE no target,
+//! o LLVM usaria XMM em qualquer função, inclusive nos handlers de
+//! interrupção (que passariam a salvar os 16 XMM a cada IRQ), no próprio
+//! handler de `#NM` antes do `clts` (recursão) e no boot antes de
+//! `init_cpu` (`#UD`). Por isso o código com SSE fica num crate à parte,
+//! compilado com outro target e chamado só dentro de `kernel_fpu_begin`.
+//!
+//! ## Inicialização (`init_cpu`)
+//!
+//! ```text
+//! CR0: EM = 0 (sem emulação), MP = 1, NE = 1 (erros via #MF)
+//! CR4: OSFXSR = 1 (FXSAVE/SSE), OSXMMEXCPT = 1 (erros SSE via #XM)
+//!      OSXSAVE = 1 se o CPU tem XSAVE
+//! XCR0: x87 | SSE | AVX (se disponível)
+//! ```
+//!
+//! Com XSAVE o estado é salvo com `xsave`/`xrstor` (inclui os YMM);
+//! sem, com `fxsave`/`fxrstor` (x87 e SSE).
+//!
+//! ## Troca preguiçosa (`#NM`)
+//!
+//! Salvar ~1 KiB a cada troca de thread é desperdício: quase nenhuma
+//! thread usa a FPU. Em vez disso, a troca só liga `CR0.TS`; a primeira
+//! instrução de FPU da nova thread gera `#NM` (device not available), e
+//! só então o estado é trocado:
+//!
+//! ```text
+//! troca A → B:  TS = 1                     (registradores ainda são de A)
+//! B usa SSE:    #NM → salva em A.fpu, restaura B.fpu, dono = B, TS = 0
+//! troca B → A:  TS = 1
+//! A não usa FPU: nada é salvo nem restaurado
+//! ```
+//!
+//! Cada CPU guarda o **dono** dos registradores (a área onde salvá-los) e
+//! a área do **contexto atual**: a `FpuState` da thread em execução ou,
+//! fora de threads (APs, boot), uma área por CPU.
+//!
+//! Tasks assíncronas não têm área própria: rodam no contexto da thread (ou
+//! CPU) que as executa. Uma task só usa os registradores dentro de uma
+//! chamada de `simd`, que termina antes do poll retornar, então nenhum
+//! estado de FPU de uma task sobrevive a um `.await`. Já uma thread pode
+//! ser preemptada no meio do uso, por isso cada `Thread` tem sua
+//! `FpuState`.
+//!
+//! ## `kernel_fpu_begin`
+//!
+//! Para usar SIMD em um trecho curto sem depender do contexto atual (ex:
+//! dentro de um poll no executor multi-core), `kernel_fpu_begin()` salva
+//! o estado do dono, entrega registradores limpos e desabilita
+//! interrupções até o guard ser solto. É assim que o módulo `simd` chama
+//! as rotinas de `simd/`:
+//!
+//! ```ignore
+//! let _fpu = fpu::kernel_fpu_begin();
+//! acc = unsafe { ffi::simd_sum(acc, chunk.as_ptr(), chunk.len()) };
+//! ```
+
+use crate::{cpu, cpu_local, percpu, smp::MAX_CPUS};
+use core::{
+    cell::UnsafeCell,
+    marker::PhantomData,
+    ptr,
+    sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, Ordering},
+};
+use x86_64::{
+    instructions::interrupts,
+    registers::{
+        control::{Cr0, Cr0Flags, Cr4, Cr4Flags},
+        xcontrol::{XCr0, XCr0Flags},
+    },
+};
+
+/// Tamanho da área de salvamento: legado (512) + header XSAVE (64) + AVX
+/// (256) = 832 bytes, arredondado.
+const STATE_SIZE: usize = 1024;
+
+/// Estado estendido (x87, SSE, AVX) de um contexto.
+#[repr(C, align(64))]
+pub struct FpuState {
+    bytes: [u8; STATE_SIZE],
+}
+
+impl FpuState {
+    /// Estado inicial: pilha x87 vazia, exceções mascaradas (FCW = 0x37F,
+    /// MXCSR = 0x1F80) e header XSAVE zerado (componentes no estado inicial).
+    pub const fn new() -> Self {
+        let mut bytes = [0; STATE_SIZE];
+        bytes[0] = 0x7F;
+        bytes[1] = 0x03;
+        bytes[24] = 0x80;
+        bytes[25] = 0x1F;
+        FpuState { bytes }
+    }
+}
+
+impl Default for FpuState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for FpuState {
+    /// Uma área liberada (thread terminada) não pode continuar como dono.
+    fn drop(&mut self) {
+        let this: *mut FpuState = self;
+        for index in 0..MAX_CPUS {
+            if let Some(owner) = OWNER.get_for(index) {
+                let _ = owner.compare_exchange(
+                    this,
+                    ptr::null_mut(),
+                    Ordering::AcqRel,
+                    Ordering::Relaxed,
+                );
+            }
+        }
+    }
+}
+
+/// Se `xsave`/`xrstor` são usados (senão, `fxsave`/`fxrstor`).
+static USE_XSAVE: AtomicBool = AtomicBool::new(false);
+/// Trocas de estado feitas pelo handler de `#NM` (todas as CPUs).
+static LAZY_SWITCHES: AtomicU64 = AtomicU64::new(0);
+
+cpu_local! {
+    /// Se `init_cpu` já rodou nesta CPU.
+    static READY: AtomicBool = AtomicBool::new(false);
+    /// Área onde os registradores desta CPU devem ser salvos (nula = ninguém).
+    static OWNER: AtomicPtr<FpuState> = AtomicPtr::new(ptr::null_mut());
+    /// Área do contexto em execução (nula = `CPU_STATE`).
+    static CURRENT: AtomicPtr<FpuState> = AtomicPtr::new(ptr::null_mut());
+    /// Se um `kernel_fpu_begin` está ativo nesta CPU.
+    static IN_KERNEL_FPU: AtomicBool = AtomicBool::new(false);
+    /// Área dos contextos que não são threads (APs, boot).
+    static CPU_STATE: UnsafeCell<FpuState> = UnsafeCell::new(FpuState::new());
+}
+
+/// Habilita FPU/SSE (e XSAVE/AVX, se disponíveis) na CPU atual.
+///
+/// Chamado por `rust_os::init()` na BSP e por `ap_entry` nas APs.
+pub fn init_cpu() {
+    let features = cpu::features();
+    unsafe {
+        Cr0::update(|flags| {
+            flags.remove(Cr0Flags::EMULATE_COPROCESSOR);
+            flags.insert(Cr0Flags::MONITOR_COPROCESSOR | Cr0Flags::NUMERIC_ERROR);
+            flags.remove(Cr0Flags::TASK_SWITCHED);
+        });
+        Cr4::update(|flags| {
+            flags.insert(Cr4Flags::OSFXSR | Cr4Flags::OSXMMEXCPT_ENABLE);
+            if features.xsave {
+                flags.insert(Cr4Flags::OSXSAVE);
+            }
+        });
+        if features.xsave {
+            let mut components = XCr0Flags::X87 | XCr0Flags::SSE;
+            if features.avx {
+                components |= XCr0Flags::AVX;
+            }
+            XCr0::write(components);
+            // Tamanho do formato padrão para os componentes de XCR0
+            let size = core::arch::x86_64::__cpuid_count(0xD, 0).ebx as usize;
+            assert!(size <= STATE_SIZE, "XSAVE area too large: {} bytes", size);
+        }
+        core::arch::asm!("fninit", options(nomem, nostack));
+    }
+    USE_XSAVE.store(features.xsave, Ordering::Relaxed);
+    READY.get().store(true, Ordering::Release);
+    OWNER.get().store(ptr::null_mut(), Ordering::Relaxed);
+    // Os registradores acabaram de ser zerados: o primeiro uso carrega o
+    // estado do contexto atual
+    set_task_switched();
+}
+
+/// Retorna se a CPU atual tem a FPU habilitada.
+pub fn is_enabled() -> bool {
+    READY.get().load(Ordering::Acquire)
+}
+
+/// Retorna se o estado é salvo com XSAVE (inclui AVX).
+pub fn uses_xsave() -> bool {
+    USE_XSAVE.load(Ordering::Relaxed)
+}
+
+/// Trocas de estado feitas sob demanda (`#NM`) desde o boot.
+pub fn lazy_switches() -> u64 {
+    LAZY_SWITCHES.load(Ordering::Relaxed)
+}
+
+fn set_task_switched() {
+    unsafe { Cr0::update(|flags| flags.insert(Cr0Flags::TASK_SWITCHED)) };
+}
+
+fn clear_task_switched() {
+    unsafe { core::arch::asm!("clts", options(nomem, nostack)) };
+}
+
+unsafe fn save(state: *mut FpuState) {
+    if uses_xsave() {
+        core::arch::asm!(
+            "xsave64 [{}]",
+            in(reg) state,
+            in("eax") u32::MAX,
+            in("edx") u32::MAX,
+            options(nostack)
+        );
+    } else {
+        core::arch::asm!("fxsave64 [{}]", in(reg) state, options(nostack));
+    }
+}
+
+unsafe fn restore(state: *const FpuState) {
+    if uses_xsave() {
+        core::arch::asm!(
+            "xrstor64 [{}]",
+            in(reg) state,
+            in("eax") u32::MAX,
+            in("edx") u32::MAX,
+            options(nostack, readonly)
+        );
+    } else {
+        core::arch::asm!("fxrstor64 [{}]", in(reg) state, options(nostack, readonly));
+    }
+}
+
+/// Área do contexto em execução nesta CPU.
+fn current_state() -> *mut FpuState {
+    let current = CURRENT.get().load(Ordering::Relaxed);
+    if current.is_null() {
+        CPU_STATE.with(|state| state.get())
+    } else {
+        current
+    }
+}
+
+/// Troca o contexto de FPU da CPU atual para `state` (nulo = área da CPU).
+///
+/// Chamado pelo escalonador ao trocar de thread, com interrupções
+/// desabilitadas. Os registradores só são trocados no próximo `#NM`.
+pub(crate) fn switch_to(state: *mut FpuState) {
+    if !is_enabled() {
+        return;
+    }
+    CURRENT.get().store(state, Ordering::Relaxed);
+    set_task_switched();
+}
+
+/// Trata `#NM`. Retorna `false` se a FPU não foi habilitada nesta CPU
+/// (a exceção é um erro de verdade).
+pub(crate) fn handle_device_not_available() -> bool {
+    if !is_enabled() || IN_KERNEL_FPU.get().load(Ordering::Relaxed) {
+        return false;
+    }
+    clear_task_switched();
+    let current = current_state();
+    let owner = OWNER.get().swap(current, Ordering::AcqRel);
+    if owner != current {
+        unsafe {
+            if !owner.is_null() {
+                save(owner);
+            }
+            restore(current);
+        }
+        LAZY_SWITCHES.fetch_add(1, Ordering::Relaxed);
+    }
+    true
+}
+
+/// Uso temporário da FPU pelo kernel (ver `kernel_fpu_begin`).
+///
+/// Não é `Send`: o trecho precisa terminar na mesma CPU, sem `.await`.
+pub struct KernelFpu {
+    irqs_enabled: bool,
+    _not_send: PhantomData<*mut ()>,
+}
+
+/// Entrega a FPU com estado limpo ao código do kernel até o guard ser
+/// solto. Interrupções ficam desabilitadas nesse intervalo.
+///
+/// # Panics
+///
+/// Se a FPU não foi habilitada nesta CPU ou se já há um
+/// `kernel_fpu_begin` ativo nela.
+pub fn kernel_fpu_begin() -> KernelFpu {
+    let irqs_enabled = interrupts::are_enabled();
+    interrupts::disable();
+    assert!(
+        is_enabled(),
+        "kernel_fpu_begin: FPU not initialized on CPU {}",
+        percpu::cpu_index()
+    );
+    assert!(
+        !IN_KERNEL_FPU.get().swap(true, Ordering::Relaxed),
+        "kernel_fpu_begin: already active on CPU {}",
+        percpu::cpu_index()
+    );
+
+    clear_task_switched();
+    let owner = OWNER.get().swap(ptr::null_mut(), Ordering::AcqRel);
+    static CLEAN: FpuState = FpuState::new();
+    unsafe {
+        if !owner.is_null() {
+            save(owner);
+        }
+        restore(&CLEAN);
+    }
+    KernelFpu {
+        irqs_enabled,
+        _not_send: PhantomData,
+    }
+}
+
+/// Equivalente a soltar o guard de `kernel_fpu_begin`.
+pub fn kernel_fpu_end(guard: KernelFpu) {
+    drop(guard);
+}
+
+impl Drop for KernelFpu {
+    fn drop(&mut self) {
+        // Sem dono: o próximo uso da FPU restaura o contexto atual
+        set_task_switched();
+        IN_KERNEL_FPU.get().store(false, Ordering::Relaxed);
+        if self.irqs_enabled {
+            interrupts::enable();
+        }
+    }
+}
+
+/// Soma dois `f64` com SSE2 (rotina de `simd/`), sem `kernel_fpu_begin`.
+#[cfg(test)]
+fn sse_add(a: f64, b: f64) -> f64 {
+    f64::from_bits(unsafe { crate::simd::ffi::simd_sum(a.to_bits(), &b, 1) })
+}
+
+/// Testa que SSE funciona depois da inicialização (via `#NM`).
+#[test_case]
+fn test_sse_after_lazy_restore() {
+    assert!(is_enabled());
+    // Sem dono e com TS ligado: o próximo uso da FPU passa pelo `#NM`
+    drop(kernel_fpu_begin());
+    let before = lazy_switches();
+    assert_eq!(sse_add(1.5, 2.25), 3.75);
+    assert!(!Cr0::read().contains(Cr0Flags::TASK_SWITCHED));
+    assert_eq!(lazy_switches(), before + 1);
+}
+
+/// Testa que `kernel_fpu_begin` desabilita interrupções e as restaura.
+#[test_case]
+fn test_kernel_fpu_guard() {
+    assert!(interrupts::are_enabled());
+    let fpu = kernel_fpu_begin();
+    assert!(!interrupts::are_enabled());
+    assert_eq!(sse_add(0.5, 0.25), 0.75);
+    kernel_fpu_end(fpu);
+    assert!(interrupts::are_enabled());
+    assert!(Cr0::read().contains(Cr0Flags::TASK_SWITCHED));
+}
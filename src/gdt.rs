@@ -25,10 +25,20 @@
 //!                      → Carrega TSS no registrador TR
 //! ```
 //!
+//! ## Várias CPUs
+//!
+//! O TSS guarda as stacks da IST, então cada CPU precisa do seu (duas
+//! CPUs tratando double faults na mesma stack se corromperiam). A BSP usa
+//! as tabelas estáticas abaixo; cada application processor chama
+//! `init_ap()`, que cria GDT e TSS próprios com stacks da IST alocadas
+//! como stacks de thread (com página de guarda).
+//!
 //! ## Estudo baseado em
 //!
 //! [Double Faults](https://os.phil-opp.com/double-fault-exceptions/) - Blog OS
 
+use crate::thread::stack::Stack;
+use alloc::boxed::Box;
 use lazy_static::lazy_static;
 use x86_64::{
     instructions::{
@@ -45,6 +55,14 @@ use x86_64::{
 
 /// Índice na IST para a stack de double fault.
 pub const DOUBLE_FAULT_IST_INDEX: u16 = 0;
+/// Índice na IST para a stack de NMI.
+///
+/// Uma NMI pode chegar a qualquer momento, inclusive com a stack corrente
+/// quase cheia ou no meio de outro handler, então ela usa stack própria.
+pub const NMI_IST_INDEX: u16 = 1;
+
+/// Tamanho das stacks da IST da BSP.
+pub const IST_STACK_SIZE: usize = 4096 * 5;
 
 /// Seletores de segmento para code e TSS.
 struct Selectors {
@@ -53,13 +71,16 @@ struct Selectors {
 }
 
 lazy_static! {
-    /// TSS com stack dedicada para double faults (20KB).
+    /// TSS com stacks dedicadas para double faults e NMIs (20KB cada).
     static ref TSS: TaskStateSegment = {
         let mut tss = TaskStateSegment::new();
         tss.interrupt_stack_table[DOUBLE_FAULT_IST_INDEX as usize] = {
-            const STACK_SIZE: usize = 4096 * 5;
-            static mut STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
-            VirtAddr::from_ptr(&raw const STACK) + STACK_SIZE
+            static mut STACK: [u8; IST_STACK_SIZE] = [0; IST_STACK_SIZE];
+            VirtAddr::from_ptr(&raw const STACK) + IST_STACK_SIZE
+        };
+        tss.interrupt_stack_table[NMI_IST_INDEX as usize] = {
+            static mut STACK: [u8; IST_STACK_SIZE] = [0; IST_STACK_SIZE];
+            VirtAddr::from_ptr(&raw const STACK) + IST_STACK_SIZE
         };
         tss
     };
@@ -80,4 +101,34 @@ pub fn init() {
         CS::set_reg(GDT.1.code_selector);
         load_tss(GDT.1.tss_selector);
     }
-}
\ No newline at end of file
+}
+
+/// Retorna o topo da stack da IST `index` da BSP.
+pub fn bsp_ist_top(index: u16) -> VirtAddr {
+    TSS.interrupt_stack_table[index as usize]
+}
+
+/// Cria e carrega GDT e TSS próprios para uma application processor.
+///
+/// As tabelas e as stacks da IST vivem até o desligamento, então são
+/// deliberadamente vazadas.
+pub fn init_ap() {
+    let mut tss = TaskStateSegment::new();
+    for index in [DOUBLE_FAULT_IST_INDEX, NMI_IST_INDEX] {
+        let stack = Stack::allocate().expect("failed to allocate IST stack");
+        tss.interrupt_stack_table[index as usize] = stack.top();
+        core::mem::forget(stack);
+    }
+    let tss: &'static TaskStateSegment = Box::leak(Box::new(tss));
+
+    let mut gdt = GlobalDescriptorTable::new();
+    let code_selector = gdt.add_entry(Descriptor::kernel_code_segment());
+    let tss_selector = gdt.add_entry(Descriptor::tss_segment(tss));
+    let gdt: &'static GlobalDescriptorTable = Box::leak(Box::new(gdt));
+
+    gdt.load();
+    unsafe {
+        CS::set_reg(code_selector);
+        load_tss(tss_selector);
+    }
+}
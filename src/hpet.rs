@@ -0,0 +1,450 @@
+//! # HPET (High Precision Event Timer)
+//!
+//! ## O que é o HPET?
+//!
+//! Um contador de 64 bits (às vezes 32) que sobe a uma frequência fixa,
+//! normalmente 10-25 MHz, mais um conjunto de **comparadores**: cada um
+//! gera uma interrupção quando o contador alcança o valor programado,
+//! uma vez (one-shot) ou a cada período (periódico).
+//!
+//! Diferente do TSC, a frequência é informada pelo próprio hardware
+//! (período em femtossegundos), então o HPET serve de referência para
+//! calibrar os outros timers: `time::calibrate_tsc()` e
+//! `apic::calibrate_timer()` o usam quando ele está disponível.
+//!
+//! ## Registradores (MMIO)
+//!
+//! O endereço vem da tabela ACPI "HPET" (`acpi::hpet`):
+//!
+//! ```text
+//! 0x000        capacidades: período (fs) nos bits 63:32, nº de comparadores
+//! 0x010        configuração: bit 0 liga o contador
+//! 0x0F0        contador principal
+//! 0x100+0x20n  configuração do comparador n
+//! 0x108+0x20n  valor do comparador n
+//! 0x110+0x20n  rota FSB do comparador n (endereço e dado da MSI)
+//! ```
+//!
+//! ## Interrupções dos comparadores
+//!
+//! O kernel não programa o I/O APIC, e o modo "legacy replacement" tomaria
+//! as IRQs 0 e 8 do PIT e do RTC. Por isso os comparadores entregam suas
+//! interrupções por **FSB**: o HPET escreve uma mensagem (MSI) direto no
+//! Local APIC da CPU que programou o comparador, no vetor
+//! `VECTOR_BASE + n`:
+//!
+//! ```text
+//! contador == comparador n ──→ escrita em 0xFEE0_0000 | apic_id << 12
+//!                              └─→ Local APIC ─→ IDT[VECTOR_BASE + n]
+//! ```
+//!
+//! Só comparadores com capacidade FSB podem ser reivindicados; sem
+//! nenhum, `Comparator::claim` retorna `HpetError::FsbUnsupported`. O
+//! QEMU só anuncia FSB com `-global hpet.msi=on`, que não é o padrão. A
+//! entrega pelo I/O APIC (o roteamento de cada comparador) ainda não
+//! existe.
+//!
+//! ```ignore
+//! let comparator = hpet::Comparator::claim(handler)?;
+//! comparator.periodic_ns(1_000_000)?; // handler a cada 1 ms
+//! // ...
+//! drop(comparator); // para o comparador e libera o vetor
+//! ```
+//!
+//! ## Referências
+//!
+//! - [HPET](https://wiki.osdev.org/HPET) - OSDev Wiki
+//! - IA-PC HPET Specification 1.0a
+
+use crate::{
+    acpi::{self, AcpiError},
+    apic, memory, percpu,
+};
+use core::sync::atomic::{AtomicBool, AtomicU16, AtomicU64, AtomicUsize, Ordering};
+use spin::RwLock;
+use x86_64::{
+    instructions::interrupts,
+    set_general_handler,
+    structures::idt::{InterruptDescriptorTable, InterruptStackFrame},
+    PhysAddr,
+};
+
+/// Primeiro vetor das interrupções dos comparadores.
+pub const VECTOR_BASE: u8 = 0xE0;
+/// Comparadores utilizáveis (um vetor para cada).
+pub const MAX_COMPARATORS: usize = 8;
+
+/// Offsets dos registradores do HPET.
+mod reg {
+    pub const CAPABILITIES: u64 = 0x000;
+    pub const CONFIG: u64 = 0x010;
+    pub const MAIN_COUNTER: u64 = 0x0F0;
+
+    pub const fn timer_config(n: usize) -> u64 {
+        0x100 + 0x20 * n as u64
+    }
+
+    pub const fn timer_comparator(n: usize) -> u64 {
+        0x108 + 0x20 * n as u64
+    }
+
+    pub const fn timer_fsb_route(n: usize) -> u64 {
+        0x110 + 0x20 * n as u64
+    }
+}
+
+/// Tamanho do bloco de registradores.
+const REGISTERS_SIZE: u64 = 0x400;
+
+/// Bits do registrador de capacidades.
+const CAP_COUNTER_64: u64 = 1 << 13;
+/// Bit que liga o contador principal.
+const CONFIG_ENABLE: u64 = 1 << 0;
+
+/// Bits da configuração de um comparador.
+const TIMER_INT_ENABLE: u64 = 1 << 2;
+const TIMER_PERIODIC: u64 = 1 << 3;
+const TIMER_PERIODIC_CAP: u64 = 1 << 4;
+const TIMER_VALUE_SET: u64 = 1 << 6;
+const TIMER_FSB_ENABLE: u64 = 1 << 14;
+const TIMER_FSB_CAP: u64 = 1 << 15;
+
+/// Maior período permitido pela especificação (100 ns).
+const MAX_PERIOD_FS: u64 = 100_000_000;
+const FS_PER_NS: u64 = 1_000_000;
+/// Endereço das mensagens MSI para o Local APIC.
+const MSI_ADDRESS_BASE: u64 = 0xFEE0_0000;
+
+/// Erros do HPET.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HpetError {
+    /// O ACPI não descreve um HPET utilizável (tabela ausente, inválida
+    /// ou com o HPET em portas de I/O).
+    Acpi(AcpiError),
+    /// O período informado pelo hardware é inválido (em fs).
+    InvalidPeriod(u64),
+    /// `hpet::init` não foi chamado.
+    NotInitialized,
+    /// Os registradores estão fora do offset mapping (ver
+    /// `memory::is_phys_mapped`).
+    Unmapped(PhysAddr),
+    /// Nenhum comparador suporta entrega por FSB.
+    FsbUnsupported,
+    /// Todos os comparadores com entrega por FSB já foram reivindicados.
+    NoFreeComparator,
+    /// O comparador não suporta o modo periódico.
+    PeriodicUnsupported,
+    /// Período menor que o mínimo da tabela ACPI.
+    PeriodTooShort { minimum_ns: u64 },
+}
+
+/// Endereço virtual dos registradores (0 = HPET não inicializado).
+static BASE: AtomicU64 = AtomicU64::new(0);
+/// Período do contador em femtossegundos.
+static PERIOD_FS: AtomicU64 = AtomicU64::new(0);
+/// Máscara do contador (32 ou 64 bits).
+static COUNTER_MASK: AtomicU64 = AtomicU64::new(0);
+static MINIMUM_TICK: AtomicU16 = AtomicU16::new(0);
+static COMPARATORS: AtomicUsize = AtomicUsize::new(0);
+
+fn base() -> u64 {
+    let base = BASE.load(Ordering::Relaxed);
+    assert!(base != 0, "HPET not initialized");
+    base
+}
+
+fn read(offset: u64) -> u64 {
+    let ptr = (base() + offset) as *const u64;
+    unsafe { ptr.read_volatile() }
+}
+
+fn write(offset: u64, value: u64) {
+    let ptr = (base() + offset) as *mut u64;
+    unsafe { ptr.write_volatile(value) }
+}
+
+/// Localiza o HPET pelo ACPI e liga o contador principal.
+///
+/// Requer `memory::init` e o heap (para percorrer as tabelas ACPI).
+pub fn init() -> Result<(), HpetError> {
+    let table = acpi::hpet().map_err(HpetError::Acpi)?;
+    if !memory::is_phys_mapped(table.base_address, REGISTERS_SIZE) {
+        return Err(HpetError::Unmapped(table.base_address));
+    }
+    let base = memory::phys_to_virt(table.base_address).as_u64();
+    let capabilities = unsafe { ((base + reg::CAPABILITIES) as *const u64).read_volatile() };
+
+    let period = capabilities >> 32;
+    if period == 0 || period > MAX_PERIOD_FS {
+        return Err(HpetError::InvalidPeriod(period));
+    }
+    let mask = if capabilities & CAP_COUNTER_64 != 0 {
+        u64::MAX
+    } else {
+        u64::from(u32::MAX)
+    };
+    let comparators = ((capabilities >> 8) & 0x1F) as usize + 1;
+
+    PERIOD_FS.store(period, Ordering::Relaxed);
+    COUNTER_MASK.store(mask, Ordering::Relaxed);
+    MINIMUM_TICK.store(table.minimum_tick, Ordering::Relaxed);
+    COMPARATORS.store(comparators.min(MAX_COMPARATORS), Ordering::Relaxed);
+    BASE.store(base, Ordering::Release);
+
+    for n in 0..comparators {
+        let config = read(reg::timer_config(n));
+        write(
+            reg::timer_config(n),
+            config & !(TIMER_INT_ENABLE | TIMER_FSB_ENABLE),
+        );
+    }
+    write(reg::CONFIG, read(reg::CONFIG) | CONFIG_ENABLE);
+    Ok(())
+}
+
+/// Retorna se o HPET já foi inicializado.
+pub fn is_initialized() -> bool {
+    BASE.load(Ordering::Acquire) != 0
+}
+
+/// Período do contador em femtossegundos, se inicializado.
+pub fn period_fs() -> Option<u64> {
+    match PERIOD_FS.load(Ordering::Relaxed) {
+        0 => None,
+        period => Some(period),
+    }
+}
+
+/// Frequência do contador em Hz, se inicializado.
+pub fn frequency() -> Option<u64> {
+    period_fs().map(|period| 1_000_000_000_000_000 / period)
+}
+
+/// Comparadores utilizáveis (limitado a `MAX_COMPARATORS`).
+pub fn comparator_count() -> usize {
+    COMPARATORS.load(Ordering::Relaxed)
+}
+
+/// Valor atual do contador principal.
+///
+/// Em HPETs de 32 bits o valor dá a volta (a ~14 MHz, a cada ~5 minutos);
+/// use `elapsed` para diferenças.
+///
+/// # Panics
+/// Entra em panic se o HPET não foi inicializado.
+pub fn counter() -> u64 {
+    read(reg::MAIN_COUNTER) & COUNTER_MASK.load(Ordering::Relaxed)
+}
+
+/// Ticks do contador desde `start` (considerando a volta do contador).
+pub fn elapsed(start: u64) -> u64 {
+    counter().wrapping_sub(start) & COUNTER_MASK.load(Ordering::Relaxed)
+}
+
+/// Converte ticks do contador em nanossegundos.
+pub fn ticks_to_ns(ticks: u64) -> u64 {
+    (u128::from(ticks) * u128::from(PERIOD_FS.load(Ordering::Relaxed)) / u128::from(FS_PER_NS))
+        as u64
+}
+
+/// Converte nanossegundos em ticks do contador (arredondando para cima).
+///
+/// # Panics
+/// Entra em panic se o HPET não foi inicializado.
+pub fn ns_to_ticks(ns: u64) -> u64 {
+    let period = period_fs().expect("HPET not initialized");
+    (u128::from(ns) * u128::from(FS_PER_NS)).div_ceil(u128::from(period)) as u64
+}
+
+/// Nanossegundos desde que o contador foi ligado (volta em HPETs de 32 bits).
+pub fn nanos() -> u64 {
+    ticks_to_ns(counter())
+}
+
+/// Espera ativa de `us` microssegundos, medida pelo HPET.
+pub fn delay_us(us: u64) {
+    let ticks = ns_to_ticks(us * 1000);
+    let start = counter();
+    while elapsed(start) < ticks {
+        core::hint::spin_loop();
+    }
+}
+
+/// Mede a frequência (Hz) de um contador crescente durante `window_us`.
+///
+/// Interrupções ficam desabilitadas durante a janela, para que um
+/// handler não atrase só uma das leituras.
+pub fn measure_frequency(window_us: u64, mut read_counter: impl FnMut() -> u64) -> u64 {
+    let window = ns_to_ticks(window_us * 1000);
+    interrupts::without_interrupts(|| {
+        let start = counter();
+        let first = read_counter();
+        let mut ticks = 0;
+        while ticks < window {
+            ticks = elapsed(start);
+        }
+        let count = read_counter().wrapping_sub(first);
+        (u128::from(count) * 1_000_000_000_000_000
+            / (u128::from(ticks) * u128::from(PERIOD_FS.load(Ordering::Relaxed)))) as u64
+    })
+}
+
+/// Estado de um vetor de comparador.
+struct Slot {
+    claimed: AtomicBool,
+    handler: RwLock<Option<fn(&InterruptStackFrame)>>,
+    fired: AtomicU64,
+}
+
+static SLOTS: [Slot; MAX_COMPARATORS] = [const {
+    Slot {
+        claimed: AtomicBool::new(false),
+        handler: RwLock::new(None),
+        fired: AtomicU64::new(0),
+    }
+}; MAX_COMPARATORS];
+
+/// Um comparador reivindicado; é parado e liberado no drop.
+#[derive(Debug)]
+pub struct Comparator {
+    index: usize,
+}
+
+impl Comparator {
+    /// Reivindica um comparador livre com entrega por FSB e instala
+    /// `handler`. As interrupções vão para a CPU atual.
+    pub fn claim(handler: fn(&InterruptStackFrame)) -> Result<Comparator, HpetError> {
+        if !is_initialized() {
+            return Err(HpetError::NotInitialized);
+        }
+        let mut fsb_capable = false;
+        for (index, slot) in SLOTS.iter().enumerate().take(comparator_count()) {
+            if read(reg::timer_config(index)) & TIMER_FSB_CAP == 0 {
+                continue;
+            }
+            fsb_capable = true;
+            if slot.claimed.swap(true, Ordering::Acquire) {
+                continue;
+            }
+            interrupts::without_interrupts(|| *slot.handler.write() = Some(handler));
+            slot.fired.store(0, Ordering::Relaxed);
+
+            let address = MSI_ADDRESS_BASE | u64::from(apic::id()) << 12;
+            let data = u64::from(VECTOR_BASE) + index as u64;
+            write(reg::timer_fsb_route(index), address << 32 | data);
+            let config = read(reg::timer_config(index));
+            write(
+                reg::timer_config(index),
+                (config & !(TIMER_INT_ENABLE | TIMER_PERIODIC)) | TIMER_FSB_ENABLE,
+            );
+            return Ok(Comparator { index });
+        }
+        if fsb_capable {
+            Err(HpetError::NoFreeComparator)
+        } else {
+            Err(HpetError::FsbUnsupported)
+        }
+    }
+
+    /// Índice do comparador no HPET.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Vetor em que as interrupções chegam.
+    pub fn vector(&self) -> u8 {
+        VECTOR_BASE + self.index as u8
+    }
+
+    /// Retorna se o comparador suporta o modo periódico.
+    pub fn supports_periodic(&self) -> bool {
+        read(reg::timer_config(self.index)) & TIMER_PERIODIC_CAP != 0
+    }
+
+    /// Interrupções entregues desde o `claim`.
+    pub fn fired(&self) -> u64 {
+        SLOTS[self.index].fired.load(Ordering::Relaxed)
+    }
+
+    /// Dispara uma vez quando o contador alcançar `deadline`.
+    pub fn oneshot_at(&self, deadline: u64) {
+        let config = read(reg::timer_config(self.index)) & !TIMER_PERIODIC;
+        write(reg::timer_config(self.index), config & !TIMER_INT_ENABLE);
+        write(reg::timer_comparator(self.index), deadline);
+        write(reg::timer_config(self.index), config | TIMER_INT_ENABLE);
+    }
+
+    /// Dispara uma vez daqui a `ns` nanossegundos.
+    pub fn oneshot_ns(&self, ns: u64) {
+        let deadline =
+            counter().wrapping_add(ns_to_ticks(ns)) & COUNTER_MASK.load(Ordering::Relaxed);
+        self.oneshot_at(deadline);
+    }
+
+    /// Dispara a cada `ns` nanossegundos.
+    pub fn periodic_ns(&self, ns: u64) -> Result<(), HpetError> {
+        if !self.supports_periodic() {
+            return Err(HpetError::PeriodicUnsupported);
+        }
+        let period = ns_to_ticks(ns);
+        let minimum = u64::from(MINIMUM_TICK.load(Ordering::Relaxed));
+        if period < minimum.max(1) {
+            return Err(HpetError::PeriodTooShort {
+                minimum_ns: ticks_to_ns(minimum.max(1)),
+            });
+        }
+
+        let config = read(reg::timer_config(self.index));
+        write(reg::timer_config(self.index), config & !TIMER_INT_ENABLE);
+        // Com VALUE_SET, a primeira escrita define o próximo disparo e a
+        // segunda o período que é somado a cada disparo
+        write(
+            reg::timer_config(self.index),
+            config | TIMER_PERIODIC | TIMER_VALUE_SET | TIMER_INT_ENABLE,
+        );
+        write(
+            reg::timer_comparator(self.index),
+            counter().wrapping_add(period),
+        );
+        write(reg::timer_comparator(self.index), period);
+        Ok(())
+    }
+
+    /// Para o comparador (o handler continua instalado).
+    pub fn stop(&self) {
+        let config = read(reg::timer_config(self.index));
+        write(
+            reg::timer_config(self.index),
+            config & !(TIMER_INT_ENABLE | TIMER_PERIODIC),
+        );
+    }
+}
+
+impl Drop for Comparator {
+    fn drop(&mut self) {
+        self.stop();
+        let config = read(reg::timer_config(self.index));
+        write(reg::timer_config(self.index), config & !TIMER_FSB_ENABLE);
+        let slot = &SLOTS[self.index];
+        interrupts::without_interrupts(|| *slot.handler.write() = None);
+        slot.claimed.store(false, Ordering::Release);
+    }
+}
+
+/// Instala o stub dos comparadores nos vetores `VECTOR_BASE..`.
+pub(crate) fn install_stubs(idt: &mut InterruptDescriptorTable) {
+    let vectors = VECTOR_BASE..VECTOR_BASE + MAX_COMPARATORS as u8;
+    set_general_handler!(idt, dispatch, vectors);
+}
+
+/// Stub comum das interrupções dos comparadores (entregues pelo Local APIC).
+fn dispatch(stack_frame: InterruptStackFrame, vector: u8, _error_code: Option<u64>) {
+    percpu::enter_interrupt();
+    let slot = &SLOTS[(vector - VECTOR_BASE) as usize];
+    slot.fired.fetch_add(1, Ordering::Relaxed);
+    if let Some(handler) = *slot.handler.read() {
+        handler(&stack_frame);
+    }
+    apic::end_of_interrupt();
+    percpu::exit_interrupt();
+}
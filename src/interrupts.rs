@@ -30,22 +30,28 @@
 //! Hardware/CPU → Interrupção N → IDT[N] → Handler → EOI → Retorna
 //! ```
 //!
+//! As IRQs de hardware (vetores 32-47) passam pelo registro dinâmico em
+//! [`irq`]: drivers reivindicam uma linha com `irq::register()` sem editar
+//! este arquivo.
+//!
 //! ## Estudo baseado em
 //!
 //! - [CPU Exceptions](https://os.phil-opp.com/cpu-exceptions/)
 //! - [Hardware Interrupts](https://os.phil-opp.com/hardware-interrupts/)
 
-use crate::{gdt, hlt_loop, print, println};
+use crate::{backtrace, gdt, hlt_loop, print, println};
+use deferred::WorkItem;
 use lazy_static::lazy_static;
-use pc_keyboard::{layouts, DecodedKey, HandleControl, Keyboard, ScancodeSet1};
 use pic8259::ChainedPics;
-use spin::Mutex;
 use x86_64::{
     instructions::port::Port,
     registers::control::Cr2,
     structures::idt::{InterruptDescriptorTable, InterruptStackFrame, PageFaultErrorCode},
 };
 
+pub mod deferred;
+pub mod irq;
+
 
 /// Índices das interrupções de hardware (IRQs remapeadas).
 #[derive(Debug, Clone, Copy)]
@@ -67,18 +73,36 @@ impl From<InterruptIndex> for usize {
     }
 }
 
+impl InterruptIndex {
+    /// Linha de IRQ no PIC correspondente ao vetor.
+    pub fn as_irq(self) -> u8 {
+        self as u8 - PIC_1_OFFSET
+    }
+}
+
 
 extern "x86-interrupt" fn page_fault_handler(
     stack_frame: InterruptStackFrame,
     error_code: PageFaultErrorCode
 ) {
+    let rbp = backtrace::frame_pointer();
     println!("EXCEPTION: PAGE FAULT");
     println!("Accessed Address: {:?}", Cr2::read());
     println!("Error code: {:?}", error_code);
     println!("{:#?}", stack_frame);
+    print_exception_backtrace(&stack_frame, rbp);
     hlt_loop();
 }
 
+/// Imprime o backtrace do código interrompido por uma exceção.
+///
+/// `handler_rbp` deve ser lido com `backtrace::frame_pointer()` no próprio
+/// handler, antes de chamar outras funções.
+fn print_exception_backtrace(stack_frame: &InterruptStackFrame, handler_rbp: u64) {
+    println!("Backtrace:");
+    backtrace::trace_exception(stack_frame, handler_rbp, |frame| println!("  {}", frame));
+}
+
 
 // ============================================================================
 // PICs 8259
@@ -103,7 +127,39 @@ pub static PICS: spin::Mutex<ChainedPics> =
 
 /// Handler para exceção de breakpoint (int3).
 extern "x86-interrupt" fn breakpoint_handler(stack_frame: InterruptStackFrame) {
+    let rbp = backtrace::frame_pointer();
     println!("EXCEPTION: BREAKPOINT\n{:#?}", stack_frame);
+    print_exception_backtrace(&stack_frame, rbp);
+}
+
+/// Handler de NMI - usa stack própria (IST) e delega ao watchdog.
+///
+/// Não pode usar `println!`: a NMI pode interromper código que segura o
+/// `WRITER`.
+extern "x86-interrupt" fn nmi_handler(stack_frame: InterruptStackFrame) {
+    let rbp = backtrace::frame_pointer();
+    crate::watchdog::handle_nmi(&stack_frame, rbp);
+}
+
+/// Handler para interrupções espúrias do Local APIC (sem EOI).
+extern "x86-interrupt" fn apic_spurious_handler(_stack_frame: InterruptStackFrame) {}
+
+/// Handler da IPI de wakeup: só interrompe o HLT da CPU (ver `smp::wake`).
+extern "x86-interrupt" fn wakeup_handler(_stack_frame: InterruptStackFrame) {
+    crate::apic::end_of_interrupt();
+}
+
+/// Handler das chamadas entre CPUs (ver `ipi::call`).
+extern "x86-interrupt" fn ipi_call_handler(_stack_frame: InterruptStackFrame) {
+    crate::ipi::handle_call();
+}
+
+/// Handler de `#NM` (device not available): troca o estado da FPU sob
+/// demanda (ver `fpu`).
+extern "x86-interrupt" fn device_not_available_handler(stack_frame: InterruptStackFrame) {
+    if !crate::fpu::handle_device_not_available() {
+        panic!("EXCEPTION: DEVICE NOT AVAILABLE\n{:#?}", stack_frame);
+    }
 }
 
 /// Handler para double fault - usa stack separada (IST) para evitar triple fault.
@@ -111,6 +167,12 @@ extern "x86-interrupt" fn double_fault_handler(
     stack_frame: InterruptStackFrame,
     _error_code: u64,
 ) -> ! {
+    let rbp = backtrace::frame_pointer();
+    let fault_addr = Cr2::read();
+    if crate::thread::stack::is_guard_page(fault_addr) {
+        println!("Kernel thread stack overflow (guard page at {:?})", fault_addr);
+    }
+    print_exception_backtrace(&stack_frame, rbp);
     panic!("EXCEPTION: DOUBLE FAULT\n{:#?}", stack_frame);
 }
 
@@ -118,44 +180,29 @@ extern "x86-interrupt" fn double_fault_handler(
 // Hardware Interrupt Handlers
 // ============================================================================
 
-/// Handler do timer (IRQ 0) - imprime um ponto a cada tick.
-extern "x86-interrupt" fn timer_interrupt_handler(_stack_frame: InterruptStackFrame) {
-    print!(".");
-
-    unsafe {
-        PICS.lock()
-            .notify_end_of_interrupt(InterruptIndex::Timer.into());
-    }
+/// Handler do timer (IRQ 0) - conta o tick, procura tasks travadas em
+/// `poll` e adia a impressão do ponto.
+///
+/// O EOI é enviado pelo stub comum em `irq::dispatch`.
+fn timer_interrupt_handler(stack_frame: &InterruptStackFrame) {
+    crate::time::tick();
+    crate::thread::tick();
+    crate::task::slow_poll::check_hung_poll(stack_frame);
+    let _ = deferred::schedule(WorkItem::new(print_tick, 0));
 }
 
-/// Handler do teclado (IRQ 1) - lê scancode e imprime caractere.
-extern "x86-interrupt" fn keyboard_interrupt_handler(_stack_frame: InterruptStackFrame) {
-
-    lazy_static! {
-        static ref KEYBOARD: Mutex<Keyboard<layouts::Us104Key, ScancodeSet1>> = Mutex::new(
-            Keyboard::new(ScancodeSet1::new(), layouts::Us104Key, HandleControl::Ignore)
-        );
-    }
+/// Bottom half do timer: imprime um ponto fora do contexto de interrupção.
+fn print_tick(_: usize) {
+    print!(".");
+}
 
-    let mut keyboard = KEYBOARD.lock();
+/// Handler do teclado (IRQ 1) - lê o scancode e o entrega à task de teclado.
+///
+/// A decodificação e a impressão acontecem em `task::keyboard`.
+fn keyboard_interrupt_handler(_stack_frame: &InterruptStackFrame) {
     let mut port = Port::new(0x60);
-
     let scancode: u8 = unsafe { port.read() };
-    if let Ok(Some(key_event)) = keyboard.add_byte(scancode) {
-        if let Some(key) = keyboard.process_keyevent(key_event) {
-            match key {
-                DecodedKey::Unicode(character) => print!("{}", character),
-                DecodedKey::RawKey(key) => print!("{:?}", key),
-            }
-        }
-    }
-
     crate::task::keyboard::add_scancode(scancode);
-
-    unsafe {
-        PICS.lock()
-            .notify_end_of_interrupt(InterruptIndex::Keyboard.into());
-    }
 }
 
 // ============================================================================
@@ -172,9 +219,18 @@ lazy_static! {
                 .set_handler_fn(double_fault_handler)
                 .set_stack_index(gdt::DOUBLE_FAULT_IST_INDEX);
         }
-        idt[InterruptIndex::Timer.into()].set_handler_fn(timer_interrupt_handler);
-        idt[InterruptIndex::Keyboard.into()].set_handler_fn(keyboard_interrupt_handler);
+        unsafe {
+            idt.non_maskable_interrupt
+                .set_handler_fn(nmi_handler)
+                .set_stack_index(gdt::NMI_IST_INDEX);
+        }
+        irq::install_stubs(&mut idt);
+        crate::hpet::install_stubs(&mut idt);
+        idt[crate::apic::SPURIOUS_VECTOR as usize].set_handler_fn(apic_spurious_handler);
+        idt[crate::apic::WAKEUP_VECTOR as usize].set_handler_fn(wakeup_handler);
+        idt[crate::ipi::CALL_VECTOR as usize].set_handler_fn(ipi_call_handler);
         idt.page_fault.set_handler_fn(page_fault_handler);
+        idt.device_not_available.set_handler_fn(device_not_available_handler);
         idt
     };
 }
@@ -184,8 +240,26 @@ pub fn init_idt() {
     IDT.load();
 }
 
+/// Inicializa os PICs, mascara linhas sem handler e registra timer e teclado.
+pub fn init_pics() {
+    unsafe { PICS.lock().initialize() };
+    irq::init();
+    irq::register(InterruptIndex::Timer.as_irq(), "timer", timer_interrupt_handler)
+        .expect("timer IRQ already claimed");
+    irq::register(InterruptIndex::Keyboard.as_irq(), "keyboard", keyboard_interrupt_handler)
+        .expect("keyboard IRQ already claimed");
+}
+
 /// Testa se breakpoint exception é tratada corretamente.
 #[test_case]
 fn test_breakpoint_exception() {
    x86_64::instructions::interrupts::int3();
+}
+
+/// Testa se o handler de NMI roda na stack da IST e retorna.
+#[test_case]
+fn test_nmi_handler_returns() {
+    let before = crate::watchdog::nmi_count();
+    unsafe { core::arch::asm!("int 2") };
+    assert_eq!(crate::watchdog::nmi_count(), before + 1);
 }
\ No newline at end of file
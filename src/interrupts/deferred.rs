@@ -0,0 +1,132 @@
+//! # Trabalho Adiado (Bottom Halves)
+//!
+//! ## Por que adiar trabalho?
+//!
+//! Handlers de IRQ rodam com interrupções desabilitadas e podem interromper
+//! código que segura locks como o `WRITER` do VGA. Imprimir ou decodificar
+//! dados dentro do handler arrisca deadlocks e aumenta a latência de todas
+//! as outras interrupções.
+//!
+//! A solução clássica é dividir o tratamento em duas metades:
+//!
+//! - **Top half**: O handler de IRQ reconhece o dispositivo, copia o mínimo
+//!   necessário e enfileira um [`WorkItem`].
+//! - **Bottom half**: A task [`process_deferred_work`] executa os itens
+//!   com interrupções habilitadas, fora do contexto de interrupção.
+//!
+//! ```text
+//! IRQ → handler → deferred::schedule(item) → WAKER.wake() → EOI
+//!                                                │
+//!                                                v
+//!                       Executor → process_deferred_work() → item.run()
+//! ```
+//!
+//! ## Por que `fn(usize)`?
+//!
+//! Um `WorkItem` é só um ponteiro de função e um argumento: é `Copy`, não
+//! aloca e cabe na `ArrayQueue` lock-free, podendo ser criado em qualquer
+//! handler sem tocar no allocator.
+
+use crate::task::coop;
+use conquer_once::spin::OnceCell;
+use core::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{ready, Context, Poll},
+};
+use crossbeam_queue::ArrayQueue;
+use futures_util::task::AtomicWaker;
+
+/// Capacidade da fila de trabalho adiado.
+const QUEUE_SIZE: usize = 256;
+
+/// Fila de itens pendentes. Inicializada pela task consumidora.
+static WORK_QUEUE: OnceCell<ArrayQueue<WorkItem>> = OnceCell::uninit();
+
+/// Waker da task que processa a fila.
+static WAKER: AtomicWaker = AtomicWaker::new();
+
+/// Itens descartados porque a fila estava cheia ou não inicializada.
+static DROPPED: AtomicU64 = AtomicU64::new(0);
+
+/// Unidade de trabalho adiado: função + argumento.
+#[derive(Debug, Clone, Copy)]
+pub struct WorkItem {
+    func: fn(usize),
+    arg: usize,
+}
+
+impl WorkItem {
+    /// Cria um item que chamará `func(arg)` fora do contexto de interrupção.
+    pub const fn new(func: fn(usize), arg: usize) -> Self {
+        WorkItem { func, arg }
+    }
+
+    fn run(self) {
+        (self.func)(self.arg)
+    }
+}
+
+/// Enfileira um item de trabalho. Seguro em contexto de interrupção.
+///
+/// Retorna o item de volta se a fila estiver cheia ou se a task
+/// consumidora ainda não foi iniciada.
+pub fn schedule(item: WorkItem) -> Result<(), WorkItem> {
+    let result = match WORK_QUEUE.try_get() {
+        Ok(queue) => queue.push(item),
+        Err(_) => Err(item),
+    };
+    match result {
+        Ok(()) => WAKER.wake(),
+        Err(_) => {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    result
+}
+
+/// Número de itens descartados desde o boot.
+pub fn dropped() -> u64 {
+    DROPPED.load(Ordering::Relaxed)
+}
+
+/// Future que nunca completa e executa os itens conforme chegam.
+struct DeferredWork {
+    queue: &'static ArrayQueue<WorkItem>,
+}
+
+impl Future for DeferredWork {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        loop {
+            while !self.queue.is_empty() {
+                // Muitos itens seguidos não podem monopolizar o executor
+                ready!(coop::poll_proceed(cx));
+                if let Some(item) = self.queue.pop() {
+                    item.run();
+                }
+            }
+
+            // Registra o waker e verifica de novo para não perder um item
+            // enfileirado entre o último pop e o registro
+            WAKER.register(cx.waker());
+            if self.queue.is_empty() {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+/// Task que executa o trabalho adiado pelos handlers de interrupção.
+///
+/// # Panics
+/// Entra em panic se chamada mais de uma vez.
+pub async fn process_deferred_work() {
+    WORK_QUEUE
+        .try_init_once(|| ArrayQueue::new(QUEUE_SIZE))
+        .expect("process_deferred_work should only be called once");
+    let queue = WORK_QUEUE.try_get().expect("not initialized");
+    DeferredWork { queue }.await
+}
@@ -0,0 +1,433 @@
+//! # Registro Dinâmico de IRQs
+//!
+//! ## Por que um registro?
+//!
+//! Antes, cada dispositivo precisava de um handler `extern "x86-interrupt"`
+//! próprio, escrito direto na IDT de `interrupts.rs`. Com o registro, um
+//! driver só precisa **reivindicar** uma linha de IRQ e instalar uma função
+//! ou closure Rust comum:
+//!
+//! ```text
+//! irq::register(4, "serial", serial_handler)?;        // fn(&InterruptStackFrame)
+//! irq::register_closure(11, "nic", move |_| { ... })?; // closure (requer heap)
+//! ```
+//!
+//! ## Stub comum
+//!
+//! Todos os vetores 32-47 apontam para stubs gerados por
+//! `set_general_handler!`, que chamam `dispatch()` com o número do vetor:
+//!
+//! ```text
+//! IRQ N → IDT[32 + N] → stub → dispatch(N)
+//!                                  │
+//!                                  ├─ percpu::enter_interrupt() (profundidade, contador da CPU)
+//!                                  ├─ spurious? (ISR do PIC) → conta e retorna
+//!                                  ├─ handler registrado → chama e mede latência
+//!                                  ├─ sem handler → conta e mascara a linha
+//!                                  ├─ EOI, percpu::exit_interrupt()
+//!                                  └─ thread::preempt() (troca de thread pedida pelo timer)
+//! ```
+//!
+//! O stub cuida de detecção de IRQs espúrias, EOI e máscara. Registrar
+//! desmascara a linha no PIC; remover o registro volta a mascará-la.
+//!
+//! ## IRQs espúrias
+//!
+//! O PIC sinaliza IRQ 7 (master) ou IRQ 15 (slave) quando uma interrupção
+//! some antes de ser reconhecida. Nesse caso o bit correspondente no ISR
+//! (In-Service Register) está zerado e **não** devemos enviar EOI ao PIC
+//! que gerou a IRQ espúria (no caso do slave, o master ainda precisa de EOI
+//! pela linha de cascata).
+//!
+//! ## Estatísticas
+//!
+//! Cada vetor mantém contadores atômicos (total, espúrias, sem handler,
+//! tick da última ocorrência e latência máxima do handler em ciclos TSC).
+//! `report()` gera uma tabela no estilo do `/proc/interrupts` do Linux:
+//!
+//! ```text
+//! IRQ VEC      COUNT SPURIOUS UNHANDLED  LAST_TICK MAX_CYCLES HANDLER
+//!   0  32       1532        0         0       1532      48210 timer
+//!   1  33         12        0         0        988     102377 keyboard
+//! ```
+//!
+//! ## Linhas do APIC
+//!
+//! O registro cobre só as 16 linhas dos PICs 8259 encadeados (`IRQ_LINES`);
+//! linhas acima disso são recusadas com `IrqError::InvalidLine`. Rotear GSIs
+//! do I/O APIC (entradas da MADT, overrides de ISA) para vetores próprios
+//! ainda está pendente: quando existir, basta aumentar a tabela e trocar
+//! `vector()`/EOI pelo caminho do APIC, sem mudar a API dos drivers.
+//!
+//! ## Referências
+//!
+//! - [8259 PIC](https://wiki.osdev.org/8259_PIC) - OSDev Wiki
+
+use super::{PICS, PIC_1_OFFSET};
+use crate::{percpu, time};
+use alloc::boxed::Box;
+use core::{
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use spin::RwLock;
+use x86_64::{
+    instructions::{interrupts, port::Port},
+    set_general_handler,
+    structures::idt::{InterruptDescriptorTable, InterruptStackFrame},
+};
+
+/// Número de linhas de IRQ disponíveis com os PICs 8259 encadeados.
+pub const IRQ_LINES: usize = 16;
+
+/// Linha usada pelo master para encadear o slave (nunca gera interrupções).
+pub const CASCADE_IRQ: u8 = 2;
+
+/// Portas de comando dos PICs (master e slave).
+const PIC_1_COMMAND: u16 = 0x20;
+const PIC_2_COMMAND: u16 = 0xA0;
+/// OCW3: próxima leitura da porta de comando retorna o ISR.
+const OCW3_READ_ISR: u8 = 0x0B;
+
+/// Função ou closure que trata uma linha de IRQ.
+///
+/// Funções comuns não precisam de heap e podem ser registradas antes de
+/// `allocator::init_heap`. Closures são alocadas em um `Box` e nunca
+/// liberadas: o stub copia o handler para fora do lock antes de chamá-lo,
+/// então a closure precisa continuar válida mesmo após `unregister`.
+#[derive(Clone, Copy)]
+pub enum IrqHandlerFn {
+    Function(fn(&InterruptStackFrame)),
+    Closure(&'static (dyn Fn(&InterruptStackFrame) + Send + Sync)),
+}
+
+/// Handler instalado em uma linha de IRQ, com o nome do dispositivo.
+#[derive(Clone, Copy)]
+pub struct IrqHandler {
+    pub name: &'static str,
+    pub handler: IrqHandlerFn,
+}
+
+impl IrqHandler {
+    fn call(self, stack_frame: &InterruptStackFrame) {
+        match self.handler {
+            IrqHandlerFn::Function(handler) => handler(stack_frame),
+            IrqHandlerFn::Closure(handler) => handler(stack_frame),
+        }
+    }
+}
+
+/// Erros retornados pelo registro de IRQs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrqError {
+    /// A linha não existe no controlador atual.
+    InvalidLine(u8),
+    /// A linha é reservada (cascata) e não pode ser usada por drivers.
+    Reserved(u8),
+    /// Outro driver já reivindicou a linha.
+    AlreadyClaimed(u8),
+    /// Nenhum handler registrado na linha.
+    NotClaimed(u8),
+}
+
+/// Handlers registrados, indexados pela linha de IRQ.
+///
+/// `RwLock` permite que o stub leia o handler sem bloquear outros leitores.
+/// Escritas só acontecem com interrupções desabilitadas, então o stub nunca
+/// encontra o lock de escrita ocupado na mesma CPU. O stub solta o lock de
+/// leitura antes de chamar o handler, que pode então registrar ou remover o
+/// registro da própria linha (como `rtc::disable_periodic`).
+static HANDLERS: [RwLock<Option<IrqHandler>>; IRQ_LINES] = [const { RwLock::new(None) }; IRQ_LINES];
+
+/// Contadores de um vetor, atualizados pelo stub comum.
+struct VectorStats {
+    count: AtomicU64,
+    spurious: AtomicU64,
+    unhandled: AtomicU64,
+    last_tick: AtomicU64,
+    max_latency: AtomicU64,
+}
+
+impl VectorStats {
+    const fn new() -> Self {
+        VectorStats {
+            count: AtomicU64::new(0),
+            spurious: AtomicU64::new(0),
+            unhandled: AtomicU64::new(0),
+            last_tick: AtomicU64::new(0),
+            max_latency: AtomicU64::new(0),
+        }
+    }
+}
+
+static STATS: [VectorStats; IRQ_LINES] = [const { VectorStats::new() }; IRQ_LINES];
+
+/// Cópia das estatísticas de uma linha de IRQ em um instante.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IrqStats {
+    pub irq: u8,
+    pub vector: u8,
+    /// Total de interrupções recebidas (incluindo espúrias).
+    pub count: u64,
+    /// Interrupções espúrias detectadas pelo ISR (só IRQ 7 e 15).
+    pub spurious: u64,
+    /// Interrupções recebidas sem handler registrado.
+    pub unhandled: u64,
+    /// Tick do timer na última ocorrência.
+    pub last_tick: u64,
+    /// Maior tempo gasto no handler, em ciclos TSC.
+    pub max_latency_cycles: u64,
+}
+
+/// Retorna o vetor da IDT usado por uma linha de IRQ.
+pub fn vector(irq: u8) -> u8 {
+    PIC_1_OFFSET + irq
+}
+
+fn check_line(irq: u8) -> Result<(), IrqError> {
+    if irq as usize >= IRQ_LINES {
+        Err(IrqError::InvalidLine(irq))
+    } else if irq == CASCADE_IRQ {
+        Err(IrqError::Reserved(irq))
+    } else {
+        Ok(())
+    }
+}
+
+fn install(irq: u8, name: &'static str, handler: IrqHandlerFn) -> Result<(), IrqError> {
+    check_line(irq)?;
+    let handler = IrqHandler { name, handler };
+    interrupts::without_interrupts(|| {
+        let mut slot = HANDLERS[irq as usize].write();
+        if slot.is_some() {
+            return Err(IrqError::AlreadyClaimed(irq));
+        }
+        *slot = Some(handler);
+        Ok(())
+    })?;
+    unmask(irq);
+    Ok(())
+}
+
+/// Reivindica uma linha de IRQ com um handler de função e a desmascara.
+pub fn register(
+    irq: u8,
+    name: &'static str,
+    handler: fn(&InterruptStackFrame),
+) -> Result<(), IrqError> {
+    install(irq, name, IrqHandlerFn::Function(handler))
+}
+
+/// Reivindica uma linha de IRQ com uma closure e a desmascara.
+///
+/// Requer o heap inicializado.
+pub fn register_closure<F>(irq: u8, name: &'static str, handler: F) -> Result<(), IrqError>
+where
+    F: Fn(&InterruptStackFrame) + Send + Sync + 'static,
+{
+    let handler = Box::leak(Box::new(handler));
+    install(irq, name, IrqHandlerFn::Closure(handler))
+}
+
+/// Libera uma linha de IRQ, mascarando-a e devolvendo o handler anterior.
+pub fn unregister(irq: u8) -> Result<IrqHandler, IrqError> {
+    check_line(irq)?;
+    mask(irq);
+    interrupts::without_interrupts(|| HANDLERS[irq as usize].write().take())
+        .ok_or(IrqError::NotClaimed(irq))
+}
+
+/// Mascara (desabilita) uma linha de IRQ no PIC.
+pub fn mask(irq: u8) {
+    update_mask(irq, true);
+}
+
+/// Desmascara (habilita) uma linha de IRQ no PIC.
+pub fn unmask(irq: u8) {
+    update_mask(irq, false);
+}
+
+fn update_mask(irq: u8, masked: bool) {
+    if irq as usize >= IRQ_LINES {
+        return;
+    }
+    interrupts::without_interrupts(|| {
+        let mut pics = PICS.lock();
+        unsafe {
+            let mut masks = pics.read_masks();
+            let (pic, bit) = ((irq / 8) as usize, irq % 8);
+            if masked {
+                masks[pic] |= 1 << bit;
+            } else {
+                masks[pic] &= !(1 << bit);
+            }
+            pics.write_masks(masks[0], masks[1]);
+        }
+    });
+}
+
+/// Mascara todas as linhas exceto a cascata e as que já têm handler.
+///
+/// Chamado após `ChainedPics::initialize`, que restaura as máscaras
+/// deixadas pela BIOS.
+pub(super) fn init() {
+    interrupts::without_interrupts(|| unsafe { PICS.lock().disable() });
+    unmask(CASCADE_IRQ);
+    for irq in 0..IRQ_LINES as u8 {
+        if HANDLERS[irq as usize].read().is_some() {
+            unmask(irq);
+        }
+    }
+}
+
+/// Instala o stub comum em todos os vetores remapeados (32-47).
+pub(super) fn install_stubs(idt: &mut InterruptDescriptorTable) {
+    set_general_handler!(idt, dispatch, 32..48);
+}
+
+/// Lê o ISR (In-Service Register) do PIC master ou slave.
+fn read_isr(command_port: u16) -> u8 {
+    let mut port: Port<u8> = Port::new(command_port);
+    unsafe {
+        port.write(OCW3_READ_ISR);
+        port.read()
+    }
+}
+
+/// Verifica se a IRQ 7/15 é espúria e envia o EOI necessário nesse caso.
+///
+/// Retorna `true` se a interrupção deve ser ignorada.
+fn handle_spurious(irq: u8) -> bool {
+    match irq {
+        7 => read_isr(PIC_1_COMMAND) & (1 << 7) == 0,
+        15 if read_isr(PIC_2_COMMAND) & (1 << 7) == 0 => {
+            // O master viu a IRQ 2 (cascata) e espera seu EOI
+            unsafe { PICS.lock().notify_end_of_interrupt(vector(CASCADE_IRQ)) };
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Stub comum chamado para todos os vetores 32-47.
+fn dispatch(stack_frame: InterruptStackFrame, index: u8, _error_code: Option<u64>) {
+    percpu::enter_interrupt();
+    let irq = index - PIC_1_OFFSET;
+    let stats = &STATS[irq as usize];
+    stats.count.fetch_add(1, Ordering::Relaxed);
+    stats.last_tick.store(time::ticks(), Ordering::Relaxed);
+
+    if handle_spurious(irq) {
+        stats.spurious.fetch_add(1, Ordering::Relaxed);
+        percpu::exit_interrupt();
+        return;
+    }
+
+    // Cópia feita com o guard já solto: o handler pode mexer na própria linha
+    let handler = *HANDLERS[irq as usize].read();
+    match handler {
+        Some(handler) => {
+            let start = time::rdtsc();
+            handler.call(&stack_frame);
+            let latency = time::rdtsc().wrapping_sub(start);
+            stats.max_latency.fetch_max(latency, Ordering::Relaxed);
+        }
+        None => {
+            // Dispositivo inesperado: mascara a linha para evitar uma
+            // tempestade de interrupções que ninguém vai reconhecer
+            stats.unhandled.fetch_add(1, Ordering::Relaxed);
+            mask(irq);
+        }
+    }
+
+    unsafe {
+        PICS.lock().notify_end_of_interrupt(index);
+    }
+    // Antes da troca: a profundidade é da CPU, não da thread
+    percpu::exit_interrupt();
+
+    // Depois do EOI: a próxima thread pode passar muito tempo sem voltar
+    // aqui, e o PIC não entregaria novas IRQs sem ele
+    crate::thread::preempt();
+}
+
+// ============================================================================
+// Estatísticas
+// ============================================================================
+
+/// Retorna as estatísticas de uma linha de IRQ.
+pub fn stats(irq: u8) -> Option<IrqStats> {
+    let stats = STATS.get(irq as usize)?;
+    Some(IrqStats {
+        irq,
+        vector: vector(irq),
+        count: stats.count.load(Ordering::Relaxed),
+        spurious: stats.spurious.load(Ordering::Relaxed),
+        unhandled: stats.unhandled.load(Ordering::Relaxed),
+        last_tick: stats.last_tick.load(Ordering::Relaxed),
+        max_latency_cycles: stats.max_latency.load(Ordering::Relaxed),
+    })
+}
+
+/// Escreve a tabela de interrupções (equivalente ao `/proc/interrupts`).
+pub fn report(out: &mut impl fmt::Write) -> fmt::Result {
+    writeln!(
+        out,
+        "IRQ VEC      COUNT SPURIOUS UNHANDLED  LAST_TICK MAX_CYCLES HANDLER"
+    )?;
+    for irq in 0..IRQ_LINES as u8 {
+        let stats = stats(irq).unwrap();
+        let name = match irq {
+            CASCADE_IRQ => "cascade",
+            _ => interrupts::without_interrupts(|| {
+                HANDLERS[irq as usize].read().as_ref().map_or("-", |h| h.name)
+            }),
+        };
+        writeln!(
+            out,
+            "{:3} {:3} {:10} {:8} {:9} {:10} {:10} {}",
+            stats.irq,
+            stats.vector,
+            stats.count,
+            stats.spurious,
+            stats.unhandled,
+            stats.last_tick,
+            stats.max_latency_cycles,
+            name
+        )?;
+    }
+    Ok(())
+}
+
+/// Testa que linhas inválidas, reservadas ou ocupadas são recusadas.
+#[test_case]
+fn test_register_rejects_unavailable_lines() {
+    fn noop(_stack_frame: &InterruptStackFrame) {}
+
+    assert_eq!(register(IRQ_LINES as u8, "test", noop), Err(IrqError::InvalidLine(16)));
+    assert_eq!(register(CASCADE_IRQ, "test", noop), Err(IrqError::Reserved(CASCADE_IRQ)));
+    assert_eq!(register(0, "test", noop), Err(IrqError::AlreadyClaimed(0)));
+}
+
+/// Testa que o timer é contado e aparece no relatório com seu nome.
+#[test_case]
+fn test_timer_statistics() {
+    let before = stats(0).unwrap().count;
+    let start = time::ticks();
+    while time::ticks() == start {
+        x86_64::instructions::hlt();
+    }
+    assert!(stats(0).unwrap().count > before);
+
+    // Os testes da lib rodam sem heap, então procuramos o nome sem String
+    struct FindTimer(bool);
+    impl fmt::Write for FindTimer {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.0 |= s.contains("timer");
+            Ok(())
+        }
+    }
+    let mut finder = FindTimer(false);
+    report(&mut finder).unwrap();
+    assert!(finder.0);
+}
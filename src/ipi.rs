@@ -0,0 +1,169 @@
+//! # Interrupções Entre Processadores (IPIs)
+//!
+//! ## Envio
+//!
+//! Uma CPU interrompe outras escrevendo o ICR do seu Local APIC com o ID
+//! do APIC destino e um vetor (ver `apic::send_fixed`). `send()` resolve
+//! o destino (`Target`) em IDs de APIC das CPUs online e envia uma IPI
+//! para cada uma, sem esperar nada.
+//!
+//! ## Chamadas com confirmação
+//!
+//! `call()` executa uma função em outras CPUs e só retorna quando todas
+//! terminaram, como o `smp_call_function` do Linux. Há uma chamada em
+//! andamento por vez:
+//!
+//! ```text
+//! CPU origem                                  CPUs destino
+//!  │ CALL_LOCK
+//!  │ CALL_FN = f, PENDING = máscara
+//!  │ IPI(CALL_VECTOR) ─────────────────────→  handle_call()
+//!  │                                            bit em PENDING? → f()
+//!  │ espera PENDING == 0  ←── PENDING &= !bit ──┘
+//!  v CALL_FN = None
+//! ```
+//!
+//! A máscara `PENDING` tem um bit por CPU: uma IPI atrasada de uma
+//! chamada anterior não executa a função atual numa CPU que não é destino
+//! nem confirma duas vezes.
+//!
+//! ## Deadlock
+//!
+//! Enquanto espera `CALL_LOCK`, uma CPU atende chamadas destinadas a ela,
+//! então duas CPUs chamando uma à outra ao mesmo tempo não travam, mesmo
+//! com interrupções desabilitadas. Ainda assim, `call()` não pode ser
+//! usado segurando um lock que as CPUs destino esperam com interrupções
+//! desabilitadas (ex: `memory::with_kernel_space`): elas nunca atenderiam
+//! a IPI.
+
+use crate::{apic, percpu, smp, smp::MAX_CPUS};
+use core::sync::atomic::{AtomicU64, Ordering};
+use x86_64::instructions::interrupts;
+
+/// Vetor das IPIs de `call()`.
+pub const CALL_VECTOR: u8 = 0xF1;
+
+/// Conjunto de CPUs destino de uma IPI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// Uma CPU, pelo índice.
+    Cpu(usize),
+    /// Todas as CPUs online exceto a atual.
+    Others,
+    /// Todas as CPUs online, inclusive a atual.
+    All,
+    /// CPUs com o bit correspondente ligado (bit = índice da CPU).
+    Mask(u64),
+}
+
+impl Target {
+    /// Máscara das CPUs online selecionadas.
+    fn mask(self) -> u64 {
+        let online = (0..MAX_CPUS)
+            .filter(|&index| smp::is_online(index))
+            .fold(0, |mask, index| mask | 1 << index);
+        let current = 1 << percpu::cpu_index();
+        online
+            & match self {
+                Target::Cpu(index) if index < MAX_CPUS => 1 << index,
+                Target::Cpu(_) => 0,
+                Target::Others => !current,
+                Target::All => u64::MAX,
+                Target::Mask(mask) => mask,
+            }
+    }
+}
+
+/// Envia `vector` para as CPUs de `target` (a atual é ignorada).
+pub fn send(target: Target, vector: u8) {
+    send_mask(target.mask() & !(1 << percpu::cpu_index()), vector);
+}
+
+fn send_mask(mask: u64, vector: u8) {
+    for index in (0..MAX_CPUS).filter(|&index| mask & 1 << index != 0) {
+        if let Some(apic_id) = smp::apic_id(index) {
+            apic::send_fixed(apic_id, vector);
+        }
+    }
+}
+
+/// Ponteiro para a função da chamada em andamento.
+#[derive(Clone, Copy)]
+struct CallFn(*const (dyn Fn() + Sync));
+
+// A função é `Sync` e só é usada enquanto `call()` espera as confirmações.
+unsafe impl Send for CallFn {}
+
+/// Serializa as chamadas.
+static CALL_LOCK: spin::Mutex<()> = spin::Mutex::new(());
+/// Função da chamada em andamento.
+static CALL_FN: spin::Mutex<Option<CallFn>> = spin::Mutex::new(None);
+/// CPUs que ainda não executaram a chamada em andamento.
+static PENDING: AtomicU64 = AtomicU64::new(0);
+/// Chamadas recebidas desde o boot (todas as CPUs).
+static CALLS_HANDLED: AtomicU64 = AtomicU64::new(0);
+
+/// Executa `f` nas CPUs de `target` e espera todas terminarem.
+///
+/// Nas outras CPUs, `f` roda dentro do handler da IPI (interrupções
+/// desabilitadas); na atual, se incluída, roda diretamente.
+pub fn call(target: Target, f: &(dyn Fn() + Sync)) {
+    let current = 1 << percpu::cpu_index();
+    let mask = target.mask();
+    let remote = mask & !current;
+
+    if remote != 0 {
+        let _guard = loop {
+            if let Some(guard) = CALL_LOCK.try_lock() {
+                break guard;
+            }
+            // Atende chamadas para esta CPU enquanto outra está em andamento
+            run_pending_call();
+            core::hint::spin_loop();
+        };
+
+        // Só é lido enquanto os bits de `PENDING` estão ligados, ou seja,
+        // enquanto esta função espera abaixo.
+        let f: *const (dyn Fn() + Sync) = f;
+        let f: *const (dyn Fn() + Sync + 'static) = unsafe { core::mem::transmute(f) };
+        interrupts::without_interrupts(|| *CALL_FN.lock() = Some(CallFn(f)));
+        PENDING.store(remote, Ordering::Release);
+        send_mask(remote, CALL_VECTOR);
+
+        while PENDING.load(Ordering::Acquire) != 0 {
+            core::hint::spin_loop();
+        }
+        interrupts::without_interrupts(|| *CALL_FN.lock() = None);
+    }
+
+    if mask & current != 0 {
+        f();
+    }
+}
+
+/// Executa a chamada em andamento, se esta CPU ainda deve executá-la.
+fn run_pending_call() {
+    let bit = 1 << percpu::cpu_index();
+    if PENDING.load(Ordering::Acquire) & bit == 0 {
+        return;
+    }
+    let f = interrupts::without_interrupts(|| *CALL_FN.lock());
+    if let Some(CallFn(f)) = f {
+        unsafe { (*f)() };
+        CALLS_HANDLED.fetch_add(1, Ordering::Relaxed);
+    }
+    PENDING.fetch_and(!bit, Ordering::AcqRel);
+}
+
+/// Chamado pelo handler de `CALL_VECTOR`.
+pub(crate) fn handle_call() {
+    percpu::enter_interrupt();
+    run_pending_call();
+    percpu::exit_interrupt();
+    apic::end_of_interrupt();
+}
+
+/// Número de chamadas executadas por IPI desde o boot.
+pub fn calls_handled() -> u64 {
+    CALLS_HANDLED.load(Ordering::Relaxed)
+}
@@ -31,6 +31,21 @@ pub mod gdt;         // GDT + TSS para double faults
 pub mod memory;      // Paginação e frame allocator
 pub mod allocator;   // Heap allocator (fixed size block)
 pub mod task;        // Async/await: Task, Executor, Waker
+pub mod thread;      // Threads do kernel com preempção
+pub mod time;        // Ticks do timer e TSC
+pub mod hpet;        // HPET: contador de alta resolução e comparadores
+pub mod rtc;         // Relógio CMOS: data e hora, IRQ 8 periódica
+pub mod cpu;         // Recursos do processador (CPUID)
+pub mod fpu;         // FPU/SSE/AVX: troca preguiçosa via #NM
+pub mod simd;        // Contas com SSE (crate simd/) dentro de kernel_fpu_begin
+pub mod apic;        // Local APIC (xAPIC via MMIO)
+pub mod acpi;        // Tabelas ACPI (RSDP, RSDT/XSDT, MADT)
+pub mod smp;         // Boot das application processors
+pub mod ipi;         // IPIs e chamadas entre CPUs
+pub mod percpu;      // Dados por CPU via GS base (cpu_local!)
+pub mod spinlock;    // Spinlocks que desabilitam interrupções e detectam deadlocks
+pub mod watchdog;    // Detecção de travamentos via NMI
+pub mod backtrace;   // Backtraces via frame pointers + tabela de símbolos
 
 extern crate alloc;
 
@@ -87,10 +102,18 @@ pub fn test_runner(tests: &[&dyn Testable]) {
     exit_qemu(QemuExitCode::Success);
 }
 
-/// Handler de panic para testes - exibe erro via serial e encerra com falha.
+/// Handler de panic para testes - exibe erro e backtrace via serial e encerra com falha.
 pub fn test_panic_handler(info: &PanicInfo) -> ! {
-    serial_println!("[failed]\n");
-    serial_println!("Error: {}\n", info);
+    use core::fmt::Write;
+
+    // O panic pode ter acontecido com SERIAL1 preso
+    let mut serial = serial::lock_for_panic();
+    let _ = writeln!(serial, "[failed]\n");
+    let _ = writeln!(serial, "Error: {}\n", info);
+    let _ = writeln!(serial, "Backtrace:");
+    backtrace::trace(backtrace::frame_pointer(), |frame| {
+        let _ = writeln!(serial, "  {}", frame);
+    });
     exit_qemu(QemuExitCode::Failed);
     hlt_loop();
 }
@@ -113,10 +136,12 @@ fn panic(info: &PanicInfo) -> ! {
     test_panic_handler(info)
 }
 
-/// Inicializa os subsistemas do kernel (GDT, IDT, PICs).
+/// Inicializa os subsistemas do kernel (dados por CPU, GDT, IDT, FPU, PICs).
 pub fn init() {
+    percpu::init_bsp();
     gdt::init();
     interrupts::init_idt();
-    unsafe { interrupts::PICS.lock().initialize() };
+    fpu::init_cpu();
+    interrupts::init_pics();
     x86_64::instructions::interrupts::enable();
 }
\ No newline at end of file
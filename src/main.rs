@@ -9,8 +9,9 @@
 //! 3. `rust_os::init()` configura GDT, IDT e PICs
 //! 4. Configura paginação e frame allocator
 //! 5. Inicializa o heap para alocação dinâmica
-//! 6. Cria o executor e spawna tasks assíncronas
-//! 7. Entra no loop do executor (nunca retorna)
+//! 6. Inicializa as threads do kernel (o código atual vira a thread `main`)
+//! 7. Cria o executor e spawna tasks assíncronas
+//! 8. Entra no loop do executor na thread `main` (nunca retorna)
 //!
 //! ## Estudo baseado em
 //!
@@ -27,10 +28,12 @@ extern crate alloc;  // Habilita tipos de alocação (Box, Vec, etc.)
 use bootloader::{entry_point, BootInfo};
 use core::panic::PanicInfo;
 use rust_os::{
-    allocator,
+    acpi, allocator, apic, hpet,
+    interrupts::deferred,
     memory::{self, BootInfoFrameAllocator},
     println,
-    task::{executor::Executor, keyboard, Task},
+    task::{executor::Executor, keyboard, Builder, Priority},
+    rtc, smp, thread, time, watchdog,
 };
 use x86_64::VirtAddr;
 
@@ -45,6 +48,17 @@ fn kernel_main(boot_info: &'static BootInfo) -> ! {
 
     rust_os::init();
 
+    let cpu = rust_os::cpu::features();
+    println!(
+        "CPU: {} ({}, family {} model {} stepping {})",
+        cpu.brand(),
+        cpu.vendor_id(),
+        cpu.family,
+        cpu.model,
+        cpu.stepping
+    );
+    println!("CPU features: {}", cpu);
+
     let phys_mem_offset = VirtAddr::new(boot_info.physical_memory_offset);
     println!("Physical Memory Offset ... [ok]");
     let mut mapper = unsafe {
@@ -62,6 +76,45 @@ fn kernel_main(boot_info: &'static BootInfo) -> ! {
 
     println!("Heap Memory initiated ... [ok]");
 
+    memory::set_kernel_space(mapper, frame_allocator);
+    thread::init().expect("thread initialization failed");
+    println!("Kernel Threads initiated ... [ok]");
+
+    match acpi::parse() {
+        Ok(tables) => println!("{}", tables),
+        Err(err) => println!("ACPI unavailable: {}", err),
+    }
+
+    match apic::init() {
+        Ok(()) => println!("Local APIC initiated ... [ok]"),
+        Err(err) => println!("Local APIC unavailable: {:?}", err),
+    }
+    match hpet::init() {
+        Ok(()) => println!(
+            "HPET: {} MHz, {} comparators ... [ok]",
+            hpet::frequency().unwrap_or(0) / 1_000_000,
+            hpet::comparator_count()
+        ),
+        Err(err) => println!("HPET unavailable: {:?}; calibrating against the PIT", err),
+    }
+    let tsc_hz = time::calibrate_tsc();
+    println!("TSC calibrated: {} MHz ... [ok]", tsc_hz / 1_000_000);
+    if let Some(timer_hz) = apic::calibrate_timer() {
+        println!("APIC timer calibrated: {} MHz ... [ok]", timer_hz / 1_000_000);
+    }
+    if !cpu.invariant_tsc {
+        println!("WARNING: TSC is not invariant; its rate may change with power states");
+    }
+    println!("RTC: {} ... [ok]", rtc::init());
+    match watchdog::init() {
+        Ok(()) => println!("NMI Watchdog initiated ... [ok]"),
+        Err(err) => println!("NMI Watchdog unavailable: {:?}", err),
+    }
+    match smp::init() {
+        Ok(online) => println!("SMP: {} CPUs online ... [ok]", online),
+        Err(err) => println!("SMP unavailable: {:?}", err),
+    }
+
     // let heap_value = Box::new(41);
     // println!("heap_value at {:p}", heap_value);
 
@@ -77,11 +130,20 @@ fn kernel_main(boot_info: &'static BootInfo) -> ! {
     // core::mem::drop(reference_counted);
     // println!("reference count is {} now", Rc::strong_count(&cloned_reference));
 
+    // As tasks do sistema rodam só na BSP; o `MulticoreExecutor` é opcional
+    // e as APs ficam em HLT até alguém usá-lo (ver `task::multicore`)
     let mut executor = Executor::new();
     println!("Simple Executor created ... [ok]");
-    executor.spawn(Task::new(example_task()));
+    Builder::new()
+        .name("deferred-work")
+        .priority(Priority::High)
+        .spawn_on(&mut executor, deferred::process_deferred_work());
+    Builder::new().name("example").spawn_on(&mut executor, example_task());
     println!("Example Task spawned ... [ok]");
-    executor.spawn(Task::new(keyboard::print_keypresses()));
+    Builder::new()
+        .name("keyboard")
+        .priority(Priority::High)
+        .spawn_on(&mut executor, keyboard::print_keypresses());
     executor.run();
     println!("Tasks running ... [ok]");
 
@@ -101,11 +163,20 @@ async fn example_task() {
     println!("async unmber: {}", number)
 }
 
-/// Panic handler para modo normal (exibe no VGA).
+/// Panic handler para modo normal (exibe mensagem e backtrace no VGA).
 #[cfg(not(test))]
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
-    println!("{}", info);
+    use core::fmt::Write;
+    use rust_os::{backtrace, vga_buffer};
+
+    // O panic pode ter acontecido com WRITER preso (ex: no meio de um println!)
+    let mut writer = vga_buffer::lock_for_panic();
+    let _ = writeln!(writer, "{}", info);
+    let _ = writeln!(writer, "Backtrace:");
+    backtrace::trace(backtrace::frame_pointer(), |frame| {
+        let _ = writeln!(writer, "  {}", frame);
+    });
     rust_os::hlt_loop();
 }
 
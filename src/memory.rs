@@ -20,7 +20,9 @@
 //!
 //! O bootloader fornece um memory map indicando regiões usáveis.
 //! O `BootInfoFrameAllocator` itera sobre essas regiões para alocar
-//! frames físicos de 4KB sob demanda.
+//! frames físicos de 4KB sob demanda. O primeiro frame usável abaixo de
+//! 1 MB fica reservado desde o início para o trampolim das APs (`smp`),
+//! já que o SIPI só aceita endereços baixos.
 //!
 //! ## Offset Mapping
 //!
@@ -31,12 +33,23 @@
 //!
 //! Isso permite acessar qualquer endereço físico facilmente.
 //!
+//! ## Várias CPUs
+//!
+//! `MapperFlush::flush()` invalida a TLB só da CPU atual. Isso basta para
+//! mapeamentos novos: uma página não presente nunca fica na TLB. Remover
+//! mapeamentos ou mudar permissões usa `tlb::TlbShootdown` para invalidar
+//! também as outras CPUs.
+//!
 //! ## Estudo baseado em
 //!
 //! [Introduction to Paging](https://os.phil-opp.com/paging-introduction/) - Blog OS
 
+pub mod tlb;
+
 use bootloader::bootinfo::{MemoryMap, MemoryRegionType};
+use core::sync::atomic::{AtomicU64, Ordering};
 use x86_64::{
+    instructions::interrupts,
     registers::control::Cr3,
     structures::paging::{
         page_table::FrameError, FrameAllocator, Mapper, OffsetPageTable, Page, PageTable,
@@ -67,19 +80,46 @@ unsafe impl FrameAllocator<Size4KiB> for EmptyFrameAllocator {
     }
 }
 
+/// Início da faixa em que `low_frame` é procurado.
+const LOW_FRAME_MIN: u64 = 0x1000;
+/// Fim da memória baixa, endereçável pelo SIPI.
+const LOW_MEMORY_END: u64 = 0x100000;
+
 /// Frame allocator que usa o memory map do bootloader.
 pub struct BootInfoFrameAllocator {
     memory_map: &'static MemoryMap,
     next: usize,
+    /// Frame baixo reservado (ver `low_frame`), nunca entregue por `allocate_frame`.
+    low_frame: Option<PhysFrame>,
 }
 
 impl BootInfoFrameAllocator {
     /// Cria um allocator a partir do memory map do bootloader.
+    ///
+    /// Também registra o fim do offset mapping (ver `is_phys_mapped`).
     pub unsafe fn init(memory_map: &'static MemoryMap) -> Self {
-        BootInfoFrameAllocator {
+        let end = memory_map
+            .iter()
+            .map(|region| region.range.end_addr())
+            .max()
+            .unwrap_or(0);
+        PHYSICAL_MEMORY_END.store(end, Ordering::Relaxed);
+
+        let mut allocator = BootInfoFrameAllocator {
             memory_map,
             next: 0,
-        }
+            low_frame: None,
+        };
+        // O frame 0 tem a IVT do modo real
+        allocator.low_frame = allocator.usable_frames().find(|frame| {
+            (LOW_FRAME_MIN..LOW_MEMORY_END).contains(&frame.start_address().as_u64())
+        });
+        allocator
+    }
+
+    /// Frame abaixo de 1 MB reservado no `init`, para o trampolim das APs.
+    pub fn low_frame(&self) -> Option<PhysFrame> {
+        self.low_frame
     }
 
     /// Retorna um iterador sobre os frames usáveis.
@@ -88,7 +128,10 @@ impl BootInfoFrameAllocator {
         let usable_regions = regions.filter(|r| r.region_type == MemoryRegionType::Usable);
         let addr_ranges = usable_regions.map(|r| r.range.start_addr()..r.range.end_addr());
         let frame_addresses = addr_ranges.flat_map(|r| r.step_by(4096));
-        frame_addresses.map(|addr| PhysFrame::containing_address(PhysAddr::new(addr)))
+        let low_frame = self.low_frame;
+        frame_addresses
+            .map(|addr| PhysFrame::containing_address(PhysAddr::new(addr)))
+            .filter(move |&frame| Some(frame) != low_frame)
     }
 }
 
@@ -100,8 +143,71 @@ unsafe impl FrameAllocator<Size4KiB> for BootInfoFrameAllocator {
     }
 }
 
+/// Page table e frame allocator do kernel, para mapeamentos feitos após o
+/// boot (ex: stacks de threads).
+static KERNEL_SPACE: spin::Mutex<Option<KernelSpace>> = spin::Mutex::new(None);
+
+/// Page table ativa e o frame allocator que a alimenta.
+pub struct KernelSpace {
+    pub mapper: OffsetPageTable<'static>,
+    pub frame_allocator: BootInfoFrameAllocator,
+}
+
+/// Entrega a page table e o frame allocator ao kernel.
+///
+/// Chamado por `kernel_main` depois de `allocator::init_heap`; a partir daí
+/// novos mapeamentos passam por `with_kernel_space`.
+pub fn set_kernel_space(mapper: OffsetPageTable<'static>, frame_allocator: BootInfoFrameAllocator) {
+    interrupts::without_interrupts(|| {
+        *KERNEL_SPACE.lock() = Some(KernelSpace { mapper, frame_allocator });
+    });
+}
+
+/// Executa `f` com acesso exclusivo à page table do kernel.
+///
+/// # Panics
+/// Entra em panic se chamado antes de `set_kernel_space`.
+pub fn with_kernel_space<R>(f: impl FnOnce(&mut KernelSpace) -> R) -> R {
+    interrupts::without_interrupts(|| {
+        let mut space = KERNEL_SPACE.lock();
+        f(space.as_mut().expect("memory::set_kernel_space not called"))
+    })
+}
+
+/// Offset onde o bootloader mapeou toda a memória física (0 = não inicializado).
+static PHYSICAL_MEMORY_OFFSET: AtomicU64 = AtomicU64::new(0);
+/// Fim do offset mapping: o maior endereço do memory map (0 = desconhecido).
+static PHYSICAL_MEMORY_END: AtomicU64 = AtomicU64::new(0);
+
+/// Converte um endereço físico no endereço virtual do offset mapping.
+///
+/// Usado para acessar MMIO de dispositivos (APIC, HPET, tabelas ACPI).
+/// O bootloader mapeia até o maior endereço do memory map, que no PC
+/// inclui a região reservada logo abaixo de 4 GiB onde ficam esses
+/// dispositivos.
+///
+/// # Panics
+/// Entra em panic se chamado antes de `memory::init`.
+pub fn phys_to_virt(addr: PhysAddr) -> VirtAddr {
+    let offset = PHYSICAL_MEMORY_OFFSET.load(Ordering::Relaxed);
+    assert!(offset != 0, "memory::init not called");
+    VirtAddr::new(offset + addr.as_u64())
+}
+
+/// Retorna se `[addr, addr + length)` está dentro do offset mapping.
+///
+/// O fim do mapeamento vem do memory map, registrado por
+/// `BootInfoFrameAllocator::init`; antes disso, nada é considerado mapeado.
+pub fn is_phys_mapped(addr: PhysAddr, length: u64) -> bool {
+    let end = PHYSICAL_MEMORY_END.load(Ordering::Relaxed);
+    addr.as_u64()
+        .checked_add(length)
+        .is_some_and(|range_end| range_end <= end)
+}
+
 /// Inicializa o OffsetPageTable a partir do offset de memória física.
 pub unsafe fn init(physical_memory_offset: VirtAddr) -> OffsetPageTable<'static> {
+    PHYSICAL_MEMORY_OFFSET.store(physical_memory_offset.as_u64(), Ordering::Relaxed);
     unsafe {
         let level_4_table = active_level_4_table(physical_memory_offset);
         OffsetPageTable::new(level_4_table, physical_memory_offset)
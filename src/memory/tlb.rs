@@ -0,0 +1,127 @@
+//! # TLB Shootdown
+//!
+//! Cada CPU tem sua própria TLB. `MapperFlush::flush()` executa `invlpg`
+//! só na CPU atual: as outras CPUs que têm a mesma page table carregada
+//! podem continuar usando a tradução antiga (e escrevendo num frame que
+//! já foi liberado).
+//!
+//! `TlbShootdown` junta as páginas alteradas e, no `finish()`, invalida
+//! todas de uma vez na CPU atual e, com uma única `ipi::call`, nas CPUs
+//! cujo CR3 é o mesmo:
+//!
+//! ```ignore
+//! let mut shootdown = TlbShootdown::new();
+//! memory::with_kernel_space(|space| {
+//!     let (_, flush) = space.mapper.unmap(page)?;
+//!     flush.ignore();
+//!     shootdown.add(page);
+//! });
+//! shootdown.finish(); // fora de `with_kernel_space`
+//! ```
+//!
+//! `finish()` precisa ser chamado sem segurar `with_kernel_space`: as
+//! outras CPUs podem estar esperando esse lock com interrupções
+//! desabilitadas e nunca atenderiam a IPI (ver `ipi`).
+//!
+//! Acima de `MAX_PAGES` páginas fica mais barato recarregar o CR3, que
+//! invalida a TLB inteira (exceto páginas globais).
+
+use crate::{
+    ipi::{self, Target},
+    percpu,
+    smp::MAX_CPUS,
+};
+use x86_64::{
+    instructions::tlb,
+    registers::control::Cr3,
+    structures::paging::{Page, PageSize},
+    VirtAddr,
+};
+
+/// Páginas guardadas individualmente antes de cair no flush completo.
+pub const MAX_PAGES: usize = 16;
+
+/// Lote de invalidações de TLB para todas as CPUs.
+#[must_use = "as invalidações só acontecem em `finish()`"]
+pub struct TlbShootdown {
+    pages: [VirtAddr; MAX_PAGES],
+    len: usize,
+    flush_all: bool,
+}
+
+impl TlbShootdown {
+    pub const fn new() -> Self {
+        TlbShootdown {
+            pages: [VirtAddr::zero(); MAX_PAGES],
+            len: 0,
+            flush_all: false,
+        }
+    }
+
+    /// Adiciona uma página cujo mapeamento mudou.
+    pub fn add<S: PageSize>(&mut self, page: Page<S>) {
+        if self.len == MAX_PAGES {
+            self.flush_all = true;
+        } else {
+            self.pages[self.len] = page.start_address();
+            self.len += 1;
+        }
+    }
+
+    /// Retorna se nenhuma página foi adicionada.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0 && !self.flush_all
+    }
+
+    /// Invalida as páginas na CPU atual e nas CPUs com o mesmo CR3.
+    pub fn finish(self) {
+        if self.is_empty() {
+            return;
+        }
+        let invalidate = || self.invalidate_local();
+        invalidate();
+
+        let (cr3, _) = Cr3::read();
+        let current = percpu::cpu_index();
+        let remote = (0..MAX_CPUS)
+            .filter(|&index| index != current)
+            .filter(|&index| percpu::active_cr3(index) == cr3.start_address().as_u64())
+            .fold(0, |mask, index| mask | 1 << index);
+        if remote != 0 {
+            ipi::call(Target::Mask(remote), &invalidate);
+        }
+    }
+
+    fn invalidate_local(&self) {
+        if self.flush_all {
+            tlb::flush_all();
+        } else {
+            for &addr in &self.pages[..self.len] {
+                tlb::flush(addr);
+            }
+        }
+    }
+}
+
+impl Default for TlbShootdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Testa que o lote passa a invalidar tudo quando enche.
+#[test_case]
+fn test_overflow_flushes_all() {
+    use x86_64::structures::paging::Size4KiB;
+
+    let mut shootdown = TlbShootdown::new();
+    assert!(shootdown.is_empty());
+    for i in 0..=MAX_PAGES as u64 {
+        shootdown.add(Page::<Size4KiB>::containing_address(VirtAddr::new(
+            i * 4096,
+        )));
+    }
+    assert_eq!(shootdown.len, MAX_PAGES);
+    assert!(shootdown.flush_all);
+    shootdown.finish();
+}
@@ -0,0 +1,307 @@
+//! # Dados por CPU
+//!
+//! ## O problema
+//!
+//! Com várias CPUs, estados como "thread atual", "executor atual" ou
+//! "profundidade de interrupções" não podem ser estáticos globais: cada
+//! CPU tem o seu. O índice da CPU precisa ser descoberto rápido, sem
+//! locks e sem perguntar ao Local APIC a cada acesso.
+//!
+//! ## GS base
+//!
+//! Cada CPU aponta o registrador base do segmento GS (MSR `IA32_GS_BASE`)
+//! para o seu próprio bloco `PerCpu`. Endereços relativos a GS (`gs:[n]`)
+//! passam a ser relativos ao bloco da CPU atual, então uma única instrução
+//! lê o índice:
+//!
+//! ```text
+//! CPU 0: GS base ──→ PERCPU[0] { index: 0, interrupt_depth, stats }
+//! CPU 1: GS base ──→ PERCPU[1] { index: 1, interrupt_depth, stats }
+//!
+//! mov rax, gs:[0]   → índice da CPU atual
+//! ```
+//!
+//! O kernel ainda não tem modo usuário, então GS sempre contém o valor do
+//! kernel e `swapgs` nunca é necessário. Quando houver, as entradas vindas
+//! do ring 3 precisam de `swapgs` (que troca `IA32_GS_BASE` com
+//! `IA32_KERNEL_GS_BASE`); por isso `IA32_KERNEL_GS_BASE` fica zerado.
+//!
+//! ## `cpu_local!`
+//!
+//! Variáveis por CPU são declaradas com a macro `cpu_local!`, que cria um
+//! `CpuLocal<T>`: um array com uma cópia por CPU, indexado por
+//! `cpu_index()`:
+//!
+//! ```ignore
+//! cpu_local! {
+//!     static COUNTER: Cell<u64> = Cell::new(0);
+//! }
+//!
+//! COUNTER.with(|counter| counter.set(counter.get() + 1));
+//! ```
+//!
+//! `with` desabilita interrupções durante o acesso: a thread não pode ser
+//! preemptada (e trocar de CPU) nem ter o valor alterado por um handler
+//! no meio do caminho, então `T` não precisa ser `Sync` (`Cell` e
+//! `RefCell` servem). Para tipos `Sync` (atômicos), `get()` dá acesso
+//! direto.
+//!
+//! ## Inicialização
+//!
+//! A BSP chama `init_bsp()` em `rust_os::init()`, antes de qualquer
+//! outro subsistema; cada AP chama `init_ap()` ao entrar em `ap_entry`.
+//! Antes disso `cpu_index()` retorna 0.
+
+use crate::smp::MAX_CPUS;
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use x86_64::{
+    instructions::interrupts,
+    registers::{
+        control::Cr3,
+        model_specific::{GsBase, KernelGsBase},
+    },
+    VirtAddr,
+};
+
+/// Bloco de dados de uma CPU, apontado pelo GS base.
+#[repr(C)]
+pub struct PerCpu {
+    /// Índice da CPU. Precisa ser o primeiro campo (lido em `gs:[0]`).
+    index: AtomicUsize,
+    /// Interrupções aninhadas em execução nesta CPU.
+    interrupt_depth: AtomicUsize,
+    /// Seções com interrupções desabilitadas abertas por `irq_save()`.
+    irq_disable_depth: AtomicUsize,
+    /// Se as interrupções estavam habilitadas no `irq_save()` mais externo.
+    irqs_were_enabled: AtomicBool,
+    /// IRQs recebidas por esta CPU.
+    irqs: AtomicU64,
+    /// Trocas de contexto feitas nesta CPU.
+    context_switches: AtomicU64,
+    /// CR3 carregado nesta CPU (0 = CPU não inicializada), usado para
+    /// saber quem precisa de TLB shootdown.
+    active_cr3: AtomicU64,
+}
+
+impl PerCpu {
+    const fn new() -> Self {
+        PerCpu {
+            index: AtomicUsize::new(0),
+            interrupt_depth: AtomicUsize::new(0),
+            irq_disable_depth: AtomicUsize::new(0),
+            irqs_were_enabled: AtomicBool::new(false),
+            irqs: AtomicU64::new(0),
+            context_switches: AtomicU64::new(0),
+            active_cr3: AtomicU64::new(0),
+        }
+    }
+
+    /// Índice da CPU (0 = BSP).
+    pub fn index(&self) -> usize {
+        self.index.load(Ordering::Relaxed)
+    }
+}
+
+static PERCPU: [PerCpu; MAX_CPUS] = [const { PerCpu::new() }; MAX_CPUS];
+/// Se a BSP já configurou seu GS base (antes disso, GS base = 0).
+static READY: AtomicBool = AtomicBool::new(false);
+
+/// Cópia das estatísticas de uma CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuStats {
+    pub index: usize,
+    pub irqs: u64,
+    pub context_switches: u64,
+}
+
+/// Aponta o GS base da CPU atual para o bloco `index`.
+fn load(index: usize) {
+    let block = &PERCPU[index];
+    block.index.store(index, Ordering::Relaxed);
+    let (cr3, _) = Cr3::read();
+    block.active_cr3.store(cr3.start_address().as_u64(), Ordering::Release);
+    GsBase::write(VirtAddr::from_ptr(block));
+    KernelGsBase::write(VirtAddr::zero());
+}
+
+/// Configura os dados por CPU da BSP.
+pub fn init_bsp() {
+    load(0);
+    READY.store(true, Ordering::Release);
+}
+
+/// Configura os dados por CPU de uma AP. Primeira coisa feita em `ap_entry`.
+pub(crate) fn init_ap(index: usize) {
+    load(index);
+}
+
+/// Índice da CPU atual (0 = BSP).
+#[inline]
+pub fn cpu_index() -> usize {
+    if !READY.load(Ordering::Acquire) {
+        return 0;
+    }
+    let index: usize;
+    unsafe {
+        core::arch::asm!(
+            "mov {}, gs:[0]",
+            out(reg) index,
+            options(nostack, preserves_flags, readonly)
+        );
+    }
+    index
+}
+
+/// Bloco de dados da CPU atual.
+pub fn current() -> &'static PerCpu {
+    &PERCPU[cpu_index()]
+}
+
+/// Interrupções aninhadas em execução nesta CPU (0 = fora de interrupções).
+pub fn interrupt_depth() -> usize {
+    current().interrupt_depth.load(Ordering::Relaxed)
+}
+
+/// Retorna se a CPU atual está tratando uma interrupção.
+pub fn in_interrupt() -> bool {
+    interrupt_depth() > 0
+}
+
+/// Marca a entrada em um handler de interrupção.
+pub(crate) fn enter_interrupt() {
+    let cpu = current();
+    cpu.interrupt_depth.fetch_add(1, Ordering::Relaxed);
+    cpu.irqs.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Marca a saída de um handler de interrupção.
+pub(crate) fn exit_interrupt() {
+    current().interrupt_depth.fetch_sub(1, Ordering::Relaxed);
+}
+
+/// Desabilita interrupções numa seção aninhável (como o `local_irq_save`
+/// do Linux). Só o `irq_restore()` da seção mais externa volta a
+/// habilitá-las, e só se estavam habilitadas quando ela começou, então
+/// seções podem terminar fora de ordem.
+pub(crate) fn irq_save() {
+    let enabled = interrupts::are_enabled();
+    interrupts::disable();
+    let cpu = current();
+    if cpu.irq_disable_depth.fetch_add(1, Ordering::Relaxed) == 0 {
+        cpu.irqs_were_enabled.store(enabled, Ordering::Relaxed);
+    }
+}
+
+/// Fecha uma seção aberta por `irq_save()`.
+pub(crate) fn irq_restore() {
+    let cpu = current();
+    let depth = cpu.irq_disable_depth.fetch_sub(1, Ordering::Relaxed);
+    debug_assert!(depth > 0, "irq_restore without irq_save");
+    if depth == 1 && cpu.irqs_were_enabled.load(Ordering::Relaxed) {
+        interrupts::enable();
+    }
+}
+
+/// Conta uma troca de contexto na CPU atual.
+pub(crate) fn count_context_switch() {
+    current().context_switches.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Endereço físico da page table carregada na CPU `index` (0 = nenhuma).
+pub fn active_cr3(index: usize) -> u64 {
+    PERCPU.get(index).map_or(0, |cpu| cpu.active_cr3.load(Ordering::Acquire))
+}
+
+/// Estatísticas de uma CPU.
+pub fn stats(index: usize) -> Option<CpuStats> {
+    let cpu = PERCPU.get(index)?;
+    Some(CpuStats {
+        index,
+        irqs: cpu.irqs.load(Ordering::Relaxed),
+        context_switches: cpu.context_switches.load(Ordering::Relaxed),
+    })
+}
+
+/// Variável com uma cópia por CPU, declarada com `cpu_local!`.
+pub struct CpuLocal<T> {
+    values: [T; MAX_CPUS],
+}
+
+// Cada CPU só acessa sua cópia, com interrupções desabilitadas (`with`),
+// ou o tipo é `Sync` (`get`, `get_for`).
+unsafe impl<T: Send> Sync for CpuLocal<T> {}
+
+impl<T> CpuLocal<T> {
+    #[doc(hidden)]
+    pub const fn new(values: [T; MAX_CPUS]) -> Self {
+        CpuLocal { values }
+    }
+
+    /// Executa `f` com a cópia da CPU atual, com interrupções desabilitadas.
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        interrupts::without_interrupts(|| f(&self.values[cpu_index()]))
+    }
+
+    /// Cópia da CPU atual.
+    ///
+    /// A thread pode trocar de CPU logo depois, então o valor pode ser de
+    /// outra CPU quando for usado; por isso exige `T: Sync`.
+    pub fn get(&self) -> &T
+    where
+        T: Sync,
+    {
+        &self.values[cpu_index()]
+    }
+
+    /// Cópia de outra CPU.
+    pub fn get_for(&self, index: usize) -> Option<&T>
+    where
+        T: Sync,
+    {
+        self.values.get(index)
+    }
+}
+
+/// Declara variáveis por CPU (ver `percpu::CpuLocal`).
+#[macro_export]
+macro_rules! cpu_local {
+    ($($(#[$attr:meta])* $vis:vis static $name:ident: $ty:ty = $init:expr;)+) => {
+        $(
+            $(#[$attr])*
+            $vis static $name: $crate::percpu::CpuLocal<$ty> =
+                $crate::percpu::CpuLocal::new([const { $init }; $crate::smp::MAX_CPUS]);
+        )+
+    };
+}
+
+/// Testa que a BSP é a CPU 0 e aponta o GS base para o próprio bloco.
+#[test_case]
+fn test_bsp_is_cpu_zero() {
+    assert_eq!(cpu_index(), 0);
+    assert_eq!(GsBase::read(), VirtAddr::from_ptr(&PERCPU[0]));
+    assert!(!in_interrupt());
+}
+
+/// Testa que cada CPU tem sua própria cópia de uma variável `cpu_local!`.
+#[test_case]
+fn test_cpu_local_values() {
+    use core::cell::Cell;
+
+    crate::cpu_local! {
+        static VALUE: Cell<u64> = Cell::new(0);
+    }
+    VALUE.with(|value| value.set(value.get() + 5));
+    VALUE.with(|value| assert_eq!(value.get(), 5));
+    assert_eq!(VALUE.values[1].get(), 0);
+}
+
+/// Testa que IRQs são contadas na CPU que as recebeu.
+#[test_case]
+fn test_irqs_counted_per_cpu() {
+    let before = stats(0).unwrap().irqs;
+    let start = crate::time::ticks();
+    while crate::time::ticks() == start {
+        x86_64::instructions::hlt();
+    }
+    assert!(stats(0).unwrap().irqs > before);
+}
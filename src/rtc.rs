@@ -0,0 +1,506 @@
+//! # Relógio de Tempo Real (CMOS RTC)
+//!
+//! ## O RTC
+//!
+//! O PC guarda data e hora num relógio alimentado por bateria (Motorola
+//! MC146818), acessado pela CMOS através de duas portas de I/O:
+//!
+//! ```text
+//! out 0x70, registrador   ; seleciona
+//! in  0x71                ; lê o valor
+//!
+//! Reg   Conteúdo           Reg   Conteúdo
+//! 0x00  segundos           0x0A  status A (bit 7 = update in progress,
+//! 0x02  minutos                  bits 0-3 = taxa da interrupção periódica)
+//! 0x04  horas              0x0B  status B (bit 1 = 24h, bit 2 = binário,
+//! 0x07  dia do mês               bit 6 = interrupção periódica)
+//! 0x08  mês                0x0C  status C (lido para reconhecer a IRQ 8)
+//! 0x09  ano (2 dígitos)    0x32  século (em geral; ver abaixo)
+//! ```
+//!
+//! ## Armadilhas
+//!
+//! - **BCD**: por padrão os valores vêm em BCD (`0x59` = 59); o status B
+//!   diz se estão em binário.
+//! - **12 horas**: no modo 12h o bit 7 da hora indica PM.
+//! - **Atualização**: uma vez por segundo o RTC atualiza os registradores;
+//!   ler durante a atualização (status A bit 7) pode misturar valores
+//!   antigos e novos. A leitura espera o fim da atualização e repete até
+//!   duas leituras seguidas serem iguais.
+//! - **Século**: não há registrador padrão. O FADT diz qual é
+//!   (`century_register`, 0 = não existe); sem FADT, `init()` mantém o
+//!   0x32 de costume. Sem século válido, o ano é 20xx.
+//!
+//! ## Hora do sistema
+//!
+//! Ler a CMOS é lento (cada acesso é I/O). `init()` lê o RTC uma vez e
+//! guarda o TSC e os ticks do momento; `now()` soma o tempo monotônico
+//! decorrido (TSC se calibrado, senão ticks do PIT). A precisão é de 1
+//! segundo (o RTC não tem frações). O RTC é tratado como UTC, que é o
+//! padrão do QEMU.
+//!
+//! ## Interrupção periódica (IRQ 8)
+//!
+//! O RTC também gera interrupções periódicas de `32768 >> (taxa - 1)` Hz
+//! (taxa 3..=15, de 8192 Hz a 2 Hz), úteis como segundo timer
+//! independente do PIT. Cada IRQ precisa de uma leitura do status C, senão
+//! o RTC não gera a próxima.
+//!
+//! ## Referências
+//!
+//! - [CMOS](https://wiki.osdev.org/CMOS) - OSDev Wiki
+//! - [RTC](https://wiki.osdev.org/RTC) - OSDev Wiki
+
+use crate::{
+    acpi,
+    interrupts::irq::{self, IrqError},
+    spinlock::IrqSpinlock,
+    time,
+};
+use core::{
+    fmt,
+    sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering},
+};
+use x86_64::{instructions::port::Port, structures::idt::InterruptStackFrame};
+
+/// Linha de IRQ do RTC (PIC slave).
+pub const RTC_IRQ: u8 = 8;
+
+const REG_SECONDS: u8 = 0x00;
+const REG_MINUTES: u8 = 0x02;
+const REG_HOURS: u8 = 0x04;
+const REG_DAY: u8 = 0x07;
+const REG_MONTH: u8 = 0x08;
+const REG_YEAR: u8 = 0x09;
+const REG_STATUS_A: u8 = 0x0A;
+const REG_STATUS_B: u8 = 0x0B;
+const REG_STATUS_C: u8 = 0x0C;
+const REG_CENTURY: u8 = 0x32;
+
+const STATUS_A_UPDATE_IN_PROGRESS: u8 = 1 << 7;
+const STATUS_B_24_HOUR: u8 = 1 << 1;
+const STATUS_B_BINARY: u8 = 1 << 2;
+const STATUS_B_PERIODIC: u8 = 1 << 6;
+const HOUR_PM: u8 = 1 << 7;
+
+/// Portas de índice (0x70) e dados (0x71) da CMOS.
+struct Cmos {
+    index: Port<u8>,
+    data: Port<u8>,
+}
+
+impl Cmos {
+    fn read(&mut self, register: u8) -> u8 {
+        unsafe {
+            self.index.write(register);
+            self.data.read()
+        }
+    }
+
+    fn write(&mut self, register: u8, value: u8) {
+        unsafe {
+            self.index.write(register);
+            self.data.write(value);
+        }
+    }
+}
+
+/// Registrador do século na CMOS (0 = não existe), definido por `init()`.
+static CENTURY_REGISTER: AtomicU8 = AtomicU8::new(REG_CENTURY);
+
+/// O par índice/dados precisa ser usado sem interrupções no meio.
+static CMOS: IrqSpinlock<Cmos> = IrqSpinlock::new(
+    "CMOS",
+    Cmos {
+        index: Port::new(0x70),
+        data: Port::new(0x71),
+    },
+);
+
+/// Data e hora do calendário (UTC).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl DateTime {
+    /// Converte segundos desde 1970-01-01 00:00:00 UTC.
+    pub fn from_unix(timestamp: u64) -> DateTime {
+        let days = timestamp / 86_400;
+        let seconds = timestamp % 86_400;
+        let (year, month, day) = civil_from_days(days as i64);
+        DateTime {
+            year: year as u16,
+            month,
+            day,
+            hour: (seconds / 3600) as u8,
+            minute: (seconds / 60 % 60) as u8,
+            second: (seconds % 60) as u8,
+        }
+    }
+
+    /// Segundos desde 1970-01-01 00:00:00 UTC.
+    pub fn to_unix(&self) -> u64 {
+        let days = days_from_civil(i64::from(self.year), self.month, self.day);
+        days as u64 * 86_400
+            + u64::from(self.hour) * 3600
+            + u64::from(self.minute) * 60
+            + u64::from(self.second)
+    }
+}
+
+/// Formato ISO 8601 (`2024-02-29 12:34:56 UTC`).
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+/// Dias desde 1970-01-01 para uma data do calendário gregoriano.
+///
+/// Algoritmo de Howard Hinnant: conta anos a partir de março, para que o
+/// dia extra de fevereiro fique no fim do "ano".
+fn days_from_civil(year: i64, month: u8, day: u8) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Inverso de `days_from_civil`.
+fn civil_from_days(days: i64) -> (i64, u8, u8) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u8;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Valores brutos dos registradores de data e hora.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RawTime {
+    second: u8,
+    minute: u8,
+    hour: u8,
+    day: u8,
+    month: u8,
+    year: u8,
+    century: u8,
+}
+
+fn bcd_to_binary(value: u8) -> u8 {
+    (value & 0x0F) + (value >> 4) * 10
+}
+
+/// Converte os valores brutos conforme o formato do status B.
+fn decode(raw: RawTime, status_b: u8) -> DateTime {
+    let binary = status_b & STATUS_B_BINARY != 0;
+    let convert = |value: u8| if binary { value } else { bcd_to_binary(value) };
+
+    let pm = raw.hour & HOUR_PM != 0;
+    let mut hour = convert(raw.hour & !HOUR_PM);
+    if status_b & STATUS_B_24_HOUR == 0 {
+        // 12h: 12 AM = 0h, 12 PM = 12h
+        hour %= 12;
+        if pm {
+            hour += 12;
+        }
+    }
+
+    // Sem registrador de século (ou com valor inválido), assume 20xx
+    let century = match convert(raw.century) {
+        century @ 19..=21 => u16::from(century),
+        _ => 20,
+    };
+    DateTime {
+        year: century * 100 + u16::from(convert(raw.year)),
+        month: convert(raw.month),
+        day: convert(raw.day),
+        hour,
+        minute: convert(raw.minute),
+        second: convert(raw.second),
+    }
+}
+
+fn read_raw(cmos: &mut Cmos) -> RawTime {
+    while cmos.read(REG_STATUS_A) & STATUS_A_UPDATE_IN_PROGRESS != 0 {
+        core::hint::spin_loop();
+    }
+    RawTime {
+        second: cmos.read(REG_SECONDS),
+        minute: cmos.read(REG_MINUTES),
+        hour: cmos.read(REG_HOURS),
+        day: cmos.read(REG_DAY),
+        month: cmos.read(REG_MONTH),
+        year: cmos.read(REG_YEAR),
+        century: match CENTURY_REGISTER.load(Ordering::Relaxed) {
+            0 => 0,
+            register => cmos.read(register),
+        },
+    }
+}
+
+/// Lê a data e hora direto do RTC (lento: vários acessos de I/O).
+pub fn read() -> DateTime {
+    let mut cmos = CMOS.lock();
+    let mut raw = read_raw(&mut cmos);
+    // Uma atualização entre a espera e o fim da leitura muda os valores
+    loop {
+        let again = read_raw(&mut cmos);
+        if again == raw {
+            break;
+        }
+        raw = again;
+    }
+    let status_b = cmos.read(REG_STATUS_B);
+    decode(raw, status_b)
+}
+
+/// Referência para `now()`: hora do RTC e o relógio monotônico no momento.
+#[derive(Clone, Copy)]
+struct BootTime {
+    unix: u64,
+    tsc: u64,
+    ticks: u64,
+}
+
+static BOOT_TIME: IrqSpinlock<Option<BootTime>> = IrqSpinlock::new("BOOT_TIME", None);
+
+/// Lê o RTC e passa a calcular a hora pelo relógio monotônico.
+///
+/// Procura o registrador do século no FADT, então precisa do heap e do
+/// offset mapping.
+pub fn init() -> DateTime {
+    if let Ok(fadt) = acpi::fadt() {
+        // Índices a partir de 0x80 ficam no segundo banco (portas 0x72/0x73);
+        // na porta 0x70 o bit 7 desligaria a NMI
+        let register = match fadt.century_register {
+            register @ 0..=0x7F => register,
+            _ => 0,
+        };
+        CENTURY_REGISTER.store(register, Ordering::Relaxed);
+    }
+    let now = read();
+    let boot = BootTime {
+        unix: now.to_unix(),
+        tsc: time::rdtsc(),
+        ticks: time::ticks(),
+    };
+    *BOOT_TIME.lock() = Some(boot);
+    now
+}
+
+/// Segundos desde 1970-01-01 00:00:00 UTC.
+///
+/// Antes de `init()` lê o RTC diretamente.
+pub fn unix_time() -> u64 {
+    let boot = *BOOT_TIME.lock();
+    let boot = match boot {
+        Some(boot) => boot,
+        None => return read().to_unix(),
+    };
+    // `boot.tsc` veio do TSC da BSP; o de uma AP pode estar um pouco atrás
+    let elapsed = match time::cycles_to_us(time::rdtsc().saturating_sub(boot.tsc)) {
+        Some(us) => us / 1_000_000,
+        None => time::ticks_to_us(time::ticks() - boot.ticks) / 1_000_000,
+    };
+    boot.unix + elapsed
+}
+
+/// Data e hora atuais (UTC).
+pub fn now() -> DateTime {
+    DateTime::from_unix(unix_time())
+}
+
+/// Erros da interrupção periódica.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RtcError {
+    /// Taxa fora de 3..=15.
+    InvalidRate(u8),
+    /// A IRQ 8 não pôde ser registrada.
+    Irq(IrqError),
+}
+
+static PERIODIC_ENABLED: AtomicBool = AtomicBool::new(false);
+static PERIODIC_TICKS: AtomicU64 = AtomicU64::new(0);
+
+/// Handler da IRQ 8: reconhece a interrupção lendo o status C.
+fn periodic_handler(_stack_frame: &InterruptStackFrame) {
+    CMOS.lock().read(REG_STATUS_C);
+    PERIODIC_TICKS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Frequência em Hz de uma taxa da interrupção periódica.
+pub fn rate_frequency(rate: u8) -> Option<u32> {
+    (3..=15).contains(&rate).then(|| 32_768 >> (rate - 1))
+}
+
+/// Liga a interrupção periódica com a taxa `rate` (3..=15) e retorna a
+/// frequência resultante em Hz.
+pub fn enable_periodic(rate: u8) -> Result<u32, RtcError> {
+    let frequency = rate_frequency(rate).ok_or(RtcError::InvalidRate(rate))?;
+    if !PERIODIC_ENABLED.swap(true, Ordering::AcqRel) {
+        if let Err(err) = irq::register(RTC_IRQ, "rtc", periodic_handler) {
+            PERIODIC_ENABLED.store(false, Ordering::Release);
+            return Err(RtcError::Irq(err));
+        }
+    }
+    let mut cmos = CMOS.lock();
+    let status_a = cmos.read(REG_STATUS_A);
+    cmos.write(REG_STATUS_A, (status_a & 0xF0) | rate);
+    let status_b = cmos.read(REG_STATUS_B);
+    cmos.write(REG_STATUS_B, status_b | STATUS_B_PERIODIC);
+    // Descarta uma interrupção pendente para o RTC gerar a próxima
+    cmos.read(REG_STATUS_C);
+    Ok(frequency)
+}
+
+/// Desliga a interrupção periódica e libera a IRQ 8.
+pub fn disable_periodic() {
+    if !PERIODIC_ENABLED.swap(false, Ordering::AcqRel) {
+        return;
+    }
+    {
+        let mut cmos = CMOS.lock();
+        let status_b = cmos.read(REG_STATUS_B);
+        cmos.write(REG_STATUS_B, status_b & !STATUS_B_PERIODIC);
+    }
+    let _ = irq::unregister(RTC_IRQ);
+}
+
+/// Interrupções periódicas recebidas desde o boot.
+pub fn periodic_ticks() -> u64 {
+    PERIODIC_TICKS.load(Ordering::Relaxed)
+}
+
+/// Testa a conversão de datas conhecidas para timestamps Unix e de volta.
+#[test_case]
+fn test_unix_roundtrip() {
+    let cases = [
+        (
+            0,
+            DateTime {
+                year: 1970,
+                month: 1,
+                day: 1,
+                hour: 0,
+                minute: 0,
+                second: 0,
+            },
+        ),
+        (
+            951_782_400,
+            DateTime {
+                year: 2000,
+                month: 2,
+                day: 29,
+                hour: 0,
+                minute: 0,
+                second: 0,
+            },
+        ),
+        (
+            1_709_210_096,
+            DateTime {
+                year: 2024,
+                month: 2,
+                day: 29,
+                hour: 12,
+                minute: 34,
+                second: 56,
+            },
+        ),
+        (
+            4_102_444_799,
+            DateTime {
+                year: 2099,
+                month: 12,
+                day: 31,
+                hour: 23,
+                minute: 59,
+                second: 59,
+            },
+        ),
+    ];
+    for (timestamp, date) in cases.iter() {
+        assert_eq!(date.to_unix(), *timestamp);
+        assert_eq!(DateTime::from_unix(*timestamp), *date);
+    }
+}
+
+/// Testa a decodificação de BCD e do modo 12 horas.
+#[test_case]
+fn test_decode_bcd_12_hour() {
+    let raw = RawTime {
+        second: 0x59,
+        minute: 0x07,
+        hour: HOUR_PM | 0x12,
+        day: 0x31,
+        month: 0x12,
+        year: 0x23,
+        century: 0x20,
+    };
+    let date = decode(raw, 0);
+    assert_eq!(
+        date,
+        DateTime {
+            year: 2023,
+            month: 12,
+            day: 31,
+            hour: 12,
+            minute: 7,
+            second: 59
+        }
+    );
+
+    let midnight = RawTime { hour: 0x12, ..raw };
+    assert_eq!(decode(midnight, 0).hour, 0);
+    let binary = RawTime {
+        hour: 23,
+        year: 24,
+        century: 0,
+        ..raw
+    };
+    assert_eq!(decode(binary, STATUS_B_BINARY | STATUS_B_24_HOUR).hour, 23);
+}
+
+/// Testa que a hora do RTC é plausível e avança com o relógio monotônico.
+#[test_case]
+fn test_now_is_plausible() {
+    let date = read();
+    assert!(date.year >= 2020);
+    assert!((1..=12).contains(&date.month) && (1..=31).contains(&date.day));
+    assert!(now().to_unix() >= date.to_unix());
+}
+
+/// Testa que a interrupção periódica do RTC chega pela IRQ 8.
+#[test_case]
+fn test_periodic_interrupt() {
+    assert_eq!(enable_periodic(6), Ok(1024));
+    let before = periodic_ticks();
+    let start = time::ticks();
+    while time::ticks() < start + 2 {
+        x86_64::instructions::hlt();
+    }
+    assert!(periodic_ticks() > before);
+    disable_periodic();
+    assert_eq!(enable_periodic(2), Err(RtcError::InvalidRate(2)));
+}
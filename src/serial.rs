@@ -30,30 +30,48 @@
 //!
 //! [Testing](https://os.phil-opp.com/testing/) - Blog OS
 
+use crate::spinlock::{IrqSpinlock, IrqSpinlockGuard};
 use core::fmt;
 use fmt::Write;
 use lazy_static::lazy_static;
-use spin::Mutex;
 use uart_16550::SerialPort;
-use x86_64::instructions::interrupts;
 
-// Porta serial COM1 (0x3F8) com mutex para acesso thread-safe.
+// Porta serial COM1 (0x3F8) com lock seguro para interrupções.
 lazy_static! {
-    pub static ref SERIAL1: Mutex<SerialPort> = {
+    pub static ref SERIAL1: IrqSpinlock<SerialPort> = {
         let mut serial_port = unsafe { SerialPort::new(0x3F8) };
         serial_port.init();
-        Mutex::new(serial_port)
+        IrqSpinlock::new("SERIAL1", serial_port)
     };
 }
 
+/// Writer que escreve direto na COM1 sem passar pelo lock de `SERIAL1`.
+///
+/// Só para contextos de emergência (NMI, watchdog) em que o código
+/// interrompido pode estar segurando o lock: usar em outro lugar pode
+/// intercalar caracteres com a saída normal.
+pub struct EmergencyWriter;
+
+impl Write for EmergencyWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        // A porta já foi configurada pela inicialização de SERIAL1
+        let mut port = unsafe { SerialPort::new(0x3F8) };
+        port.write_str(s)
+    }
+}
+
+/// Acesso a `SERIAL1` para panic handlers, mesmo que o panic tenha
+/// acontecido com o lock preso (ver `IrqSpinlock::emergency_lock`).
+pub fn lock_for_panic() -> IrqSpinlockGuard<'static, SerialPort> {
+    unsafe { SERIAL1.emergency_lock() }
+}
+
 #[doc(hidden)]
 pub fn _print(args: fmt::Arguments) {
-    interrupts::without_interrupts(|| {
-        SERIAL1
+    SERIAL1
         .lock()
         .write_fmt(args)
         .expect("Printing to serial failed");
-    })
 }
 
 /// Macro para print na serial sem newline.
@@ -0,0 +1,124 @@
+//! # Contas em ponto flutuante com SSE
+//!
+//! O kernel é `+soft-float` (ver `fpu`): todo `f64` em código do kernel
+//! vira chamada de função (`__adddf3`, `__muldf3`...). Contas pesadas
+//! ficam no crate `simd/`, compilado com SSE2 pelo `build.rs`:
+//!
+//! ```text
+//! x86_64-rust_os.json      kernel   -sse, +soft-float, f64 em rax/rdi
+//! x86_64-rust_os-sse.json  simd/    +sse2,            f64 em xmm0..
+//! ```
+//!
+//! As funções deste módulo são a única porta de entrada: cada uma chama
+//! as rotinas de `simd/` dentro de `fpu::kernel_fpu_begin`, que salva o
+//! estado do dono da FPU e entrega registradores limpos. Quem está usando
+//! a FPU (outra thread, outro contexto) não percebe nada, e o `#NM` traz
+//! o estado de volta no próximo uso.
+//!
+//! ## Pedaços de `CHUNK` elementos
+//!
+//! `kernel_fpu_begin` desabilita interrupções. Para um vetor grande não
+//! segurar as IRQs durante a conta inteira, ele é processado em pedaços
+//! de `CHUNK` elementos, cada um em seu próprio `kernel_fpu_begin`. As
+//! reduções passam o acumulador (em bits) de um pedaço para o próximo,
+//! então nenhuma soma é feita em soft-float.
+//!
+//! ## Uso
+//!
+//! ```ignore
+//! let norm2 = simd::dot(&samples, &samples);
+//! simd::axpy(0.5, &gradient, &mut weights);
+//! ```
+//!
+//! As funções entram em `kernel_fpu_begin`, então não podem ser chamadas
+//! com um `kernel_fpu_begin` já ativo na CPU (pânico).
+
+use crate::fpu;
+
+/// Elementos processados por seção de `kernel_fpu_begin`.
+pub const CHUNK: usize = 1024;
+
+/// Rotinas de `simd/` (ver `simd/src/lib.rs`). `f64` passam como bits.
+pub(crate) mod ffi {
+    unsafe extern "C" {
+        pub fn simd_dot(acc: u64, a: *const f64, b: *const f64, len: usize) -> u64;
+        pub fn simd_sum(acc: u64, values: *const f64, len: usize) -> u64;
+        pub fn simd_axpy(alpha: u64, x: *const f64, y: *mut f64, len: usize);
+    }
+}
+
+/// Produto escalar `Σ a[i] * b[i]`.
+///
+/// # Panics
+///
+/// Se `a` e `b` têm tamanhos diferentes.
+pub fn dot(a: &[f64], b: &[f64]) -> f64 {
+    assert_eq!(a.len(), b.len(), "simd::dot: length mismatch");
+    let mut acc = 0.0f64.to_bits();
+    for (a, b) in a.chunks(CHUNK).zip(b.chunks(CHUNK)) {
+        let _fpu = fpu::kernel_fpu_begin();
+        acc = unsafe { ffi::simd_dot(acc, a.as_ptr(), b.as_ptr(), a.len()) };
+    }
+    f64::from_bits(acc)
+}
+
+/// Soma dos elementos de `values`.
+pub fn sum(values: &[f64]) -> f64 {
+    let mut acc = 0.0f64.to_bits();
+    for chunk in values.chunks(CHUNK) {
+        let _fpu = fpu::kernel_fpu_begin();
+        acc = unsafe { ffi::simd_sum(acc, chunk.as_ptr(), chunk.len()) };
+    }
+    f64::from_bits(acc)
+}
+
+/// `y[i] += alpha * x[i]`.
+///
+/// # Panics
+///
+/// Se `x` e `y` têm tamanhos diferentes.
+pub fn axpy(alpha: f64, x: &[f64], y: &mut [f64]) {
+    assert_eq!(x.len(), y.len(), "simd::axpy: length mismatch");
+    for (x, y) in x.chunks(CHUNK).zip(y.chunks_mut(CHUNK)) {
+        let _fpu = fpu::kernel_fpu_begin();
+        unsafe { ffi::simd_axpy(alpha.to_bits(), x.as_ptr(), y.as_mut_ptr(), x.len()) };
+    }
+}
+
+/// Chamada pelo panic handler de `simd/`.
+#[no_mangle]
+extern "C" fn simd_panic() -> ! {
+    panic!("simd: panic in SSE routine");
+}
+
+/// Testa os resultados das três rotinas com valores exatos.
+#[test_case]
+fn test_simd_routines() {
+    let a = [1.0, 2.0, 3.0, 4.0, 5.0];
+    let b = [0.5, 0.25, 2.0, 1.0, -1.0];
+    assert_eq!(dot(&a, &b), 6.0);
+    assert_eq!(sum(&a), 15.0);
+    assert_eq!(dot(&[], &[]), 0.0);
+
+    let mut y = [1.0; 5];
+    axpy(2.0, &a, &mut y);
+    assert_eq!(y, [3.0, 5.0, 7.0, 9.0, 11.0]);
+}
+
+/// Testa que vetores maiores que `CHUNK` acumulam entre os pedaços.
+#[test_case]
+fn test_simd_across_chunks() {
+    static ONES: [f64; 2 * CHUNK + 3] = [1.0; 2 * CHUNK + 3];
+    assert_eq!(sum(&ONES), (2 * CHUNK + 3) as f64);
+    assert_eq!(dot(&ONES, &ONES), (2 * CHUNK + 3) as f64);
+}
+
+/// Testa que as interrupções voltam depois de cada pedaço.
+#[test_case]
+fn test_simd_restores_interrupts() {
+    use x86_64::instructions::interrupts;
+
+    assert!(interrupts::are_enabled());
+    assert_eq!(sum(&[0.5, 0.25]), 0.75);
+    assert!(interrupts::are_enabled());
+}
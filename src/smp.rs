@@ -0,0 +1,512 @@
+//! # SMP: Ligando as Outras CPUs
+//!
+//! ## BSP e APs
+//!
+//! Só uma CPU, a **BSP** (bootstrap processor), executa o bootloader e o
+//! `kernel_main`. As demais, as **APs** (application processors), ficam
+//! paradas até receberem a sequência INIT-SIPI-SIPI do Local APIC:
+//!
+//! ```text
+//! BSP                                       AP
+//!  │ INIT ──────────────────────────────→  reset, espera SIPI
+//!  │ (10 ms)
+//!  │ SIPI(página) ──────────────────────→  modo real em página << 12
+//!  │ (200 µs, segundo SIPI se necessário)      │ trampolim
+//!  │                                           v
+//!  │                                       long mode → ap_entry()
+//!  │                                           │ GDT/TSS/IDT/APIC próprios
+//!  │ ←──────────────── online = true ──────────┘
+//!  v                                       loop de HLT
+//! ```
+//!
+//! As CPUs são descobertas pelo MADT (`acpi::madt`).
+//!
+//! ## Trabalho nas APs
+//!
+//! Uma AP online espera em HLT por trabalho. `run_on(cpu, f)` deixa uma
+//! closure na caixa de entrada da CPU e a acorda com a IPI de
+//! `apic::WAKEUP_VECTOR`; a AP executa a closure e volta a esperar. É
+//! assim que o executor multi-core (`task::multicore`) inicia um worker
+//! em cada CPU.
+//!
+//! ## Trampolim
+//!
+//! O SIPI só aceita um endereço de página abaixo de 1 MB, e a AP começa
+//! em modo real (16 bits). O trampolim é copiado para o frame baixo que o
+//! frame allocator reserva desde o boot (`low_frame`),
+//! mapeado com identidade (virtual = físico) para continuar executando
+//! quando a paginação ligar, e vai direto de modo real para long mode:
+//!
+//! 1. Carrega uma GDT mínima com um segmento de código de 64 bits
+//! 2. CR4 (PAE) e CR3 copiados da BSP, EFER.LME
+//! 3. Liga CR0.PE e CR0.PG juntos e salta para o código de 64 bits
+//! 4. Carrega a stack da AP e chama `ap_entry` no endereço do kernel
+//!
+//! Os campos marcados como "patched" são preenchidos pela BSP antes de
+//! cada SIPI; as APs são ligadas uma por vez, então um bloco basta.
+//!
+//! ## Referências
+//!
+//! - [SMP](https://wiki.osdev.org/SMP) - OSDev Wiki
+//! - Intel SDM Vol. 3A, Seção 8.4 (Multiple-Processor Initialization)
+
+use crate::{
+    acpi::{self, AcpiError},
+    apic, cpu_local, gdt,
+    ipi::{self, Target},
+    memory::{self, tlb::TlbShootdown},
+    percpu,
+    thread::stack::{Stack, StackError},
+    time,
+};
+use alloc::{boxed::Box, vec::Vec};
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use x86_64::{
+    instructions::interrupts,
+    registers::{
+        control::{Cr3, Cr4, Cr4Flags},
+        model_specific::{Efer, EferFlags},
+    },
+    structures::paging::{mapper::MapToError, Mapper, Page, PageTableFlags, PhysFrame, Size4KiB},
+    VirtAddr,
+};
+
+/// Número máximo de CPUs suportadas.
+pub const MAX_CPUS: usize = 64;
+
+/// Espera entre o INIT e o primeiro SIPI.
+const INIT_DELAY_US: u64 = 10_000;
+/// Espera entre os dois SIPIs.
+const SIPI_DELAY_US: u64 = 200;
+/// Tempo máximo para uma AP ficar online depois dos SIPIs.
+const ONLINE_TIMEOUT_US: u64 = 100_000;
+
+core::arch::global_asm!(
+    r#"
+    .pushsection .text.ap_trampoline, "ax"
+    .global ap_trampoline_start, ap_trampoline_end, ap_long_mode
+    .global ap_gdt, ap_gdt_ptr, ap_far_ptr
+    .global ap_cr3, ap_cr4, ap_efer, ap_stack_top, ap_cpu_index, ap_entry_addr
+
+    .code16
+ap_trampoline_start:
+    cli
+    cld
+    movw %cs, %ax
+    movw %ax, %ds
+
+    lgdtl (ap_gdt_ptr - ap_trampoline_start)
+
+    movl (ap_cr4 - ap_trampoline_start), %eax
+    movl %eax, %cr4
+    movl (ap_cr3 - ap_trampoline_start), %eax
+    movl %eax, %cr3
+
+    movl $0xC0000080, %ecx
+    movl (ap_efer - ap_trampoline_start), %eax
+    xorl %edx, %edx
+    wrmsr
+
+    /* PG | WP | PE */
+    movl %cr0, %eax
+    orl $0x80010001, %eax
+    movl %eax, %cr0
+
+    ljmpl *(ap_far_ptr - ap_trampoline_start)
+
+    .code64
+ap_long_mode:
+    xorl %eax, %eax
+    movw %ax, %ds
+    movw %ax, %es
+    movw %ax, %ss
+    movw %ax, %fs
+    movw %ax, %gs
+    movq ap_stack_top(%rip), %rsp
+    movq ap_cpu_index(%rip), %rdi
+    xorl %ebp, %ebp
+    callq *ap_entry_addr(%rip)
+    ud2
+
+    .balign 8
+ap_gdt:
+    .quad 0
+    .quad 0x00209A0000000000
+ap_gdt_end:
+ap_gdt_ptr:
+    .word ap_gdt_end - ap_gdt - 1
+    .long 0 /* patched: endereço físico de ap_gdt */
+    .balign 8
+ap_far_ptr:
+    .long 0 /* patched: endereço físico de ap_long_mode */
+    .word 0x08
+    .balign 8
+ap_cr3: .quad 0
+ap_cr4: .quad 0
+ap_efer: .quad 0
+ap_stack_top: .quad 0
+ap_cpu_index: .quad 0
+ap_entry_addr: .quad 0
+ap_trampoline_end:
+    .popsection
+"#,
+    options(att_syntax)
+);
+
+unsafe extern "C" {
+    static ap_trampoline_start: u8;
+    static ap_trampoline_end: u8;
+    static ap_long_mode: u8;
+    static ap_gdt: u8;
+    static ap_gdt_ptr: u8;
+    static ap_far_ptr: u8;
+    static ap_cr3: u8;
+    static ap_cr4: u8;
+    static ap_efer: u8;
+    static ap_stack_top: u8;
+    static ap_cpu_index: u8;
+    static ap_entry_addr: u8;
+}
+
+/// Erros ao ligar as APs.
+#[derive(Debug)]
+pub enum SmpError {
+    /// O Local APIC da BSP não foi inicializado (`apic::init`).
+    NoApic,
+    /// O TSC não foi calibrado (`time::calibrate_tsc`), necessário para as esperas.
+    TscNotCalibrated,
+    /// MADT ausente ou inválido.
+    Acpi(AcpiError),
+    /// O memory map não tem frame usável abaixo de 1 MB para o trampolim
+    /// (ver `BootInfoFrameAllocator::low_frame`).
+    NoLowMemory,
+    /// A page table de nível 4 está acima de 4 GB (o trampolim só carrega 32 bits no CR3).
+    PageTableAbove4GiB,
+    /// Falha ao mapear o trampolim.
+    MapFailed(MapToError<Size4KiB>),
+    /// Falha ao alocar a stack de uma AP.
+    Stack(StackError),
+    /// `run_on` com uma CPU que não é uma AP online.
+    InvalidCpu(usize),
+    /// `run_on` com uma AP que ainda não pegou o trabalho anterior.
+    CpuBusy(usize),
+}
+
+/// Trabalho entregue a uma AP por `run_on`.
+type ApWork = Box<dyn FnOnce() + Send>;
+
+cpu_local! {
+    /// Caixa de entrada de cada AP, esvaziada pelo laço de `ap_entry`.
+    static AP_WORK: spin::Mutex<Option<ApWork>> = spin::Mutex::new(None);
+}
+
+/// Estado de uma CPU, indexada na ordem do MADT (BSP sempre 0).
+struct Cpu {
+    apic_id: AtomicU32,
+    online: AtomicBool,
+    /// A AP não respondeu aos SIPIs e foi deixada em INIT.
+    failed: AtomicBool,
+}
+
+static CPUS: [Cpu; MAX_CPUS] = [const {
+    Cpu {
+        apic_id: AtomicU32::new(0),
+        online: AtomicBool::new(false),
+        failed: AtomicBool::new(false),
+    }
+}; MAX_CPUS];
+/// CPUs descobertas (entradas válidas em `CPUS`).
+static CPU_COUNT: AtomicUsize = AtomicUsize::new(1);
+/// CPUs online, inclusive a BSP.
+static ONLINE: AtomicUsize = AtomicUsize::new(1);
+
+/// Uma CPU descoberta no MADT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuInfo {
+    /// Índice da CPU (0 = BSP).
+    pub index: usize,
+    pub apic_id: u32,
+    pub online: bool,
+    /// A CPU não ficou online a tempo em `init`.
+    pub failed: bool,
+}
+
+/// Número de CPUs online, inclusive a BSP.
+pub fn cpu_count() -> usize {
+    ONLINE.load(Ordering::Acquire)
+}
+
+/// CPUs descobertas, online ou não.
+pub fn cpus() -> Vec<CpuInfo> {
+    (0..CPU_COUNT.load(Ordering::Acquire))
+        .map(|index| CpuInfo {
+            index,
+            apic_id: CPUS[index].apic_id.load(Ordering::Relaxed),
+            online: CPUS[index].online.load(Ordering::Acquire),
+            failed: CPUS[index].failed.load(Ordering::Acquire),
+        })
+        .collect()
+}
+
+/// Índice da CPU atual (ver `percpu::cpu_index`).
+pub fn current_cpu() -> usize {
+    percpu::cpu_index()
+}
+
+/// Retorna se a CPU `index` está online.
+pub fn is_online(index: usize) -> bool {
+    index < MAX_CPUS && CPUS[index].online.load(Ordering::Acquire)
+}
+
+/// ID do Local APIC da CPU `index`, se ela foi descoberta.
+pub fn apic_id(index: usize) -> Option<u32> {
+    (index < CPU_COUNT.load(Ordering::Acquire)).then(|| CPUS[index].apic_id.load(Ordering::Relaxed))
+}
+
+/// Tira a CPU `index` do HLT com uma IPI. Não faz nada para a CPU atual
+/// ou CPUs offline.
+pub fn wake(index: usize) {
+    ipi::send(Target::Cpu(index), apic::WAKEUP_VECTOR);
+}
+
+/// Executa `f` na AP `index`, que precisa estar online e livre.
+///
+/// Retorna assim que o trabalho é entregue; `f` roda com interrupções
+/// habilitadas e, ao retornar, a AP volta a esperar em HLT.
+pub fn run_on<F>(index: usize, f: F) -> Result<(), SmpError>
+where
+    F: FnOnce() + Send + 'static,
+{
+    if index == 0 || !is_online(index) {
+        return Err(SmpError::InvalidCpu(index));
+    }
+    let slot = AP_WORK.get_for(index).expect("CPU index out of range");
+    interrupts::without_interrupts(|| {
+        let mut slot = slot.lock();
+        if slot.is_some() {
+            return Err(SmpError::CpuBusy(index));
+        }
+        *slot = Some(Box::new(f));
+        Ok(())
+    })?;
+    wake(index);
+    Ok(())
+}
+
+/// Endereço de um símbolo do trampolim relativo ao seu início.
+fn trampoline_offset(symbol: *const u8) -> u64 {
+    symbol as u64 - (&raw const ap_trampoline_start) as u64
+}
+
+/// Liga todas as APs listadas no MADT. Retorna o número de CPUs online.
+///
+/// Requer `apic::init`, `time::calibrate_tsc` e `memory::set_kernel_space`.
+/// APs que não respondem são marcadas como `failed` e ignoradas.
+pub fn init() -> Result<usize, SmpError> {
+    if !apic::is_initialized() {
+        return Err(SmpError::NoApic);
+    }
+    if time::tsc_frequency().is_none() {
+        return Err(SmpError::TscNotCalibrated);
+    }
+    let madt = acpi::madt().map_err(SmpError::Acpi)?;
+
+    let bsp_id = apic::id();
+    CPUS[0].apic_id.store(bsp_id, Ordering::Relaxed);
+    CPUS[0].online.store(true, Ordering::Release);
+    let aps: Vec<u32> = madt
+        .local_apics
+        .iter()
+        .map(|cpu| cpu.apic_id)
+        .filter(|&id| id != bsp_id)
+        .take(MAX_CPUS - 1)
+        .collect();
+    for (index, &apic_id) in aps.iter().enumerate() {
+        CPUS[index + 1].apic_id.store(apic_id, Ordering::Relaxed);
+    }
+    CPU_COUNT.store(aps.len() + 1, Ordering::Release);
+    if aps.is_empty() {
+        return Ok(cpu_count());
+    }
+
+    let trampoline = Trampoline::install()?;
+    for (index, &apic_id) in aps.iter().enumerate() {
+        let stack = Stack::allocate().map_err(SmpError::Stack)?;
+        trampoline.prepare(index + 1, &stack);
+        if !start_ap(index + 1, apic_id, trampoline.page()) {
+            // A AP pode ter chegado ao trampolim sem ficar online: o INIT a
+            // devolve à espera por SIPI antes do trampolim ser reescrito
+            apic::send_init(apic_id);
+            CPUS[index + 1].failed.store(true, Ordering::Release);
+        }
+        // A AP usa essa stack até o desligamento. Se falhou, a stack vaza:
+        // ela pode ter sido usada antes do INIT e não deve ser reaproveitada
+        core::mem::forget(stack);
+    }
+    trampoline.remove();
+
+    Ok(cpu_count())
+}
+
+/// Envia INIT-SIPI-SIPI e espera a AP ficar online.
+fn start_ap(index: usize, apic_id: u32, page: u8) -> bool {
+    let online = || CPUS[index].online.load(Ordering::Acquire);
+
+    apic::send_init(apic_id);
+    time::delay_us(INIT_DELAY_US);
+    apic::send_startup(apic_id, page);
+    time::delay_us(SIPI_DELAY_US);
+    if !online() {
+        apic::send_startup(apic_id, page);
+    }
+
+    let start = time::rdtsc();
+    let timeout = time::us_to_cycles(ONLINE_TIMEOUT_US).unwrap_or(0);
+    while !online() {
+        if time::rdtsc() - start > timeout {
+            return false;
+        }
+        core::hint::spin_loop();
+    }
+    true
+}
+
+/// Cópia do trampolim num frame baixo, mapeado com identidade.
+struct Trampoline {
+    frame: PhysFrame,
+    /// Se o mapeamento de identidade foi criado por nós (e deve ser removido).
+    mapped: bool,
+}
+
+impl Trampoline {
+    fn install() -> Result<Trampoline, SmpError> {
+        let (cr3_frame, _) = Cr3::read();
+        let cr3 = cr3_frame.start_address().as_u64();
+        if cr3 > u64::from(u32::MAX) {
+            return Err(SmpError::PageTableAbove4GiB);
+        }
+
+        let (frame, mapped) = memory::with_kernel_space(|space| {
+            let frame = space
+                .frame_allocator
+                .low_frame()
+                .ok_or(SmpError::NoLowMemory)?;
+            let page = Page::containing_address(VirtAddr::new(frame.start_address().as_u64()));
+            let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
+            let result = unsafe {
+                space
+                    .mapper
+                    .map_to(page, frame, flags, &mut space.frame_allocator)
+            };
+            match result {
+                Ok(flush) => {
+                    flush.flush();
+                    Ok((frame, true))
+                }
+                Err(MapToError::PageAlreadyMapped(existing)) if existing == frame => {
+                    Ok((frame, false))
+                }
+                Err(err) => Err(SmpError::MapFailed(err)),
+            }
+        })?;
+
+        let trampoline = Trampoline { frame, mapped };
+        let size = trampoline_offset(&raw const ap_trampoline_end) as usize;
+        unsafe {
+            core::ptr::copy_nonoverlapping(&raw const ap_trampoline_start, trampoline.ptr(0), size);
+        }
+
+        let base = frame.start_address().as_u64();
+        // CR4.PCIDE só pode ser ligado com long mode já ativo
+        let cr4 = Cr4::read() - Cr4Flags::PCID;
+        let efer = EferFlags::LONG_MODE_ENABLE | (Efer::read() & EferFlags::NO_EXECUTE_ENABLE);
+        trampoline.write_u32(
+            trampoline_offset(&raw const ap_gdt_ptr) + 2,
+            (base + trampoline_offset(&raw const ap_gdt)) as u32,
+        );
+        trampoline.write_u32(
+            trampoline_offset(&raw const ap_far_ptr),
+            (base + trampoline_offset(&raw const ap_long_mode)) as u32,
+        );
+        trampoline.write_u64(trampoline_offset(&raw const ap_cr3), cr3);
+        trampoline.write_u64(trampoline_offset(&raw const ap_cr4), cr4.bits());
+        trampoline.write_u64(trampoline_offset(&raw const ap_efer), efer.bits());
+        trampoline.write_u64(
+            trampoline_offset(&raw const ap_entry_addr),
+            ap_entry as *const () as u64,
+        );
+        Ok(trampoline)
+    }
+
+    /// Ponteiro para um offset da cópia (pelo offset mapping).
+    fn ptr(&self, offset: u64) -> *mut u8 {
+        memory::phys_to_virt(self.frame.start_address() + offset).as_mut_ptr()
+    }
+
+    fn write_u32(&self, offset: u64, value: u32) {
+        unsafe { self.ptr(offset).cast::<u32>().write_unaligned(value) }
+    }
+
+    fn write_u64(&self, offset: u64, value: u64) {
+        unsafe { self.ptr(offset).cast::<u64>().write_unaligned(value) }
+    }
+
+    /// Página passada no SIPI.
+    fn page(&self) -> u8 {
+        (self.frame.start_address().as_u64() >> 12) as u8
+    }
+
+    /// Preenche a stack e o índice da próxima AP.
+    fn prepare(&self, index: usize, stack: &Stack) {
+        self.write_u64(
+            trampoline_offset(&raw const ap_stack_top),
+            stack.top().as_u64(),
+        );
+        self.write_u64(trampoline_offset(&raw const ap_cpu_index), index as u64);
+    }
+
+    /// Remove o mapeamento de identidade. O frame fica reservado.
+    fn remove(self) {
+        if !self.mapped {
+            return;
+        }
+        let page: Page<Size4KiB> =
+            Page::containing_address(VirtAddr::new(self.frame.start_address().as_u64()));
+        let mut shootdown = TlbShootdown::new();
+        memory::with_kernel_space(|space| {
+            if let Ok((_, flush)) = space.mapper.unmap(page) {
+                flush.ignore();
+                shootdown.add(page);
+            }
+        });
+        shootdown.finish();
+    }
+}
+
+/// Ponto de entrada das APs em Rust, chamado pelo trampolim.
+extern "C" fn ap_entry(index: u64) -> ! {
+    let index = index as usize;
+    percpu::init_ap(index);
+    gdt::init_ap();
+    crate::interrupts::init_idt();
+    crate::fpu::init_cpu();
+    // A BSP já conferiu o mapeamento da mesma página
+    apic::init().expect("Local APIC initialization failed");
+
+    CPUS[index].online.store(true, Ordering::Release);
+    ONLINE.fetch_add(1, Ordering::AcqRel);
+
+    // Espera por trabalho de `run_on`. A caixa é verificada com interrupções
+    // desabilitadas: uma IPI que chegue depois fica pendente e acorda o HLT.
+    loop {
+        interrupts::disable();
+        let work = AP_WORK.with(|slot| slot.lock().take());
+        match work {
+            Some(work) => {
+                interrupts::enable();
+                work();
+            }
+            None => interrupts::enable_and_hlt(),
+        }
+    }
+}
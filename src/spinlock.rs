@@ -0,0 +1,348 @@
+//! # Spinlocks Seguros para Interrupções
+//!
+//! ## O problema
+//!
+//! Um `spin::Mutex` comum trava a CPU para sempre se um handler de
+//! interrupção tentar pegar um lock que o código interrompido segura:
+//!
+//! ```text
+//! kernel_main: WRITER.lock() ──→ IRQ do teclado ──→ println!
+//!                                                   └─ WRITER.lock()  ← espera
+//!                                                      para sempre
+//! ```
+//!
+//! O mesmo acontece com um panic enquanto o lock está preso: o panic
+//! handler tenta imprimir e nunca consegue o lock.
+//!
+//! ## `IrqSpinlock`
+//!
+//! - **Interrupções desabilitadas** enquanto o lock é segurado, então um
+//!   handler nunca interrompe o dono na mesma CPU. A CPU conta quantos
+//!   guards estão vivos (`percpu::irq_save`) e só reabilita as interrupções
+//!   quando o último é solto, mesmo que os guards sejam soltos fora de
+//!   ordem.
+//! - **Dono registrado**: CPU, RIP e local (`arquivo:linha`) de quem
+//!   pegou o lock, para diagnósticos (`owner()`, watchdog).
+//! - **Reentrância detectada**: pegar de novo um lock que a própria CPU
+//!   segura é um deadlock certo; em vez de travar, entra em panic dizendo
+//!   quem segura o lock.
+//! - **Caminho de emergência**: `emergency_lock()` para panic handlers,
+//!   que toma o lock à força se o dono nunca vai soltá-lo.
+//! - **Contadores**: aquisições e quantas delas precisaram esperar
+//!   (`stats()`).
+//!
+//! ```ignore
+//! static COUNTER: IrqSpinlock<u64> = IrqSpinlock::new("counter", 0);
+//!
+//! *COUNTER.lock() += 1;
+//! ```
+
+use crate::{backtrace, percpu};
+use core::{
+    cell::UnsafeCell,
+    fmt,
+    ops::{Deref, DerefMut},
+    panic::Location,
+    ptr,
+    sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, AtomicUsize, Ordering},
+};
+use x86_64::instructions::interrupts;
+
+/// Valor de `owner_cpu` quando ninguém segura o lock.
+const NO_OWNER: usize = usize::MAX;
+
+/// Tentativas de `emergency_lock()` antes de tomar o lock de outra CPU.
+const EMERGENCY_SPINS: usize = 10_000_000;
+
+/// Spinlock que desabilita interrupções e detecta reentrância.
+pub struct IrqSpinlock<T: ?Sized> {
+    name: &'static str,
+    locked: AtomicBool,
+    owner_cpu: AtomicUsize,
+    owner_rip: AtomicU64,
+    owner_location: AtomicPtr<Location<'static>>,
+    acquisitions: AtomicU64,
+    contentions: AtomicU64,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for IrqSpinlock<T> {}
+unsafe impl<T: ?Sized + Send> Sync for IrqSpinlock<T> {}
+
+/// Quem segura um lock.
+#[derive(Debug, Clone, Copy)]
+pub struct Owner {
+    pub cpu: usize,
+    /// Endereço de retorno da chamada a `lock()`.
+    pub rip: u64,
+    pub location: &'static Location<'static>,
+}
+
+impl fmt::Display for Owner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "CPU {} at {} (RIP {:#x}",
+            self.cpu, self.location, self.rip
+        )?;
+        if let Some((name, offset)) = backtrace::resolve(self.rip) {
+            write!(f, " {}+{:#x}", name, offset)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Contadores de um lock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockStats {
+    pub name: &'static str,
+    pub acquisitions: u64,
+    /// Aquisições que encontraram o lock ocupado e tiveram que esperar.
+    pub contentions: u64,
+}
+
+impl<T> IrqSpinlock<T> {
+    pub const fn new(name: &'static str, value: T) -> Self {
+        IrqSpinlock {
+            name,
+            locked: AtomicBool::new(false),
+            owner_cpu: AtomicUsize::new(NO_OWNER),
+            owner_rip: AtomicU64::new(0),
+            owner_location: AtomicPtr::new(ptr::null_mut()),
+            acquisitions: AtomicU64::new(0),
+            contentions: AtomicU64::new(0),
+            data: UnsafeCell::new(value),
+        }
+    }
+}
+
+impl<T: ?Sized> IrqSpinlock<T> {
+    /// Adquire o lock, desabilitando interrupções até o guard ser solto.
+    ///
+    /// # Panics
+    ///
+    /// Se a CPU atual já segura o lock (deadlock).
+    #[track_caller]
+    #[inline(never)]
+    pub fn lock(&self) -> IrqSpinlockGuard<'_, T> {
+        let rip = return_address();
+        percpu::irq_save();
+        let cpu = percpu::cpu_index();
+
+        if !self.try_acquire() {
+            if self.owner_cpu.load(Ordering::Relaxed) == cpu {
+                let owner = self.owner();
+                self.report_reentrancy(owner);
+            }
+            self.contentions.fetch_add(1, Ordering::Relaxed);
+            while !self.try_acquire() {
+                core::hint::spin_loop();
+            }
+        }
+        self.acquired(cpu, rip, Location::caller(), true)
+    }
+
+    /// Adquire o lock só se estiver livre.
+    #[track_caller]
+    #[inline(never)]
+    pub fn try_lock(&self) -> Option<IrqSpinlockGuard<'_, T>> {
+        let rip = return_address();
+        percpu::irq_save();
+        if self.try_acquire() {
+            Some(self.acquired(percpu::cpu_index(), rip, Location::caller(), true))
+        } else {
+            percpu::irq_restore();
+            None
+        }
+    }
+
+    /// Adquire o lock para um panic handler.
+    ///
+    /// Se a CPU atual já segura o lock (panic no meio de um `println!`),
+    /// ou se outra CPU não solta o lock em algum tempo, o lock é tomado à
+    /// força. Interrupções ficam desabilitadas.
+    ///
+    /// # Safety
+    ///
+    /// Quebra a exclusão mútua: o dono anterior pode continuar usando os
+    /// dados. Só deve ser usado quando o sistema não vai continuar
+    /// executando normalmente (panic, watchdog).
+    #[track_caller]
+    #[inline(never)]
+    pub unsafe fn emergency_lock(&self) -> IrqSpinlockGuard<'_, T> {
+        let rip = return_address();
+        interrupts::disable();
+        let cpu = percpu::cpu_index();
+        let mut spins = 0;
+        while !self.try_acquire() {
+            if self.owner_cpu.load(Ordering::Relaxed) == cpu || spins == EMERGENCY_SPINS {
+                break;
+            }
+            spins += 1;
+            core::hint::spin_loop();
+        }
+        self.acquired(cpu, rip, Location::caller(), false)
+    }
+
+    /// Dono atual do lock (o valor pode mudar logo depois).
+    pub fn owner(&self) -> Option<Owner> {
+        let location = self.owner_location.load(Ordering::Acquire);
+        let cpu = self.owner_cpu.load(Ordering::Relaxed);
+        if !self.is_locked() || location.is_null() || cpu == NO_OWNER {
+            return None;
+        }
+        Some(Owner {
+            cpu,
+            rip: self.owner_rip.load(Ordering::Relaxed),
+            location: unsafe { &*location },
+        })
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked.load(Ordering::Relaxed)
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn stats(&self) -> LockStats {
+        LockStats {
+            name: self.name,
+            acquisitions: self.acquisitions.load(Ordering::Relaxed),
+            contentions: self.contentions.load(Ordering::Relaxed),
+        }
+    }
+
+    fn try_acquire(&self) -> bool {
+        self.locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    fn acquired(
+        &self,
+        cpu: usize,
+        rip: u64,
+        location: &'static Location<'static>,
+        nested: bool,
+    ) -> IrqSpinlockGuard<'_, T> {
+        self.owner_cpu.store(cpu, Ordering::Relaxed);
+        self.owner_rip.store(rip, Ordering::Relaxed);
+        self.owner_location
+            .store(location as *const _ as *mut _, Ordering::Release);
+        self.acquisitions.fetch_add(1, Ordering::Relaxed);
+        IrqSpinlockGuard { lock: self, nested }
+    }
+
+    #[cold]
+    #[track_caller]
+    fn report_reentrancy(&self, owner: Option<Owner>) -> ! {
+        match owner {
+            Some(owner) => panic!(
+                "deadlock: lock `{}` acquired again by its owner, {}",
+                self.name, owner
+            ),
+            None => panic!(
+                "deadlock: lock `{}` acquired again on the same CPU",
+                self.name
+            ),
+        }
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for IrqSpinlock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("IrqSpinlock")
+            .field("name", &self.name)
+            .field("owner", &self.owner())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Guard de `IrqSpinlock`; solta o lock no drop e, se era o último guard
+/// da CPU, restaura as interrupções.
+pub struct IrqSpinlockGuard<'a, T: ?Sized> {
+    lock: &'a IrqSpinlock<T>,
+    /// Abriu uma seção de `percpu::irq_save()` (falso em `emergency_lock`).
+    nested: bool,
+}
+
+impl<T: ?Sized> Deref for IrqSpinlockGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for IrqSpinlockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for IrqSpinlockGuard<'_, T> {
+    fn drop(&mut self) {
+        // O dono é limpo antes de soltar: `owner_cpu == cpu` com o lock
+        // preso significa que esta CPU ainda o segura.
+        self.lock.owner_cpu.store(NO_OWNER, Ordering::Relaxed);
+        self.lock.locked.store(false, Ordering::Release);
+        if self.nested {
+            percpu::irq_restore();
+        }
+    }
+}
+
+/// Endereço de retorno da função que chama (precisa de frame pointers e
+/// de `#[inline(never)]` no chamador).
+#[inline(always)]
+fn return_address() -> u64 {
+    let rbp = backtrace::frame_pointer();
+    unsafe { (rbp as *const u64).add(1).read() }
+}
+
+/// Testa que o lock desabilita interrupções e restaura o estado anterior.
+#[test_case]
+fn test_lock_restores_interrupts() {
+    static LOCK: IrqSpinlock<u32> = IrqSpinlock::new("test", 0);
+
+    assert!(interrupts::are_enabled());
+    {
+        let mut value = LOCK.lock();
+        *value += 1;
+        assert!(!interrupts::are_enabled());
+        assert_eq!(LOCK.owner().unwrap().cpu, 0);
+    }
+    assert!(interrupts::are_enabled());
+    assert!(LOCK.owner().is_none());
+    assert_eq!(LOCK.stats().acquisitions, 1);
+}
+
+/// Testa que `try_lock` falha enquanto o lock está preso.
+#[test_case]
+fn test_try_lock_fails_while_held() {
+    static LOCK: IrqSpinlock<u32> = IrqSpinlock::new("test", 0);
+
+    let guard = LOCK.lock();
+    assert!(LOCK.try_lock().is_none());
+    assert!(!interrupts::are_enabled());
+    drop(guard);
+    assert_eq!(*LOCK.try_lock().unwrap(), 0);
+    assert_eq!(LOCK.stats().contentions, 0);
+}
+
+/// Testa que soltar guards fora de ordem não reabilita as interrupções
+/// enquanto algum lock ainda está preso.
+#[test_case]
+fn test_out_of_order_release_keeps_interrupts_disabled() {
+    static OUTER: IrqSpinlock<u32> = IrqSpinlock::new("outer", 0);
+    static INNER: IrqSpinlock<u32> = IrqSpinlock::new("inner", 0);
+
+    let outer = OUTER.lock();
+    let inner = INNER.lock();
+    drop(outer);
+    assert!(!interrupts::are_enabled());
+    drop(inner);
+    assert!(interrupts::are_enabled());
+}
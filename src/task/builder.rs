@@ -0,0 +1,107 @@
+//! Criação de tasks com nome e prioridade.
+//!
+//! ```ignore
+//! let handle = task::Builder::new()
+//!     .name("keyboard")
+//!     .priority(Priority::High)
+//!     .spawn_on(&mut executor, keyboard::print_keypresses());
+//! ```
+//!
+//! O local da chamada (`arquivo:linha`) é registrado via `#[track_caller]`
+//! e aparece no `snapshot()` junto com o nome.
+
+use super::{
+    executor::{self, Executor, Spawner},
+    info::TaskStats,
+    join,
+    multicore::MulticoreExecutor,
+    JoinHandle, Priority, TaskId,
+};
+use alloc::{boxed::Box, sync::Arc};
+use core::{future::Future, panic::Location};
+
+/// Opções de uma nova task.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Builder {
+    name: Option<&'static str>,
+    priority: Priority,
+}
+
+impl Builder {
+    pub const fn new() -> Self {
+        Builder {
+            name: None,
+            priority: Priority::Normal,
+        }
+    }
+
+    /// Nome exibido em listagens de tasks e avisos do executor.
+    pub const fn name(mut self, name: &'static str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    pub const fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    #[track_caller]
+    fn stats(self) -> Arc<TaskStats> {
+        Arc::new(TaskStats::new(TaskId::new(), self.name, Location::caller(), self.priority))
+    }
+
+    /// Cria a task no executor informado (o future não precisa ser `Send`).
+    #[track_caller]
+    pub fn spawn_on<F>(self, executor: &mut Executor, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + 'static,
+        F::Output: 'static,
+    {
+        let stats = self.stats();
+        let (future, handle) = join::joinable(stats.id, future);
+        executor.spawn_pinned(stats, Box::pin(future));
+        handle
+    }
+
+    /// Cria a task pelo `Spawner` informado.
+    #[track_caller]
+    pub fn spawn_with<F>(self, spawner: &Spawner, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let stats = self.stats();
+        let (future, handle) = join::joinable(stats.id, future);
+        spawner.push(stats, Box::pin(future));
+        handle
+    }
+
+    /// Cria a task no executor multi-core informado.
+    #[track_caller]
+    pub fn spawn_multicore<F>(self, executor: &MulticoreExecutor, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let stats = self.stats();
+        let (future, handle) = join::joinable(stats.id, future);
+        executor.spawn_pinned(stats, Box::pin(future));
+        handle
+    }
+
+    /// Cria a task no executor em execução.
+    ///
+    /// # Panics
+    ///
+    /// Se nenhum executor estiver rodando (`Executor::run`).
+    #[track_caller]
+    pub fn spawn<F>(self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let spawner = executor::current_spawner().expect("no executor running");
+        self.spawn_with(&spawner, future)
+    }
+}
@@ -0,0 +1,149 @@
+//! # Tokens de Cancelamento
+//!
+//! ## Para que servem?
+//!
+//! `JoinHandle::abort()` descarta o future no próximo ponto de
+//! escalonamento, sem dar à task chance de terminar o que estava fazendo.
+//! Um `CancellationToken` é a forma **cooperativa**: a task observa o
+//! token e decide onde parar.
+//!
+//! ```text
+//! token.cancel() ──→ cancelled = true ──→ wake() de cada task aguardando
+//!                                                │
+//!        select(token.cancelled(), trabalho) ←───┘ → limpa e retorna
+//! ```
+//!
+//! Clones do token compartilham o mesmo estado: cancelar um cancela todos.
+
+use alloc::{sync::Arc, vec::Vec};
+use core::{
+    future::Future,
+    pin::{pin, Pin},
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    task::{Context, Poll, Waker},
+};
+use futures_util::future::{self, Either};
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+
+struct TokenState {
+    cancelled: AtomicBool,
+    /// Wakers das tasks aguardando `cancelled()`, com o id de cada espera.
+    wakers: Mutex<Vec<(u64, Waker)>>,
+    next_waiter: AtomicU64,
+}
+
+/// Token clonável para pedir o cancelamento cooperativo de tasks.
+#[derive(Clone)]
+pub struct CancellationToken {
+    state: Arc<TokenState>,
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        CancellationToken {
+            state: Arc::new(TokenState {
+                cancelled: AtomicBool::new(false),
+                wakers: Mutex::new(Vec::new()),
+                next_waiter: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Cancela o token, acordando todas as tasks que o aguardam.
+    ///
+    /// Pode ser chamado de handlers de interrupção.
+    pub fn cancel(&self) {
+        if self.state.cancelled.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let wakers = interrupts::without_interrupts(|| core::mem::take(&mut *self.state.wakers.lock()));
+        for (_, waker) in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Retorna se o token já foi cancelado.
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Future que completa quando o token é cancelado.
+    pub fn cancelled(&self) -> Cancelled<'_> {
+        Cancelled {
+            token: self,
+            waiter: None,
+        }
+    }
+
+    /// Executa `future` até completar ou até o token ser cancelado.
+    ///
+    /// Retorna `None` se cancelado; o future é descartado nesse caso.
+    pub async fn run_until_cancelled<F: Future>(&self, future: F) -> Option<F::Output> {
+        match future::select(pin!(self.cancelled()), pin!(future)).await {
+            Either::Left(_) => None,
+            Either::Right((output, _)) => Some(output),
+        }
+    }
+}
+
+/// Future retornado por `CancellationToken::cancelled`.
+///
+/// Descartá-lo antes de completar remove seu waker do token.
+pub struct Cancelled<'a> {
+    token: &'a CancellationToken,
+    /// Id da espera registrada no token.
+    waiter: Option<u64>,
+}
+
+impl Future for Cancelled<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        let this = self.get_mut();
+        if this.token.is_cancelled() {
+            return Poll::Ready(());
+        }
+
+        let state = &this.token.state;
+        let waiter = &mut this.waiter;
+        interrupts::without_interrupts(|| {
+            let mut wakers = state.wakers.lock();
+            let queued = waiter.and_then(|id| wakers.iter_mut().find(|(other, _)| *other == id));
+            match queued {
+                Some((_, waker)) => {
+                    if !waker.will_wake(cx.waker()) {
+                        *waker = cx.waker().clone();
+                    }
+                }
+                None => {
+                    let id = state.next_waiter.fetch_add(1, Ordering::Relaxed);
+                    wakers.push((id, cx.waker().clone()));
+                    *waiter = Some(id);
+                }
+            }
+        });
+
+        // Verifica de novo: `cancel()` pode ter rodado antes do registro
+        if this.token.is_cancelled() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for Cancelled<'_> {
+    fn drop(&mut self) {
+        if let Some(id) = self.waiter {
+            let wakers = &self.token.state.wakers;
+            interrupts::without_interrupts(|| wakers.lock().retain(|&(other, _)| other != id));
+        }
+    }
+}
@@ -0,0 +1,222 @@
+//! Canal de difusão: cada receiver recebe todas as mensagens.
+//!
+//! O canal guarda as últimas `capacity` mensagens, numeradas em sequência.
+//! Cada receiver lembra o número da próxima que vai ler; quem fica mais de
+//! `capacity` mensagens para trás perde as mais antigas e recebe
+//! `RecvError::Lagged` com a quantidade perdida:
+//!
+//! ```text
+//! buffer: [seq 5][seq 6][seq 7]     (capacity = 3, próxima = 8)
+//!            ^                ^
+//!      receiver A (next=5)  receiver B (next=8, aguardando)
+//! receiver C (next=3) → Lagged(2), continua em 5
+//! ```
+
+use super::SendError;
+use crate::task::{coop, sync::with_state};
+use alloc::{collections::VecDeque, sync::Arc, vec::Vec};
+use core::{
+    future::poll_fn,
+    pin::Pin,
+    task::{ready, Context, Poll, Waker},
+};
+use futures_util::stream::Stream;
+
+/// Erro de recebimento.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvError {
+    /// Todos os senders foram descartados e não há mais mensagens.
+    Closed,
+    /// O receiver ficou para trás e perdeu este número de mensagens.
+    Lagged(u64),
+}
+
+struct State<T> {
+    /// Últimas mensagens, da mais antiga para a mais nova.
+    buffer: VecDeque<T>,
+    /// Número de sequência da próxima mensagem enviada.
+    next_seq: u64,
+    senders: usize,
+    receivers: usize,
+    /// Receivers aguardando uma nova mensagem.
+    waiters: Vec<Waker>,
+}
+
+impl<T> State<T> {
+    /// Número de sequência da mensagem mais antiga no buffer.
+    fn oldest_seq(&self) -> u64 {
+        self.next_seq - self.buffer.len() as u64
+    }
+}
+
+struct Chan<T> {
+    capacity: usize,
+    state: spin::Mutex<State<T>>,
+}
+
+/// Cria um canal de difusão que guarda as últimas `capacity` mensagens.
+///
+/// # Panics
+/// Se `capacity` for 0.
+pub fn channel<T: Clone>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "broadcast channel capacity must be positive");
+    let chan = Arc::new(Chan {
+        capacity,
+        state: spin::Mutex::new(State {
+            buffer: VecDeque::with_capacity(capacity),
+            next_seq: 0,
+            senders: 1,
+            receivers: 1,
+            waiters: Vec::new(),
+        }),
+    });
+    (Sender { chan: chan.clone() }, Receiver { chan, next: 0 })
+}
+
+/// Lado que envia. Clonável.
+pub struct Sender<T> {
+    chan: Arc<Chan<T>>,
+}
+
+impl<T: Clone> Sender<T> {
+    /// Envia para todos os receivers, sem esperar. Seguro em handlers de
+    /// interrupção.
+    ///
+    /// Retorna o número de receivers, ou devolve o valor se não há nenhum.
+    pub fn send(&self, value: T) -> Result<usize, SendError<T>> {
+        let capacity = self.chan.capacity;
+        let result = with_state(&self.chan.state, |state| {
+            if state.receivers == 0 {
+                return Err(SendError(value));
+            }
+            // A mais antiga é descartada fora da seção crítica
+            let evicted = if state.buffer.len() == capacity {
+                state.buffer.pop_front()
+            } else {
+                None
+            };
+            state.buffer.push_back(value);
+            state.next_seq += 1;
+            Ok((state.receivers, evicted, core::mem::take(&mut state.waiters)))
+        });
+        let (receivers, evicted, waiters) = result?;
+        drop(evicted);
+        waiters.into_iter().for_each(Waker::wake);
+        Ok(receivers)
+    }
+
+    /// Cria um receiver que recebe as mensagens enviadas a partir de agora.
+    pub fn subscribe(&self) -> Receiver<T> {
+        let next = with_state(&self.chan.state, |state| {
+            state.receivers += 1;
+            state.next_seq
+        });
+        Receiver {
+            chan: self.chan.clone(),
+            next,
+        }
+    }
+
+    /// Número de receivers ativos.
+    pub fn receiver_count(&self) -> usize {
+        with_state(&self.chan.state, |state| state.receivers)
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        with_state(&self.chan.state, |state| state.senders += 1);
+        Sender {
+            chan: self.chan.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let waiters = with_state(&self.chan.state, |state| {
+            state.senders -= 1;
+            if state.senders == 0 {
+                core::mem::take(&mut state.waiters)
+            } else {
+                Vec::new()
+            }
+        });
+        waiters.into_iter().for_each(Waker::wake);
+    }
+}
+
+/// Lado que recebe. Cada receiver tem sua própria posição no canal.
+pub struct Receiver<T> {
+    chan: Arc<Chan<T>>,
+    /// Número de sequência da próxima mensagem a ler.
+    next: u64,
+}
+
+impl<T: Clone> Receiver<T> {
+    /// Lê a próxima mensagem; `None` se ainda não chegou.
+    fn try_next(&mut self, waker: Option<&Waker>) -> Option<Result<T, RecvError>> {
+        let next = &mut self.next;
+        with_state(&self.chan.state, |state| {
+            let oldest = state.oldest_seq();
+            if *next < oldest {
+                let lagged = oldest - *next;
+                *next = oldest;
+                return Some(Err(RecvError::Lagged(lagged)));
+            }
+            if *next < state.next_seq {
+                let value = state.buffer[(*next - oldest) as usize].clone();
+                *next += 1;
+                return Some(Ok(value));
+            }
+            if state.senders == 0 {
+                return Some(Err(RecvError::Closed));
+            }
+            if let Some(waker) = waker {
+                if !state.waiters.iter().any(|waiting| waiting.will_wake(waker)) {
+                    state.waiters.push(waker.clone());
+                }
+            }
+            None
+        })
+    }
+
+    /// Recebe a próxima mensagem.
+    pub async fn recv(&mut self) -> Result<T, RecvError> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Recebe sem esperar; `Ok(None)` se não há mensagem nova.
+    pub fn try_recv(&mut self) -> Result<Option<T>, RecvError> {
+        self.try_next(None).transpose()
+    }
+
+    pub fn poll_recv(&mut self, cx: &mut Context) -> Poll<Result<T, RecvError>> {
+        ready!(coop::poll_proceed(cx));
+        // O waker é registrado na mesma seção crítica da verificação
+        match self.try_next(Some(cx.waker())) {
+            Some(result) => Poll::Ready(result),
+            None => Poll::Pending,
+        }
+    }
+}
+
+/// Stream das mensagens; termina quando o canal fecha.
+///
+/// Mensagens perdidas aparecem como `Err(RecvError::Lagged(n))`.
+impl<T: Clone> Stream for Receiver<T> {
+    type Item = Result<T, RecvError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        match ready!(self.get_mut().poll_recv(cx)) {
+            Err(RecvError::Closed) => Poll::Ready(None),
+            result => Poll::Ready(Some(result)),
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        with_state(&self.chan.state, |state| state.receivers -= 1);
+    }
+}
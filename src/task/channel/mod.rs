@@ -0,0 +1,91 @@
+//! # Canais entre Tasks
+//!
+//! ## Para que servem?
+//!
+//! Sem canais, tasks só se comunicam por estáticos globais com filas e
+//! wakers escritos à mão (como era o `ScancodeStream`). Os canais
+//! encapsulam esse padrão: o lado que recebe estaciona a task com o
+//! waker, e o lado que envia a acorda.
+//!
+//! ```text
+//! Sender ──send(valor)──→ [ fila ] ──→ Receiver.recv().await
+//!                            │                   ^
+//!                            └──── wake() ───────┘
+//! ```
+//!
+//! ## Tipos de canal
+//!
+//! | Canal | Produtores | Consumidores | Uso |
+//! |-------|------------|--------------|-----|
+//! | `mpsc::channel(n)` | N | 1 | Fila com capacidade `n` (backpressure) |
+//! | `mpsc::unbounded()` | N | 1 | Fila sem limite |
+//! | `oneshot::channel()` | 1 | 1 | Um único valor (ex: resposta) |
+//! | `broadcast::channel(n)` | N | N | Cada receiver vê todas as mensagens |
+//!
+//! Receivers de `mpsc` e `broadcast` implementam `Stream`, e o `Sender`
+//! de `mpsc` implementa `Sink` (de `futures_util`).
+//!
+//! ## Interrupções
+//!
+//! `mpsc::Sender::try_send`, `oneshot::Sender::send` e
+//! `broadcast::Sender::send` nunca esperam e podem ser chamados de
+//! handlers de interrupção. O canal limitado pré-aloca sua fila, então
+//! `try_send` não aloca memória.
+
+use core::fmt;
+
+pub mod broadcast;
+pub mod mpsc;
+pub mod oneshot;
+
+/// Erro de envio: o receiver foi descartado. Devolve o valor.
+#[derive(PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SendError(..)")
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("channel closed")
+    }
+}
+
+/// Erro de envio sem espera. Devolve o valor.
+#[derive(PartialEq, Eq)]
+pub enum TrySendError<T> {
+    /// O canal está cheio.
+    Full(T),
+    /// O receiver foi descartado.
+    Closed(T),
+}
+
+impl<T> TrySendError<T> {
+    /// Recupera o valor que não foi enviado.
+    pub fn into_inner(self) -> T {
+        match self {
+            TrySendError::Full(value) | TrySendError::Closed(value) => value,
+        }
+    }
+}
+
+impl<T> fmt::Debug for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TrySendError::Full(_) => f.write_str("Full(..)"),
+            TrySendError::Closed(_) => f.write_str("Closed(..)"),
+        }
+    }
+}
+
+/// Erro de recebimento sem espera.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    /// Nenhum valor disponível no momento.
+    Empty,
+    /// Todos os senders foram descartados e não há mais valores.
+    Closed,
+}
@@ -0,0 +1,359 @@
+//! Canal de múltiplos produtores e um consumidor.
+//!
+//! No canal limitado cada envio precisa de uma **vaga**: `send().await`
+//! (ou `Sink::poll_ready`) reserva uma vaga, esperando se o canal estiver
+//! cheio, e o envio a ocupa. Cada `recv` libera uma vaga e acorda um único
+//! sender, o mais antigo da fila de espera. Se esse sender desistir antes
+//! de usar a vaga (future de `send` descartado), ela passa para o próximo,
+//! então ninguém fica preso.
+
+use super::{SendError, TryRecvError, TrySendError};
+use crate::task::{coop, sync::with_state};
+use alloc::{collections::VecDeque, sync::Arc};
+use core::{
+    future::{poll_fn, Future},
+    pin::Pin,
+    task::{ready, Context, Poll, Waker},
+};
+use futures_util::{sink::Sink, stream::Stream, task::AtomicWaker};
+
+struct State<T> {
+    queue: VecDeque<T>,
+    /// Vagas reservadas por senders e ainda não usadas.
+    reserved: usize,
+    /// Senders esperando uma vaga, com o id de cada espera.
+    send_waiters: VecDeque<(u64, Waker)>,
+    next_waiter: u64,
+    senders: usize,
+    receiver_closed: bool,
+}
+
+struct Chan<T> {
+    /// `None` = sem limite.
+    capacity: Option<usize>,
+    state: spin::Mutex<State<T>>,
+    rx_waker: AtomicWaker,
+}
+
+impl<T> State<T> {
+    fn has_room(&self, capacity: Option<usize>) -> bool {
+        capacity.is_none_or(|capacity| self.queue.len() + self.reserved < capacity)
+    }
+
+    /// Libera uma vaga, retornando o sender que deve ser acordado.
+    fn release_slot(&mut self) -> Option<Waker> {
+        self.send_waiters.pop_front().map(|(_, waker)| waker)
+    }
+}
+
+/// Cria um canal com capacidade para `capacity` valores.
+///
+/// # Panics
+/// Se `capacity` for 0.
+pub fn channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "mpsc channel capacity must be positive");
+    new_chan(Some(capacity), VecDeque::with_capacity(capacity))
+}
+
+/// Cria um canal sem limite de capacidade: `send` nunca espera.
+pub fn unbounded<T>() -> (Sender<T>, Receiver<T>) {
+    new_chan(None, VecDeque::new())
+}
+
+fn new_chan<T>(capacity: Option<usize>, queue: VecDeque<T>) -> (Sender<T>, Receiver<T>) {
+    let chan = Arc::new(Chan {
+        capacity,
+        state: spin::Mutex::new(State {
+            queue,
+            reserved: 0,
+            send_waiters: VecDeque::new(),
+            next_waiter: 0,
+            senders: 1,
+            receiver_closed: false,
+        }),
+        rx_waker: AtomicWaker::new(),
+    });
+    let sender = Sender {
+        chan: chan.clone(),
+        reserved: false,
+        waiter: None,
+    };
+    (sender, Receiver { chan })
+}
+
+/// Lado que envia. Clonável; o canal fecha quando todos são descartados.
+pub struct Sender<T> {
+    chan: Arc<Chan<T>>,
+    /// Se este sender tem uma vaga reservada.
+    reserved: bool,
+    /// Id da espera em `send_waiters` (ou já acordada e ainda não atendida).
+    waiter: Option<u64>,
+}
+
+impl<T> Sender<T> {
+    /// Reserva uma vaga, registrando o waker se o canal estiver cheio.
+    fn poll_reserve(&mut self, cx: &mut Context) -> Poll<Result<(), SendError<()>>> {
+        if self.reserved {
+            return Poll::Ready(Ok(()));
+        }
+        let capacity = self.chan.capacity;
+        let waiter = &mut self.waiter;
+        let result = with_state(&self.chan.state, |state| {
+            if state.receiver_closed {
+                *waiter = None;
+                Poll::Ready(Err(SendError(())))
+            } else if state.has_room(capacity) {
+                state.reserved += 1;
+                if let Some(id) = waiter.take() {
+                    state.send_waiters.retain(|&(other, _)| other != id);
+                }
+                Poll::Ready(Ok(()))
+            } else {
+                let queued = waiter.and_then(|id| state.send_waiters.iter_mut().find(|(other, _)| *other == id));
+                match (queued, *waiter) {
+                    (Some((_, waker)), _) => {
+                        if !waker.will_wake(cx.waker()) {
+                            *waker = cx.waker().clone();
+                        }
+                    }
+                    // Acordado, mas um `try_send` ocupou a vaga antes: volta
+                    // para o início da fila, sem perder a vez
+                    (None, Some(id)) => state.send_waiters.push_front((id, cx.waker().clone())),
+                    (None, None) => {
+                        let id = state.next_waiter;
+                        state.next_waiter += 1;
+                        state.send_waiters.push_back((id, cx.waker().clone()));
+                        *waiter = Some(id);
+                    }
+                }
+                Poll::Pending
+            }
+        });
+        if let Poll::Ready(Ok(())) = result {
+            self.reserved = true;
+        }
+        result
+    }
+
+    /// Desiste de esperar por uma vaga. Se este sender já tinha sido
+    /// acordado e não usou a vaga, acorda o próximo da fila no lugar dele.
+    fn cancel_wait(&mut self) {
+        let id = match self.waiter.take() {
+            Some(id) => id,
+            None => return,
+        };
+        let capacity = self.chan.capacity;
+        let next = with_state(&self.chan.state, |state| {
+            match state.send_waiters.iter().position(|&(other, _)| other == id) {
+                Some(index) => {
+                    state.send_waiters.remove(index);
+                    None
+                }
+                None if state.has_room(capacity) => state.release_slot(),
+                None => None,
+            }
+        });
+        if let Some(waker) = next {
+            waker.wake();
+        }
+    }
+
+    /// Envia usando a vaga reservada por `poll_reserve`.
+    fn send_reserved(&mut self, value: T) -> Result<(), SendError<T>> {
+        assert!(self.reserved, "send without a reserved slot");
+        self.reserved = false;
+        let result = with_state(&self.chan.state, |state| {
+            state.reserved -= 1;
+            if state.receiver_closed {
+                Err(SendError(value))
+            } else {
+                state.queue.push_back(value);
+                Ok(())
+            }
+        });
+        self.chan.rx_waker.wake();
+        result
+    }
+
+    /// Envia um valor, esperando por uma vaga se o canal estiver cheio.
+    pub async fn send(&mut self, value: T) -> Result<(), SendError<T>> {
+        if (Reserve { sender: &mut *self }).await.is_err() {
+            return Err(SendError(value));
+        }
+        self.send_reserved(value)
+    }
+
+    /// Envia sem esperar. Seguro em handlers de interrupção.
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        let capacity = self.chan.capacity;
+        let result = with_state(&self.chan.state, |state| {
+            if state.receiver_closed {
+                Err(TrySendError::Closed(value))
+            } else if state.has_room(capacity) {
+                state.queue.push_back(value);
+                Ok(())
+            } else {
+                Err(TrySendError::Full(value))
+            }
+        });
+        if result.is_ok() {
+            self.chan.rx_waker.wake();
+        }
+        result
+    }
+
+    /// Retorna se o receiver foi descartado.
+    pub fn is_closed(&self) -> bool {
+        with_state(&self.chan.state, |state| state.receiver_closed)
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        with_state(&self.chan.state, |state| state.senders += 1);
+        Sender {
+            chan: self.chan.clone(),
+            reserved: false,
+            waiter: None,
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.cancel_wait();
+        let reserved = self.reserved;
+        let (last, waiter) = with_state(&self.chan.state, |state| {
+            state.senders -= 1;
+            let waiter = if reserved {
+                state.reserved -= 1;
+                state.release_slot()
+            } else {
+                None
+            };
+            (state.senders == 0, waiter)
+        });
+        if let Some(waker) = waiter {
+            waker.wake();
+        }
+        if last {
+            self.chan.rx_waker.wake();
+        }
+    }
+}
+
+/// Future da reserva de `send`: se for descartado durante a espera, a
+/// vez na fila é repassada (`cancel_wait`).
+struct Reserve<'a, T> {
+    sender: &'a mut Sender<T>,
+}
+
+impl<T> Future for Reserve<'_, T> {
+    type Output = Result<(), SendError<()>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        self.sender.poll_reserve(cx)
+    }
+}
+
+impl<T> Drop for Reserve<'_, T> {
+    fn drop(&mut self) {
+        self.sender.cancel_wait();
+    }
+}
+
+impl<T> Sink<T> for Sender<T> {
+    type Error = SendError<()>;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_reserve(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
+        self.get_mut().send_reserved(item).map_err(|_| SendError(()))
+    }
+
+    /// Valores enviados já estão visíveis para o receiver.
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    /// O canal só fecha quando todos os senders são descartados.
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Lado que recebe.
+pub struct Receiver<T> {
+    chan: Arc<Chan<T>>,
+}
+
+impl<T> Receiver<T> {
+    /// Retira um valor da fila, liberando uma vaga.
+    fn pop(&self) -> Result<T, TryRecvError> {
+        let (result, waiter) = with_state(&self.chan.state, |state| match state.queue.pop_front() {
+            Some(value) => (Ok(value), state.release_slot()),
+            None if state.senders == 0 => (Err(TryRecvError::Closed), None),
+            None => (Err(TryRecvError::Empty), None),
+        });
+        if let Some(waker) = waiter {
+            waker.wake();
+        }
+        result
+    }
+
+    /// Recebe o próximo valor. Retorna `None` quando todos os senders
+    /// foram descartados e a fila esvaziou.
+    pub async fn recv(&mut self) -> Option<T> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Recebe sem esperar.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        self.pop()
+    }
+
+    pub fn poll_recv(&mut self, cx: &mut Context) -> Poll<Option<T>> {
+        ready!(coop::poll_proceed(cx));
+        match self.pop() {
+            Ok(value) => return Poll::Ready(Some(value)),
+            Err(TryRecvError::Closed) => return Poll::Ready(None),
+            Err(TryRecvError::Empty) => {}
+        }
+
+        // Registra o waker e verifica de novo (um envio pode ter chegado
+        // entre o pop e o registro)
+        self.chan.rx_waker.register(cx.waker());
+        match self.pop() {
+            Ok(value) => Poll::Ready(Some(value)),
+            Err(TryRecvError::Closed) => Poll::Ready(None),
+            Err(TryRecvError::Empty) => Poll::Pending,
+        }
+    }
+}
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<T>> {
+        self.get_mut().poll_recv(cx)
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        // Fecha o canal e acorda quem espera por vaga; os valores
+        // pendentes são descartados fora da seção com interrupções
+        // desabilitadas
+        let (queue, waiters) = with_state(&self.chan.state, |state| {
+            state.receiver_closed = true;
+            (
+                core::mem::take(&mut state.queue),
+                core::mem::take(&mut state.send_waiters),
+            )
+        });
+        drop(queue);
+        waiters.into_iter().for_each(|(_, waker)| waker.wake());
+    }
+}
@@ -0,0 +1,117 @@
+//! Canal de um único valor.
+//!
+//! Útil para respostas: a task que pede algo cria o canal, entrega o
+//! `Sender` e aguarda o `Receiver`, que é um `Future`.
+
+use crate::task::{coop, sync::with_state};
+use alloc::sync::Arc;
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use futures_util::task::AtomicWaker;
+
+/// Erro de recebimento: o sender foi descartado sem enviar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecvError;
+
+struct State<T> {
+    value: Option<T>,
+    sender_dropped: bool,
+    receiver_dropped: bool,
+}
+
+struct Chan<T> {
+    state: spin::Mutex<State<T>>,
+    rx_waker: AtomicWaker,
+}
+
+/// Cria um canal de um único valor.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let chan = Arc::new(Chan {
+        state: spin::Mutex::new(State {
+            value: None,
+            sender_dropped: false,
+            receiver_dropped: false,
+        }),
+        rx_waker: AtomicWaker::new(),
+    });
+    (Sender { chan: chan.clone() }, Receiver { chan })
+}
+
+/// Lado que envia; consumido pelo envio.
+pub struct Sender<T> {
+    chan: Arc<Chan<T>>,
+}
+
+impl<T> Sender<T> {
+    /// Envia o valor sem esperar. Seguro em handlers de interrupção.
+    ///
+    /// Devolve o valor se o receiver já foi descartado.
+    pub fn send(self, value: T) -> Result<(), T> {
+        with_state(&self.chan.state, |state| {
+            if state.receiver_dropped {
+                Err(value)
+            } else {
+                state.value = Some(value);
+                Ok(())
+            }
+        })
+        // O `drop` de `self` acorda o receiver
+    }
+
+    /// Retorna se o receiver foi descartado.
+    pub fn is_closed(&self) -> bool {
+        with_state(&self.chan.state, |state| state.receiver_dropped)
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        with_state(&self.chan.state, |state| state.sender_dropped = true);
+        self.chan.rx_waker.wake();
+    }
+}
+
+/// Lado que recebe: um `Future` que resolve para o valor.
+pub struct Receiver<T> {
+    chan: Arc<Chan<T>>,
+}
+
+impl<T> Receiver<T> {
+    /// Recebe sem esperar; `Ok(None)` se o valor ainda não chegou.
+    pub fn try_recv(&mut self) -> Result<Option<T>, RecvError> {
+        with_state(&self.chan.state, |state| match state.value.take() {
+            Some(value) => Ok(Some(value)),
+            None if state.sender_dropped => Err(RecvError),
+            None => Ok(None),
+        })
+    }
+}
+
+impl<T> Future for Receiver<T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        ready!(coop::poll_proceed(cx));
+        if let Some(value) = self.try_recv().transpose() {
+            return Poll::Ready(value);
+        }
+        self.chan.rx_waker.register(cx.waker());
+        match self.try_recv().transpose() {
+            Some(value) => Poll::Ready(value),
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let value = with_state(&self.chan.state, |state| {
+            state.receiver_dropped = true;
+            state.value.take()
+        });
+        drop(value);
+    }
+}
@@ -0,0 +1,95 @@
+//! # Escalonamento Cooperativo: Orçamento e `yield_now`
+//!
+//! ## O problema
+//!
+//! O executor só retoma o controle quando uma task retorna `Pending`.
+//! Uma task cujos futures estão sempre prontos (ex: uma fila sempre cheia)
+//! nunca retorna, e tasks de maior prioridade acordadas por IRQs esperam.
+//!
+//! ## Orçamento por poll
+//!
+//! Antes de cada poll o executor dá à task um orçamento de operações.
+//! Futures "folha" (streams, filas, `JoinHandle`, ...) chamam
+//! `poll_proceed()` antes de produzir um valor; com o orçamento esgotado
+//! eles se acordam e retornam `Pending`, forçando a task a ceder:
+//!
+//! ```text
+//! executor: reset(POLL_BUDGET) → poll(task) → ... → unlimited()
+//!                                   │
+//!                   poll_proceed(): budget > 0 ? budget -= 1 → Ready
+//!                                              : wake() → Pending (cede)
+//! ```
+//!
+//! Fora do `Executor` (ex: `SimpleExecutor`) o orçamento é ilimitado.
+//!
+//! ## `yield_now`
+//!
+//! Cede explicitamente: retorna `Pending` uma vez, já se acordando, e a
+//! task volta para o fim da fila da sua prioridade.
+
+use crate::cpu_local;
+use core::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicU32, Ordering},
+    task::{Context, Poll},
+};
+
+/// Operações permitidas por poll de uma task.
+pub const POLL_BUDGET: u32 = 128;
+/// Valor de `BUDGET` fora de um poll do executor.
+const UNLIMITED: u32 = u32::MAX;
+
+cpu_local! {
+    /// Orçamento restante da task em execução em cada CPU.
+    static BUDGET: AtomicU32 = AtomicU32::new(UNLIMITED);
+}
+
+/// Dá à próxima task o orçamento completo. Chamado pelo executor.
+pub(crate) fn reset() {
+    BUDGET.get().store(POLL_BUDGET, Ordering::Relaxed);
+}
+
+/// Remove o limite ao sair do poll de uma task.
+pub(crate) fn unlimited() {
+    BUDGET.get().store(UNLIMITED, Ordering::Relaxed);
+}
+
+/// Consome uma unidade do orçamento da task atual.
+///
+/// Retorna `Pending` (após agendar a task de novo) se o orçamento acabou.
+pub fn poll_proceed(cx: &mut Context) -> Poll<()> {
+    let budget = BUDGET.get().load(Ordering::Relaxed);
+    if budget == UNLIMITED {
+        return Poll::Ready(());
+    }
+    if budget == 0 {
+        cx.waker().wake_by_ref();
+        return Poll::Pending;
+    }
+    BUDGET.get().store(budget - 1, Ordering::Relaxed);
+    Poll::Ready(())
+}
+
+/// Future retornado por `yield_now`.
+pub struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if self.yielded {
+            return Poll::Ready(());
+        }
+        self.yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// Cede a vez para as outras tasks prontas.
+pub fn yield_now() -> YieldNow {
+    YieldNow { yielded: false }
+}
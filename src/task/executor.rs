@@ -8,21 +8,24 @@
 //! ## Arquitetura
 //!
 //! ```text
-//! ┌───────────────────────────────────────┐
-//! │              Executor                 │
-//! ├─────────────┬────────────┬────────────┤
-//! │   tasks     │ task_queue │waker_cache│
-//! │ BTreeMap    │ ArrayQueue │ BTreeMap  │
-//! │ ID→Task     │ [TaskId]   │ ID→Waker  │
-//! └─────────────┴────────────┴────────────┘
+//! ┌────────────────────────────────────────┐
+//! │              Executor                  │
+//! ├─────────────┬─────────────┬────────────┤
+//! │   tasks     │ task_queues │waker_cache│
+//! │ BTreeMap    │ SegQueue    │ BTreeMap  │
+//! │ ID→Task     │ [TaskId] x3 │ ID→Waker  │
+//! └─────────────┴─────────────┴────────────┘
 //! ```
 //!
 //! ## Fluxo de Execução
 //!
-//! 1. `spawn()`: Adiciona task ao mapa e ID à fila
+//! 1. `spawn()`: Embrulha o future em uma task, adiciona ao mapa e ID à
+//!    fila, e retorna um `JoinHandle` para o resultado
 //! 2. `run()`: Loop infinito que processa tasks e dorme quando ocioso
-//! 3. `run_ready_tasks()`: Faz poll de cada task na fila
+//! 3. `run_ready_tasks()`: Recebe tasks criadas por `Spawner`s e faz poll
+//!    das tasks prontas, sempre da fila de maior prioridade
 //! 4. `sleep_if_idle()`: Usa HLT para economizar CPU quando não há trabalho
+//!    (ou cede a CPU para outras threads, ver `thread::wait_for_interrupt`)
 //!
 //! ## Sistema de Wakers
 //!
@@ -31,45 +34,215 @@
 //! chama `wake()`, que adiciona o TaskId de volta à fila.
 //!
 //! ```text
-//! Handler de IRQ → wake() → task_queue.push(id) → Executor processa
+//! Handler de IRQ → wake() → task_queues[prioridade].push(id) → Executor processa
 //! ```
 //!
-//! ## Por que ArrayQueue?
+//! ## Prioridades
 //!
-//! `ArrayQueue` do crossbeam é lock-free e pode ser usado em handlers
-//! de interrupção sem causar deadlocks.
+//! Há uma fila por `Priority`. Antes de cada poll o executor escolhe a
+//! primeira task da fila não-vazia de maior prioridade, então uma task
+//! `High` acordada por uma IRQ roda logo após o poll atual, à frente de
+//! qualquer trabalho `Normal` ou `Background` já enfileirado.
+//!
+//! Como o modelo é cooperativo, isso só funciona se cada poll for curto:
+//! o orçamento de `coop` força a task a ceder depois de `POLL_BUDGET`
+//! operações, e `yield_now()` cede explicitamente.
+//!
+//! ## Spawner
+//!
+//! `run()` empresta o executor para sempre, então tasks em execução não
+//! podem chamar `Executor::spawn`. Um `Spawner` compartilha apenas a fila
+//! de tasks novas com o executor:
+//!
+//! ```text
+//! Task / IRQ → Spawner::spawn() → spawn_queue.push(future)
+//!                                        │
+//!              run_ready_tasks() ←───────┘ (insere em tasks e task_queue)
+//! ```
+//!
+//! Enquanto `run()` executa, `task::spawn()` usa o spawner desse executor
+//! (um por CPU, ver `percpu`).
+//! Futures enviados por um `Spawner` precisam ser `Send`, pois podem vir
+//! de handlers de interrupção.
+//!
+//! ## Deduplicação de wakes
+//!
+//! Cada `TaskWaker` tem uma flag atômica `scheduled`. Só o wake que a
+//! muda de `false` para `true` coloca o ID na fila; wakes repetidos antes
+//! do próximo poll são descartados. O executor limpa a flag logo antes
+//! de fazer poll da task:
+//!
+//! ```text
+//! wake() ─→ scheduled.swap(true) ─ era false? ─ sim → task_queue.push(id)
+//!                                        │ não
+//!                                        └──→ já está na fila, ignora
+//! ```
+//!
+//! Assim cada task ocupa no máximo uma posição nas filas, e o tamanho das
+//! filas acompanha o número de tasks.
+//!
+//! ## Informações de tasks
+//!
+//! Cada task tem um `TaskStats` (ver `task::info`) compartilhado com o
+//! registro do executor. O executor marca o estado em cada wake e poll e
+//! soma os ciclos de TSC gastos em `poll`; `snapshot()` lista as tasks
+//! vivas e as que completaram recentemente, como um `ps`. Polls longos
+//! demais geram avisos (ver `task::slow_poll`).
+//!
+//! ## Por que SegQueue?
+//!
+//! `SegQueue` do crossbeam é lock-free e pode ser usado em handlers
+//! de interrupção sem causar deadlocks. Diferente de `ArrayQueue`, não
+//! tem capacidade fixa: cresce em blocos conforme o número de tasks (o
+//! allocator desabilita interrupções, então alocar dentro de um wake
+//! vindo de uma IRQ é seguro).
 //!
 //! ## Estudo baseado em
 //!
 //! [Async/Await](https://os.phil-opp.com/async-await/) - Blog OS
 
-use super::{Task, TaskId};
-use alloc::{collections::BTreeMap, sync::Arc, task::Wake};
-use core::task::{Context, Poll, Waker};
-use crossbeam_queue::ArrayQueue;
-use x86_64::instructions::interrupts::{self, enable_and_hlt};
+use super::{
+    coop,
+    info::{Registry, TaskInfo, TaskStats},
+    slow_poll,
+    Builder, JoinHandle, Priority, Task, TaskId,
+};
+use crate::{cpu_local, thread};
+use alloc::{boxed::Box, collections::BTreeMap, sync::Arc, task::Wake, vec::Vec};
+use core::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicBool, Ordering},
+    task::{Context, Waker},
+};
+use crossbeam_queue::SegQueue;
+use x86_64::instructions::interrupts;
+
+/// Future de uma task criada por um `Spawner`, ainda fora do executor.
+type SpawnedFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+cpu_local! {
+    /// Spawner do executor em execução em cada CPU, usado por `spawn()`.
+    static CURRENT_SPAWNER: RefCell<Option<Spawner>> = RefCell::new(None);
+}
+
+/// Handle clonável para criar tasks no executor.
+///
+/// Pode ser usado de dentro de tasks e de handlers de interrupção
+/// (a fila é lock-free e o allocator desabilita interrupções).
+#[derive(Clone)]
+pub struct Spawner {
+    spawn_queue: Arc<SegQueue<(Arc<TaskStats>, SpawnedFuture)>>,
+    registry: Arc<Registry>,
+}
+
+impl Spawner {
+    /// Agenda um future como nova task e retorna o handle do seu resultado.
+    ///
+    /// A task é inserida no executor na próxima passada de `run_ready_tasks`.
+    #[track_caller]
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        Builder::new().spawn_with(self, future)
+    }
+
+    /// Como `spawn`, com a prioridade informada.
+    #[track_caller]
+    pub fn spawn_with_priority<F>(&self, priority: Priority, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        Builder::new().priority(priority).spawn_with(self, future)
+    }
+
+    /// Estado de todas as tasks do executor (ver `task::snapshot`).
+    pub fn snapshot(&self) -> Vec<TaskInfo> {
+        self.registry.snapshot()
+    }
+
+    pub(super) fn push(&self, stats: Arc<TaskStats>, future: SpawnedFuture) {
+        self.registry.insert(stats.clone());
+        self.spawn_queue.push((stats, future));
+    }
+}
+
+/// Agenda um future no executor em execução.
+///
+/// # Panics
+///
+/// Se nenhum executor estiver rodando (`Executor::run`).
+#[track_caller]
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    Builder::new().spawn(future)
+}
+
+/// Como `spawn`, com a prioridade informada.
+#[track_caller]
+pub fn spawn_with_priority<F>(priority: Priority, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    Builder::new().priority(priority).spawn(future)
+}
+
+/// Estado de todas as tasks do executor em execução, para listagens
+/// estilo `ps` (ver `info::write_task_table`).
+///
+/// Inclui as tasks vivas e as que completaram recentemente; vazio se
+/// nenhum executor estiver rodando.
+pub fn snapshot() -> Vec<TaskInfo> {
+    current_spawner().map(|spawner| spawner.snapshot()).unwrap_or_default()
+}
+
+/// Spawner do executor em execução.
+pub(super) fn current_spawner() -> Option<Spawner> {
+    CURRENT_SPAWNER.with(|spawner| spawner.borrow().clone())
+}
+
+/// Troca o spawner usado por `spawn()`, retornando o anterior.
+fn set_current_spawner(spawner: Option<Spawner>) -> Option<Spawner> {
+    // `with` desabilita interrupções: um handler que chame `spawn()` não
+    // encontra o `RefCell` emprestado.
+    CURRENT_SPAWNER.with(|current| current.replace(spawner))
+}
 
 /// Waker customizado que recoloca a task na fila quando acordada.
 ///
 /// Cada task tem seu próprio TaskWaker que conhece o TaskId
-/// e tem uma referência à fila compartilhada.
+/// e tem uma referência à fila da sua prioridade.
 struct TaskWaker {
-    task_id: TaskId,
-    task_queue: Arc<ArrayQueue<TaskId>>,
+    stats: Arc<TaskStats>,
+    /// Se a task já está na fila (ou completou, e não deve voltar a ela)
+    scheduled: AtomicBool,
+    task_queue: Arc<SegQueue<TaskId>>,
 }
 
 impl TaskWaker {
-    /// Adiciona o TaskId de volta à fila para ser processado.
+    /// Adiciona o TaskId de volta à fila, se ainda não estiver nela.
     fn wake_task(&self) {
-        self.task_queue.push(self.task_id).expect("task_queue full");
+        if !self.scheduled.swap(true, Ordering::AcqRel) {
+            self.stats.set_queued();
+            self.task_queue.push(self.stats.id);
+        }
     }
 
-    /// Cria um novo Waker para a task especificada.
-    fn new(task_id: TaskId, task_queue: Arc<ArrayQueue<TaskId>>) -> Waker {
-        Waker::from(Arc::new(TaskWaker {
-            task_id,
+    /// Cria um novo TaskWaker para a task especificada.
+    fn new(stats: Arc<TaskStats>, task_queue: Arc<SegQueue<TaskId>>) -> Arc<TaskWaker> {
+        Arc::new(TaskWaker {
+            stats,
+            scheduled: AtomicBool::new(false),
             task_queue,
-        }))
+        })
     }
 }
 
@@ -90,72 +263,164 @@ impl Wake for TaskWaker {
 pub struct Executor {
     /// Mapa de todas as tasks registradas (ID -> Task)
     tasks: BTreeMap<TaskId, Task>,
-    /// Fila de IDs de tasks prontas para executar (lock-free)
-    task_queue: Arc<ArrayQueue<TaskId>>,
+    /// Filas de IDs de tasks prontas, uma por prioridade (lock-free)
+    task_queues: [Arc<SegQueue<TaskId>>; Priority::COUNT],
     /// Cache de Wakers para reutilização
-    waker_cache: BTreeMap<TaskId, Waker>,
+    waker_cache: BTreeMap<TaskId, Arc<TaskWaker>>,
+    /// Tasks criadas por `Spawner`s, ainda não inseridas em `tasks`
+    spawn_queue: Arc<SegQueue<(Arc<TaskStats>, SpawnedFuture)>>,
+    /// Estado das tasks para `snapshot()`
+    registry: Arc<Registry>,
 }
 
 impl Executor {
     pub fn new() -> Self {
-        Executor { 
-            tasks: BTreeMap::new(), 
-            task_queue: Arc::new(ArrayQueue::new(100)), 
-            waker_cache: BTreeMap::new() 
+        Executor {
+            tasks: BTreeMap::new(),
+            task_queues: core::array::from_fn(|_| Arc::new(SegQueue::new())),
+            waker_cache: BTreeMap::new(),
+            spawn_queue: Arc::new(SegQueue::new()),
+            registry: Arc::new(Registry::new()),
         }
     }
 
-    pub fn spawn(&mut self, task: Task) {
+    /// Retorna um `Spawner` que cria tasks neste executor.
+    pub fn spawner(&self) -> Spawner {
+        Spawner {
+            spawn_queue: self.spawn_queue.clone(),
+            registry: self.registry.clone(),
+        }
+    }
+
+    /// Agenda um future como nova task e retorna o handle do seu resultado.
+    ///
+    /// Descartar o `JoinHandle` não cancela a task (ver `JoinHandle::detach`).
+    /// Para dar nome à task, use `task::Builder`.
+    #[track_caller]
+    pub fn spawn<F>(&mut self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + 'static,
+        F::Output: 'static,
+    {
+        Builder::new().spawn_on(self, future)
+    }
+
+    /// Como `spawn`, com a prioridade informada.
+    #[track_caller]
+    pub fn spawn_with_priority<F>(&mut self, priority: Priority, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + 'static,
+        F::Output: 'static,
+    {
+        Builder::new().priority(priority).spawn_on(self, future)
+    }
+
+    /// Número de tasks vivas (ainda não completas nem canceladas).
+    pub fn task_count(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Estado de todas as tasks: as vivas e as que completaram recentemente.
+    pub fn snapshot(&self) -> Vec<TaskInfo> {
+        self.registry.snapshot()
+    }
+
+    pub(super) fn spawn_pinned(&mut self, stats: Arc<TaskStats>, future: Pin<Box<dyn Future<Output = ()>>>) {
+        self.registry.insert(stats.clone());
+        self.spawn_task(Task::from_pinned(future, stats));
+    }
+
+    fn spawn_task(&mut self, task: Task) {
         let task_id = task.id;
+        let queue = self.task_queues[task.stats.priority.index()].clone();
+        let waker = TaskWaker::new(task.stats.clone(), queue);
         if self.tasks.insert(task.id, task).is_some() {
             panic!("task with same ID already in tasks")
         }
-        self.task_queue.push(task_id).expect("queue full")
+        waker.wake_task();
+        self.waker_cache.insert(task_id, waker);
     }
 
     pub fn run(&mut self) -> ! {
+        set_current_spawner(Some(self.spawner()));
         loop {
             self.run_ready_tasks();
             self.sleep_if_idle();
         }
     }
 
+    /// Executa tasks até não haver nenhuma pronta, sem dormir.
+    ///
+    /// Útil em testes, onde o executor não deve rodar para sempre.
+    pub fn run_until_idle(&mut self) {
+        let previous = set_current_spawner(Some(self.spawner()));
+        self.run_ready_tasks();
+        set_current_spawner(previous);
+    }
+
+    /// Move as tasks criadas por `Spawner`s para o executor.
+    fn accept_spawned_tasks(&mut self) {
+        // Já registradas pelo `Spawner`
+        while let Some((stats, future)) = self.spawn_queue.pop() {
+            self.spawn_task(Task::from_pinned(future, stats));
+        }
+    }
+
+    /// Retira a próxima task pronta da fila de maior prioridade.
+    fn next_ready_task(&self) -> Option<TaskId> {
+        self.task_queues.iter().find_map(|queue| queue.pop())
+    }
+
     fn run_ready_tasks(&mut self) {
-        let Self {
-            tasks,
-            task_queue,
-            waker_cache,
-        } = self;
-
-        while let Some(task_id) = task_queue.pop() {
-            let task = match tasks.get_mut(&task_id) {
-                Some(task) => task,
-                None => continue,
-            };
-
-            let waker = waker_cache
-                .entry(task_id)
-                .or_insert_with(|| TaskWaker::new(task_id, task_queue.clone()));
-            
-            let mut context = Context::from_waker(waker);
-            match task.poll(&mut context) {
-                Poll::Ready(()) => {
-                    tasks.remove(&task_id);
-                    waker_cache.remove(&task_id);
-                }
-                Poll::Pending => {}
+        // Tasks podem criar outras tasks: aceita novas antes de cada poll,
+        // para que uma task `High` recém-criada passe à frente
+        loop {
+            self.accept_spawned_tasks();
+            match self.next_ready_task() {
+                Some(task_id) => self.poll_task(task_id),
+                None => break,
             }
         }
-        
     }
 
+    fn poll_task(&mut self, task_id: TaskId) {
+        let (task, task_waker) = match (self.tasks.get_mut(&task_id), self.waker_cache.get(&task_id)) {
+            (Some(task), Some(task_waker)) => (task, task_waker),
+            _ => return,
+        };
+
+        // Wakes a partir daqui (inclusive durante o poll) reenfileiram a task
+        task_waker.scheduled.store(false, Ordering::Release);
+        let waker = Waker::from(task_waker.clone());
+
+        let mut context = Context::from_waker(&waker);
+        task.stats.begin_poll();
+        coop::reset();
+        let timer = slow_poll::start(&task.stats);
+        let poll = task.poll(&mut context);
+        let cycles = timer.finish(&task.stats);
+        coop::unlimited();
+        task.stats.end_poll(cycles, poll.is_ready());
+        if poll.is_ready() {
+            // Wakers que sobreviverem à task não a colocam mais na fila
+            task_waker.scheduled.store(true, Ordering::Release);
+            self.tasks.remove(&task_id);
+            self.waker_cache.remove(&task_id);
+            self.registry.complete(task_id);
+        }
+    }
+
+    fn is_idle(&self) -> bool {
+        self.task_queues.iter().all(|queue| queue.is_empty()) && self.spawn_queue.is_empty()
+    }
 
     fn sleep_if_idle(&self) {
-        if self.task_queue.is_empty() {
+        if self.is_idle() {
             interrupts::disable();
 
-            if self.task_queue.is_empty() {
-                enable_and_hlt();
+            if self.is_idle() {
+                // HLT, ou cede a CPU se outras threads estiverem prontas
+                thread::wait_for_interrupt();
             } else {
                 interrupts::enable();
             }
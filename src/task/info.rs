@@ -0,0 +1,240 @@
+//! # Informações de Tasks
+//!
+//! Cada task carrega um `TaskStats` compartilhado entre o executor (que o
+//! atualiza a cada poll), o `TaskWaker` (que marca a task como na fila) e
+//! o registro usado por `snapshot()`:
+//!
+//! ```text
+//!            spawn ──→ Queued ──poll──→ Polling ──Pending──→ Pending
+//!                        ^                 │                    │
+//!                        └──── wake() ─────┼────────────────────┘
+//!                                          └──Ready──→ Completed
+//! ```
+//!
+//! `snapshot()` copia esses dados para `TaskInfo`s, usados em listagens
+//! estilo `ps` (`write_task_table`) e em asserções de testes.
+
+use super::{Priority, TaskId};
+use crate::time;
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    sync::Arc,
+    vec::Vec,
+};
+use core::{
+    fmt,
+    panic::Location,
+    sync::atomic::{AtomicU64, AtomicU8, Ordering},
+};
+use x86_64::instructions::interrupts;
+
+/// Tasks completas mantidas no registro para listagem.
+const RECENT_COMPLETED: usize = 16;
+
+/// Estado de execução de uma task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskState {
+    /// Na fila de tasks prontas.
+    Queued,
+    /// Sendo executada (dentro de `poll`).
+    Polling,
+    /// Aguardando um wake.
+    Pending,
+    /// Completou (ou foi cancelada).
+    Completed,
+}
+
+impl TaskState {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => TaskState::Queued,
+            1 => TaskState::Polling,
+            2 => TaskState::Pending,
+            _ => TaskState::Completed,
+        }
+    }
+}
+
+impl fmt::Display for TaskState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            TaskState::Queued => "queued",
+            TaskState::Polling => "polling",
+            TaskState::Pending => "pending",
+            TaskState::Completed => "completed",
+        })
+    }
+}
+
+/// Dados de uma task atualizados pelo executor.
+pub(crate) struct TaskStats {
+    pub(crate) id: TaskId,
+    pub(crate) name: Option<&'static str>,
+    pub(crate) location: &'static Location<'static>,
+    pub(crate) priority: Priority,
+    state: AtomicU8,
+    polls: AtomicU64,
+    poll_cycles: AtomicU64,
+    max_poll_cycles: AtomicU64,
+}
+
+impl TaskStats {
+    pub(crate) fn new(
+        id: TaskId,
+        name: Option<&'static str>,
+        location: &'static Location<'static>,
+        priority: Priority,
+    ) -> Self {
+        TaskStats {
+            id,
+            name,
+            location,
+            priority,
+            state: AtomicU8::new(TaskState::Queued as u8),
+            polls: AtomicU64::new(0),
+            poll_cycles: AtomicU64::new(0),
+            max_poll_cycles: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn state(&self) -> TaskState {
+        TaskState::from_u8(self.state.load(Ordering::Acquire))
+    }
+
+    /// Marca a task como na fila. Chamado por wakes (inclusive de IRQs).
+    pub(crate) fn set_queued(&self) {
+        self.state.store(TaskState::Queued as u8, Ordering::Release);
+    }
+
+    pub(crate) fn begin_poll(&self) {
+        self.state.store(TaskState::Polling as u8, Ordering::Release);
+    }
+
+    /// Registra o fim de um poll que levou `cycles` ciclos do TSC.
+    pub(crate) fn end_poll(&self, cycles: u64, ready: bool) {
+        self.polls.fetch_add(1, Ordering::Relaxed);
+        self.poll_cycles.fetch_add(cycles, Ordering::Relaxed);
+        self.max_poll_cycles.fetch_max(cycles, Ordering::Relaxed);
+        if ready {
+            self.state.store(TaskState::Completed as u8, Ordering::Release);
+        } else {
+            // Um wake durante o poll já a marcou como `Queued`
+            let _ = self.state.compare_exchange(
+                TaskState::Polling as u8,
+                TaskState::Pending as u8,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            );
+        }
+    }
+
+    pub(crate) fn info(&self) -> TaskInfo {
+        TaskInfo {
+            id: self.id,
+            name: self.name,
+            location: self.location,
+            priority: self.priority,
+            state: self.state(),
+            polls: self.polls.load(Ordering::Relaxed),
+            poll_cycles: self.poll_cycles.load(Ordering::Relaxed),
+            max_poll_cycles: self.max_poll_cycles.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Cópia dos dados de uma task no momento do `snapshot()`.
+#[derive(Debug, Clone, Copy)]
+pub struct TaskInfo {
+    pub id: TaskId,
+    pub name: Option<&'static str>,
+    /// Onde a task foi criada (`spawn`).
+    pub location: &'static Location<'static>,
+    pub priority: Priority,
+    pub state: TaskState,
+    /// Número de vezes que a task foi executada.
+    pub polls: u64,
+    /// Tempo total dentro de `poll`, em ciclos do TSC.
+    pub poll_cycles: u64,
+    /// Poll mais longo da task, em ciclos do TSC.
+    pub max_poll_cycles: u64,
+}
+
+impl TaskInfo {
+    /// Tempo total dentro de `poll` em microssegundos, se o TSC foi calibrado.
+    pub fn poll_time_us(&self) -> Option<u64> {
+        time::cycles_to_us(self.poll_cycles)
+    }
+
+    /// Poll mais longo em microssegundos, se o TSC foi calibrado.
+    pub fn max_poll_time_us(&self) -> Option<u64> {
+        time::cycles_to_us(self.max_poll_cycles)
+    }
+}
+
+/// Registro das tasks de um executor, compartilhado com seus `Spawner`s.
+pub(crate) struct Registry {
+    live: spin::Mutex<BTreeMap<TaskId, Arc<TaskStats>>>,
+    completed: spin::Mutex<VecDeque<TaskInfo>>,
+}
+
+impl Registry {
+    pub(crate) fn new() -> Self {
+        Registry {
+            live: spin::Mutex::new(BTreeMap::new()),
+            completed: spin::Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub(crate) fn insert(&self, stats: Arc<TaskStats>) {
+        interrupts::without_interrupts(|| self.live.lock().insert(stats.id, stats));
+    }
+
+    /// Move a task para a lista de completas recentes.
+    pub(crate) fn complete(&self, id: TaskId) {
+        interrupts::without_interrupts(|| {
+            if let Some(stats) = self.live.lock().remove(&id) {
+                let mut completed = self.completed.lock();
+                if completed.len() == RECENT_COMPLETED {
+                    completed.pop_front();
+                }
+                completed.push_back(stats.info());
+            }
+        });
+    }
+
+    /// Tasks vivas seguidas das completas recentemente.
+    pub(crate) fn snapshot(&self) -> Vec<TaskInfo> {
+        interrupts::without_interrupts(|| {
+            let live = self.live.lock();
+            let completed = self.completed.lock();
+            live.values()
+                .map(|stats| stats.info())
+                .chain(completed.iter().copied())
+                .collect()
+        })
+    }
+}
+
+/// Escreve uma tabela estilo `ps` com as tasks.
+pub fn write_task_table(out: &mut impl fmt::Write, tasks: &[TaskInfo]) -> fmt::Result {
+    writeln!(
+        out,
+        "{:>5} {:<10} {:<10} {:>8} {:>12} {:>12}  {:<16} SPAWNED AT",
+        "ID", "PRIORITY", "STATE", "POLLS", "CYCLES", "MAX", "NAME"
+    )?;
+    for task in tasks {
+        writeln!(
+            out,
+            "{:>5} {:<10} {:<10} {:>8} {:>12} {:>12}  {:<16} {}",
+            task.id.as_u64(),
+            task.priority,
+            task.state,
+            task.polls,
+            task.poll_cycles,
+            task.max_poll_cycles,
+            task.name.unwrap_or("-"),
+            task.location,
+        )?;
+    }
+    Ok(())
+}
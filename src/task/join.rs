@@ -0,0 +1,168 @@
+//! # JoinHandle: Resultado de Tasks
+//!
+//! ## Como funciona?
+//!
+//! `Executor::spawn` embrulha o future do usuário em uma task que, ao
+//! terminar, guarda o resultado em um estado compartilhado com o
+//! `JoinHandle`:
+//!
+//! ```text
+//! spawn(future) ──→ Task { async { state.complete(future.await) } }
+//!       │                                   │
+//!       v                                   v
+//! JoinHandle<T> ←──── Arc<Mutex<JoinState<T>>> ────→ result + waker
+//! ```
+//!
+//! O `JoinHandle` é ele mesmo um `Future`: se o resultado ainda não
+//! existe, registra o waker de quem o aguarda e retorna `Pending`. Ao
+//! completar, a task acorda esse waker.
+//!
+//! ## Detach
+//!
+//! Descartar o `JoinHandle` (ou chamar `detach()`) não cancela a task:
+//! ela continua rodando e seu resultado é descartado.
+//!
+//! ## Abort
+//!
+//! `abort()` cancela a task. O future do usuário é embrulhado em um
+//! `Abortable`, que acorda a task ao ser abortado; no próximo poll ele
+//! retorna sem avançar o future, a task completa e o executor a remove
+//! (junto com seu waker). O future é descartado antes de o `JoinHandle`
+//! receber `Err(JoinError::Cancelled)`:
+//!
+//! ```text
+//! abort() ──→ wake(task) ──→ poll: Abortable → Err(Aborted)
+//!                                   │
+//!                   drop(future) ←──┘──→ JoinHandle: Err(Cancelled)
+//! ```
+
+use super::{coop, TaskId};
+use alloc::sync::Arc;
+use core::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll, Waker},
+};
+use futures_util::future::Abortable;
+use spin::Mutex;
+
+pub use futures_util::future::AbortHandle;
+
+/// Motivo de uma task não ter produzido resultado.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinError {
+    /// A task foi cancelada com `abort()`.
+    Cancelled,
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JoinError::Cancelled => write!(f, "task was cancelled"),
+        }
+    }
+}
+
+/// Estado compartilhado entre a task e seu `JoinHandle`.
+struct JoinState<T> {
+    /// Resultado da task, disponível após completar.
+    result: Option<Result<T, JoinError>>,
+    /// Waker da task que aguarda o `JoinHandle`.
+    waker: Option<Waker>,
+    /// Se a task já completou (o resultado pode já ter sido consumido).
+    finished: bool,
+}
+
+/// Handle para aguardar o resultado de uma task.
+pub struct JoinHandle<T> {
+    id: TaskId,
+    state: Arc<Mutex<JoinState<T>>>,
+    abort: AbortHandle,
+}
+
+impl<T> JoinHandle<T> {
+    /// ID da task (o mesmo de `TaskInfo::id` no `snapshot()`).
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
+    /// Retorna se a task já completou (inclusive por cancelamento).
+    pub fn is_finished(&self) -> bool {
+        self.state.lock().finished
+    }
+
+    /// Cancela a task.
+    ///
+    /// O future é descartado no próximo ponto de escalonamento e quem
+    /// aguarda o handle recebe `Err(JoinError::Cancelled)`. Não tem efeito
+    /// se a task já completou.
+    pub fn abort(&self) {
+        self.abort.abort();
+    }
+
+    /// Retorna um handle clonável que cancela a task, útil para cancelá-la
+    /// depois de entregar o `JoinHandle` a outra task.
+    pub fn abort_handle(&self) -> AbortHandle {
+        self.abort.clone()
+    }
+
+    /// Desacopla a task do handle: ela continua rodando e o resultado é
+    /// descartado. Equivalente a descartar o handle.
+    pub fn detach(self) {}
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        ready!(coop::poll_proceed(cx));
+        let mut state = self.state.lock();
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                assert!(!state.finished, "JoinHandle polled after completion");
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Embrulha `future` em um future que entrega o resultado ao `JoinHandle`.
+///
+/// O future retornado é `Send` quando `F` e `F::Output` são.
+pub(crate) fn joinable<F>(id: TaskId, future: F) -> (impl Future<Output = ()>, JoinHandle<F::Output>)
+where
+    F: Future,
+{
+    let state = Arc::new(Mutex::new(JoinState {
+        result: None,
+        waker: None,
+        finished: false,
+    }));
+    let task_state = state.clone();
+    let (abort, registration) = AbortHandle::new_pair();
+
+    let task = async move {
+        // O `Abortable` (e o future) é descartado ao fim desta instrução,
+        // antes de o resultado ser entregue
+        let result = Abortable::new(future, registration)
+            .await
+            .map_err(|_| JoinError::Cancelled);
+        let waker = {
+            let mut state = task_state.lock();
+            state.finished = true;
+            // Sem handle (detach), não há quem consuma o resultado
+            if Arc::strong_count(&task_state) > 1 {
+                state.result = Some(result);
+            }
+            state.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    };
+
+    (task, JoinHandle { id, state, abort })
+}
@@ -10,13 +10,10 @@
 //! Tecla → IRQ1 → keyboard_handler → add_scancode()
 //!                                         │
 //!                                         v
-//!                              SCANCODE_QUEUE.push()
+//!                          SCANCODE_SENDER.try_send()
 //!                                         │
 //!                                         v
-//!                                  WAKER.wake()
-//!                                         │
-//!                                         v
-//!                              Executor acorda task
+//!                       canal mpsc acorda a task consumidora
 //!                                         │
 //!                                         v
 //!                              ScancodeStream.poll_next()
@@ -27,76 +24,103 @@
 //!
 //! ## Componentes
 //!
-//! - **SCANCODE_QUEUE**: Fila lock-free (ArrayQueue) de scancodes
-//! - **WAKER**: AtomicWaker que guarda o waker da task consumidora
-//! - **ScancodeStream**: Implementa `Stream` para consumo assíncrono
+//! - **SCANCODE_SENDER**: Lado que envia de um canal `mpsc` limitado,
+//!   usado pelo handler de interrupção
+//! - **ScancodeStream**: Dono do `Receiver` do canal; implementa `Stream`
+//!
+//! ## Cancelamento
 //!
-//! ## Por que lock-free?
+//! Só pode existir um `ScancodeStream` por vez. Ao ser descartado (por
+//! exemplo, quando a task de `print_keypresses` é abortada) ele remove o
+//! sender global e o canal é fechado: scancodes pendentes são descartados
+//! e um novo stream pode ser criado depois. Sem stream, as teclas são
+//! ignoradas.
 //!
-//! O handler de interrupção não pode bloquear! Se usássemos Mutex,
-//! poderíamos ter deadlock se a interrupção ocorresse enquanto
-//! a task segura o lock.
+//! ## Por que não bloqueia?
+//!
+//! O handler de interrupção não pode bloquear! `try_send` nunca espera
+//! (o canal está cheio → a tecla é descartada) e o canal protege seu
+//! estado desabilitando interrupções, então não há deadlock se a IRQ
+//! ocorrer enquanto a task recebe.
 //!
 //! ## Estudo baseado em
 //!
 //! [Async/Await](https://os.phil-opp.com/async-await/) - Blog OS
 
-use crate::{print, println};
-use conquer_once::spin::OnceCell;
+use super::channel::{mpsc, TrySendError};
+use crate::{
+    interrupts::deferred::{self, WorkItem},
+    print, println,
+};
 use core::{
     pin::Pin,
     task::{Context, Poll},
 };
-use crossbeam_queue::ArrayQueue;
-use futures_util::{
-    stream::{Stream, StreamExt},
-    task::AtomicWaker,
-};
+use futures_util::stream::{Stream, StreamExt};
+use spin::RwLock;
+use x86_64::instructions::interrupts;
 use pc_keyboard::{layouts, DecodedKey, HandleControl, Keyboard, ScancodeSet1};
 
-/// Fila de scancodes recebidos do teclado.
-/// Inicializada lazily na primeira chamada a `ScancodeStream::new()`.
-static SCANCODE_QUEUE: OnceCell<ArrayQueue<u8>> = OnceCell::uninit();
+/// Scancodes pendentes que o canal guarda antes de descartar teclas.
+const SCANCODE_QUEUE_SIZE: usize = 100;
 
-/// Waker atômico que armazena o waker da task consumidora.
-/// Quando um scancode chega, acordamos a task para processá-lo.
-static WAKER: AtomicWaker = AtomicWaker::new();
+/// Sender do canal de scancodes, presente enquanto existir um
+/// `ScancodeStream`.
+///
+/// Só é escrito com interrupções desabilitadas, então o handler nunca
+/// espera pelo lock.
+static SCANCODE_SENDER: RwLock<Option<mpsc::Sender<u8>>> = RwLock::new(None);
 
 /// Adiciona um scancode à fila. Chamado pelo handler de interrupção.
 ///
 /// Esta função é `pub(crate)` pois só deve ser chamada por `interrupts.rs`.
-/// É segura para uso em contexto de interrupção (lock-free).
+/// É segura para uso em contexto de interrupção (não bloqueia): os avisos
+/// são adiados via `deferred` em vez de imprimir com o `WRITER` dentro da
+/// IRQ.
 pub(crate) fn add_scancode(scancode: u8) {
-    if let Ok(queue) = SCANCODE_QUEUE.try_get() {
-        if let Err(_) = queue.push(scancode) {
-            println!("WARNING: scancode queue full; dropping keyboard input");
-        } else {
-            // Acorda a task que está aguardando scancodes
-            WAKER.wake();
+    // Sem stream ninguém consome as teclas: descarta
+    if let Some(sender) = SCANCODE_SENDER.read().as_ref() {
+        if let Err(TrySendError::Full(_)) = sender.try_send(scancode) {
+            let _ = deferred::schedule(WorkItem::new(warn_queue_full, 0));
         }
-    } else {
-        println!("WARNING: scancode queue uninitialized");
     }
 }
 
+fn warn_queue_full(_: usize) {
+    println!("WARNING: scancode queue full; dropping keyboard input");
+}
+
 /// Stream assíncrono de scancodes do teclado.
 ///
 /// Implementa a trait `Stream` do futures_util, permitindo
 /// consumo com `while let Some(scancode) = stream.next().await`.
 pub struct ScancodeStream {
-    _private: (), // Previne construção externa
+    receiver: mpsc::Receiver<u8>,
 }
 
 impl ScancodeStream {
-    /// Cria um novo ScancodeStream, inicializando a fila global.
+    /// Cria um novo ScancodeStream, abrindo o canal de scancodes.
     ///
     /// # Panics
-    /// Entra em panic se chamado mais de uma vez.
+    /// Entra em panic se já existir outro `ScancodeStream`.
     pub fn new() -> Self {
-        SCANCODE_QUEUE
-            .try_init_once(|| ArrayQueue::new(100))
-            .expect("ScancodeStream::new should only be called once");
-        ScancodeStream { _private: () }
+        let (sender, receiver) = mpsc::channel(SCANCODE_QUEUE_SIZE);
+        interrupts::without_interrupts(|| {
+            let mut current = SCANCODE_SENDER.write();
+            if current.is_some() {
+                panic!("only one ScancodeStream may exist at a time");
+            }
+            *current = Some(sender);
+        });
+        ScancodeStream { receiver }
+    }
+}
+
+/// Fecha o canal para que um próximo stream possa ser criado.
+impl Drop for ScancodeStream {
+    fn drop(&mut self) {
+        let sender = interrupts::without_interrupts(|| SCANCODE_SENDER.write().take());
+        drop(sender);
     }
 }
 
@@ -106,33 +130,18 @@ impl Stream for ScancodeStream {
 
     /// Tenta obter o próximo scancode.
     ///
-    /// Se a fila está vazia, registra o waker e retorna Pending.
-    /// Quando `add_scancode()` chamar `wake()`, seremos acordados.
+    /// Se o canal está vazio, o `Receiver` registra o waker e retorna
+    /// Pending; `add_scancode()` nos acorda ao enviar.
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<u8>> {
-        let queue = SCANCODE_QUEUE.try_get().expect("not initialized");
-
-        // Fast path: se há scancode disponível, retorna imediatamente
-        if let Some(scancode) = queue.pop() {
-            return Poll::Ready(Some(scancode));
-        }
-
-        // Slow path: registra waker e verifica novamente
-        // (evita race condition se scancode chegou entre as operações)
-        WAKER.register(&cx.waker());
-        match queue.pop() {
-            Some(scancode) => {
-                WAKER.take(); // Remove waker pois temos dado
-                Poll::Ready(Some(scancode))
-            }
-            None => Poll::Pending,
-        }
+        self.get_mut().receiver.poll_recv(cx)
     }
 }
 
 /// Função assíncrona que processa e imprime teclas pressionadas.
 ///
 /// Usa pc-keyboard para decodificar scancodes em caracteres/teclas.
-/// Roda indefinidamente, aguardando e processando cada tecla.
+/// Roda indefinidamente, aguardando e processando cada tecla; pode ser
+/// cancelada com `JoinHandle::abort()` e criada de novo depois.
 pub async fn print_keypresses() {
     let mut scancodes = ScancodeStream::new();
     let mut keyboard = Keyboard::new(
@@ -26,6 +26,17 @@
 //!
 //! - **TaskId**: Identificador único gerado atomicamente
 //! - **Task**: Wrapper de um Future com ID e Box pinado
+//! - **Builder**: Cria tasks com nome e prioridade
+//! - **TaskInfo**: Nome, origem, estado e tempo de poll (`snapshot()`)
+//! - **JoinHandle**: Future que resolve para o resultado de uma task
+//!   (`abort()` cancela a task)
+//! - **CancellationToken**: Cancelamento cooperativo entre tasks
+//! - **Priority**: Prioridade de escalonamento (high, normal, background)
+//! - **sync**: Mutex, RwLock, Semaphore, Notify e Barrier assíncronos
+//! - **channel**: Canais mpsc, oneshot e broadcast entre tasks
+//! - **Spawner**: Handle clonável para criar tasks de dentro de tasks
+//!   (ou de handlers de interrupção); `spawn()` usa o executor atual
+//! - **MulticoreExecutor**: Workers em todas as CPUs, com work stealing
 //!
 //! ## Por que Pin?
 //!
@@ -37,24 +48,45 @@
 //!
 //! [Async/Await](https://os.phil-opp.com/async-await/) - Blog OS
 
-use alloc::boxed::Box;
+use alloc::{boxed::Box, sync::Arc};
 use core::{
+    fmt,
     future::Future,
+    panic::Location,
     pin::Pin,
     sync::atomic::{AtomicU64, Ordering},
     task::{Context, Poll},
 };
 
+pub mod builder;
+pub mod cancel;
+pub mod channel;
+pub mod coop;
 pub mod executor;
+pub mod info;
+pub mod join;
 pub mod keyboard;
+pub mod multicore;
 pub mod simple_executor;
+pub mod slow_poll;
+pub mod sync;
+
+pub use builder::Builder;
+pub use cancel::CancellationToken;
+pub use coop::yield_now;
+pub use executor::{snapshot, spawn, spawn_with_priority, Spawner};
+pub use info::{TaskInfo, TaskState};
+pub use join::{AbortHandle, JoinError, JoinHandle};
+pub use multicore::MulticoreExecutor;
+
+use info::TaskStats;
 
 /// Identificador único de uma task.
 ///
 /// Gerado atomicamente usando `AtomicU64` para garantir unicidade
 /// mesmo em contextos concorrentes (interrupções).
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-struct TaskId(u64);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TaskId(u64);
 
 impl TaskId {
     /// Gera um novo TaskId único incrementando um contador global.
@@ -62,6 +94,51 @@ impl TaskId {
         static NEXT_ID: AtomicU64 = AtomicU64::new(0);
         TaskId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
     }
+
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for TaskId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// Prioridade de escalonamento de uma task.
+///
+/// O executor sempre roda primeiro as tasks prontas de maior prioridade.
+/// Tasks `Background` só rodam quando não há nenhuma outra pronta.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Priority {
+    /// Sensível a latência (ex: entrada do teclado, trabalho adiado).
+    High,
+    /// Prioridade padrão.
+    #[default]
+    Normal,
+    /// Trabalho em lote, executado quando o sistema está ocioso.
+    Background,
+}
+
+impl Priority {
+    /// Número de níveis de prioridade.
+    pub const COUNT: usize = 3;
+
+    /// Índice da fila desta prioridade (0 = maior).
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            Priority::High => "high",
+            Priority::Normal => "normal",
+            Priority::Background => "background",
+        })
+    }
 }
 
 /// Uma task assíncrona que pode ser executada pelo Executor.
@@ -72,6 +149,8 @@ impl TaskId {
 /// - Prevenir movimentação (`Pin`) para self-references seguras
 pub struct Task {
     id: TaskId,
+    /// Nome, origem, prioridade e estatísticas (ver `info`)
+    stats: Arc<TaskStats>,
     future: Pin<Box<dyn Future<Output = ()>>>,
 }
 
@@ -80,10 +159,18 @@ impl Task {
     ///
     /// O Future deve ter lifetime `'static` pois a task pode viver
     /// indefinidamente no executor.
+    #[track_caller]
     pub fn new(future: impl Future<Output = ()> + 'static) -> Task {
+        let stats = TaskStats::new(TaskId::new(), None, Location::caller(), Priority::Normal);
+        Task::from_pinned(Box::pin(future), Arc::new(stats))
+    }
+
+    /// Cria uma task a partir de um future já alocado no heap.
+    fn from_pinned(future: Pin<Box<dyn Future<Output = ()>>>, stats: Arc<TaskStats>) -> Task {
         Task {
-            id: TaskId::new(),
-            future: Box::pin(future),
+            id: stats.id,
+            stats,
+            future,
         }
     }
 
@@ -0,0 +1,451 @@
+//! # Executor Multi-core com Work Stealing
+//!
+//! ## Por que outro executor?
+//!
+//! `Executor` roda numa única CPU: as outras, ligadas por `smp`, ficam
+//! paradas em HLT. `MulticoreExecutor` roda um **worker** em cada CPU
+//! online, todos compartilhando as mesmas tasks.
+//!
+//! ## Filas
+//!
+//! ```text
+//!                 spawn / wake fora de um worker
+//!                              │
+//!                              v
+//!                  ┌───────────────────────┐
+//!                  │ injector [High..Bg]   │  fila global
+//!                  └───────────────────────┘
+//!                     │         │         │
+//!                     v         v         v
+//!               ┌─────────┐┌─────────┐┌─────────┐
+//!  wake no  ──→ │ local 0 ││ local 1 ││ local 2 │  uma por CPU,
+//!  worker       └─────────┘└─────────┘└─────────┘  por prioridade
+//!                   CPU 0  ←── roubo ──  CPU 2
+//! ```
+//!
+//! Cada worker procura trabalho, por ordem de prioridade, na sua fila
+//! local, depois no injector e por fim **rouba** metade da fila de outro
+//! worker. Tasks acordadas por código rodando num worker vão para a fila
+//! local dele, mantendo a task perto dos dados que acabou de tocar.
+//!
+//! ## CPUs ociosas
+//!
+//! Sem trabalho, o worker marca sua CPU no bitmap `idle` e faz HLT, como o
+//! `sleep_if_idle` do `Executor`. Quem enfileira uma task tira uma CPU do
+//! bitmap e a acorda com uma IPI (`smp::wake`):
+//!
+//! ```text
+//! worker: idle |= bit ─→ cli ─→ há trabalho? ─ não → sti; hlt
+//!                                    │ sim                │
+//!                                    v                    v
+//!                              sti, continua      IPI de wakeup → continua
+//! ```
+//!
+//! A verificação com interrupções desabilitadas fecha a janela entre
+//! "não há trabalho" e o HLT: uma IPI enviada nesse meio fica pendente e
+//! acorda o HLT na hora.
+//!
+//! ## Uso
+//!
+//! ```ignore
+//! let executor = MulticoreExecutor::new();
+//! for i in 0..100 {
+//!     executor.spawn(async move { work(i).await });
+//! }
+//! executor.run_until_complete(); // workers em todas as CPUs online
+//! ```
+//!
+//! As tasks precisam ser `Send`, pois podem rodar em qualquer CPU.
+//! `task::spawn()` continua usando o `Executor` da CPU; para criar tasks
+//! aqui de dentro de uma task, clone o `MulticoreExecutor` (é um handle).
+//!
+//! O executor é **opcional**: `kernel_main` roda as tasks do sistema
+//! (teclado, trabalho adiado) no `Executor` da BSP, e as APs ficam em HLT
+//! até alguém chamar `run` ou `run_until_complete`.
+//!
+//! ## Task ocupada em outra CPU
+//!
+//! Um wake durante o poll pode colocar a task na fila de outra CPU
+//! enquanto o poll ainda roda. Em vez de devolvê-la à fila (e deixar as
+//! CPUs trocando a task entre si), a CPU que a encontra ocupada marca
+//! `repoll` e segue em frente; quem está fazendo o poll a reagenda ao
+//! terminar.
+
+use super::{
+    coop,
+    info::{Registry, TaskInfo, TaskStats},
+    slow_poll, Builder, JoinHandle, Priority,
+};
+use crate::{percpu, smp, smp::MAX_CPUS, thread};
+use alloc::{
+    boxed::Box,
+    sync::{Arc, Weak},
+    task::Wake,
+    vec::Vec,
+};
+use core::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{fence, AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    task::{Context, Waker},
+};
+use crossbeam_queue::SegQueue;
+use x86_64::instructions::interrupts;
+
+/// Future de uma task do executor multi-core.
+type SendFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Uma fila por prioridade.
+type Queues = [SegQueue<Arc<MulticoreTask>>; Priority::COUNT];
+
+/// Task que pode rodar em qualquer CPU. Também é o seu próprio waker.
+struct MulticoreTask {
+    stats: Arc<TaskStats>,
+    /// `None` depois que a task completa. O lock impede dois polls ao
+    /// mesmo tempo quando um wake durante o poll a manda para outra CPU.
+    future: spin::Mutex<Option<SendFuture>>,
+    /// Se a task já está numa fila (ou completou), como no `TaskWaker`.
+    scheduled: AtomicBool,
+    /// Foi tirada da fila enquanto outra CPU fazia o poll; quem segura o
+    /// `future` a reagenda ao terminar.
+    repoll: AtomicBool,
+    executor: Weak<Shared>,
+}
+
+impl Wake for MulticoreTask {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        if self.scheduled.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        self.stats.set_queued();
+        if let Some(shared) = self.executor.upgrade() {
+            shared.schedule(self.clone());
+        }
+    }
+}
+
+/// Estado compartilhado entre o handle e os workers.
+struct Shared {
+    injector: Queues,
+    /// Filas locais, indexadas pelo índice da CPU.
+    local: Vec<Queues>,
+    /// CPUs com um worker rodando (bit = índice da CPU).
+    workers: AtomicU64,
+    /// Workers parados em HLT esperando trabalho.
+    idle: AtomicU64,
+    /// Tasks criadas e ainda não completas.
+    live: AtomicUsize,
+    /// Pedido para os workers das APs voltarem.
+    shutdown: AtomicBool,
+    /// Workers entregues às APs e ainda não terminados.
+    ap_workers: AtomicUsize,
+    polls: Vec<AtomicU64>,
+    steals: Vec<AtomicU64>,
+    registry: Registry,
+}
+
+/// Estatísticas do worker de uma CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkerStats {
+    pub cpu: usize,
+    /// Polls feitos por esta CPU.
+    pub polls: u64,
+    /// Tasks roubadas de outras CPUs.
+    pub steals: u64,
+}
+
+fn bit(cpu: usize) -> u64 {
+    1 << cpu
+}
+
+impl Shared {
+    fn is_worker(&self, cpu: usize) -> bool {
+        self.workers.load(Ordering::Acquire) & bit(cpu) != 0
+    }
+
+    /// Enfileira uma task pronta e acorda uma CPU ociosa.
+    fn schedule(&self, task: Arc<MulticoreTask>) {
+        let cpu = percpu::cpu_index();
+        let priority = task.stats.priority.index();
+        if self.is_worker(cpu) {
+            self.local[cpu][priority].push(task);
+        } else {
+            self.injector[priority].push(task);
+        }
+        self.wake_one(cpu);
+    }
+
+    /// Acorda um worker ocioso (que não seja `except`), se houver.
+    fn wake_one(&self, except: usize) {
+        let mut idle = self.idle.load(Ordering::Acquire) & !bit(except);
+        while idle != 0 {
+            let cpu = idle.trailing_zeros() as usize;
+            // Só quem limpa o bit envia a IPI
+            if self.idle.fetch_and(!bit(cpu), Ordering::AcqRel) & bit(cpu) != 0 {
+                smp::wake(cpu);
+                return;
+            }
+            idle &= !bit(cpu);
+        }
+    }
+
+    /// Acorda todos os workers (fim das tasks ou `shutdown`).
+    fn wake_all(&self) {
+        let workers = self.workers.load(Ordering::Acquire);
+        for cpu in (0..MAX_CPUS).filter(|&cpu| workers & bit(cpu) != 0) {
+            smp::wake(cpu);
+        }
+    }
+
+    /// Próxima task para a CPU `cpu`: fila local, injector, roubo.
+    fn find_task(&self, cpu: usize) -> Option<Arc<MulticoreTask>> {
+        (0..Priority::COUNT).find_map(|priority| {
+            self.local[cpu][priority]
+                .pop()
+                .or_else(|| self.injector[priority].pop())
+                .or_else(|| self.steal(cpu, priority))
+        })
+    }
+
+    /// Rouba metade da fila `priority` de outro worker, começando pela
+    /// próxima CPU para espalhar os roubos.
+    fn steal(&self, cpu: usize, priority: usize) -> Option<Arc<MulticoreTask>> {
+        let workers = self.workers.load(Ordering::Acquire);
+        let victims = (1..MAX_CPUS)
+            .map(|offset| (cpu + offset) % MAX_CPUS)
+            .filter(|&victim| workers & bit(victim) != 0);
+        for victim in victims {
+            let queue = &self.local[victim][priority];
+            let Some(task) = queue.pop() else {
+                continue;
+            };
+            for _ in 0..queue.len() / 2 {
+                match queue.pop() {
+                    Some(extra) => self.local[cpu][priority].push(extra),
+                    None => break,
+                }
+            }
+            self.steals[cpu].fetch_add(1, Ordering::Relaxed);
+            return Some(task);
+        }
+        None
+    }
+
+    fn has_work(&self) -> bool {
+        let workers = self.workers.load(Ordering::Acquire);
+        let has_tasks = |queues: &Queues| queues.iter().any(|queue| !queue.is_empty());
+        has_tasks(&self.injector)
+            || (0..MAX_CPUS).any(|cpu| workers & bit(cpu) != 0 && has_tasks(&self.local[cpu]))
+    }
+
+    fn poll(&self, cpu: usize, task: Arc<MulticoreTask>) {
+        let mut future = match task.future.try_lock() {
+            Some(future) => future,
+            None => {
+                // Outra CPU ainda está no poll anterior: deixa o pedido com ela
+                task.repoll.store(true, Ordering::Release);
+                fence(Ordering::SeqCst);
+                // Se o poll terminou antes do aviso, ninguém o veria
+                match task.future.try_lock() {
+                    Some(future) => future,
+                    None => return,
+                }
+            }
+        };
+        task.repoll.store(false, Ordering::Relaxed);
+        let Some(pinned) = future.as_mut() else {
+            return; // já completou
+        };
+
+        task.scheduled.store(false, Ordering::Release);
+        let waker = Waker::from(task.clone());
+        let mut context = Context::from_waker(&waker);
+
+        task.stats.begin_poll();
+        coop::reset();
+        let timer = slow_poll::start(&task.stats);
+        let poll = pinned.as_mut().poll(&mut context);
+        let cycles = timer.finish(&task.stats);
+        coop::unlimited();
+        task.stats.end_poll(cycles, poll.is_ready());
+        self.polls[cpu].fetch_add(1, Ordering::Relaxed);
+
+        if poll.is_ready() {
+            task.scheduled.store(true, Ordering::Release);
+            *future = None;
+            self.registry.complete(task.stats.id);
+            if self.live.fetch_sub(1, Ordering::AcqRel) == 1 {
+                self.wake_all();
+            }
+        }
+
+        drop(future);
+        fence(Ordering::SeqCst);
+        if task.repoll.swap(false, Ordering::AcqRel) {
+            self.schedule(task);
+        }
+    }
+
+    /// HLT até uma IPI ou interrupção, se não houver trabalho.
+    fn sleep(&self, cpu: usize, done: impl Fn() -> bool) {
+        self.idle.fetch_or(bit(cpu), Ordering::AcqRel);
+        interrupts::disable();
+        if self.has_work() || done() {
+            interrupts::enable();
+        } else {
+            // Na BSP, cede para outras threads prontas
+            thread::wait_for_interrupt();
+        }
+        self.idle.fetch_and(!bit(cpu), Ordering::AcqRel);
+    }
+
+    /// Laço do worker da CPU atual, até `done()`.
+    fn work(&self, done: impl Fn() -> bool) {
+        let cpu = percpu::cpu_index();
+        self.workers.fetch_or(bit(cpu), Ordering::AcqRel);
+        while !done() {
+            match self.find_task(cpu) {
+                Some(task) => self.poll(cpu, task),
+                None => self.sleep(cpu, &done),
+            }
+        }
+        self.workers.fetch_and(!bit(cpu), Ordering::AcqRel);
+        // Tasks deixadas na fila local ficam para os outros workers
+        for priority in 0..Priority::COUNT {
+            while let Some(task) = self.local[cpu][priority].pop() {
+                self.injector[priority].push(task);
+            }
+        }
+    }
+}
+
+/// Executor que distribui tasks entre todas as CPUs online.
+///
+/// É um handle: clones compartilham as mesmas tasks.
+#[derive(Clone)]
+pub struct MulticoreExecutor {
+    shared: Arc<Shared>,
+}
+
+impl MulticoreExecutor {
+    pub fn new() -> Self {
+        MulticoreExecutor {
+            shared: Arc::new(Shared {
+                injector: Default::default(),
+                local: (0..MAX_CPUS).map(|_| Default::default()).collect(),
+                workers: AtomicU64::new(0),
+                idle: AtomicU64::new(0),
+                live: AtomicUsize::new(0),
+                shutdown: AtomicBool::new(false),
+                ap_workers: AtomicUsize::new(0),
+                polls: (0..MAX_CPUS).map(|_| AtomicU64::new(0)).collect(),
+                steals: (0..MAX_CPUS).map(|_| AtomicU64::new(0)).collect(),
+                registry: Registry::new(),
+            }),
+        }
+    }
+
+    /// Agenda um future como nova task e retorna o handle do seu resultado.
+    ///
+    /// Para dar nome ou prioridade à task, use `task::Builder`.
+    #[track_caller]
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        Builder::new().spawn_multicore(self, future)
+    }
+
+    pub(super) fn spawn_pinned(&self, stats: Arc<TaskStats>, future: SendFuture) {
+        let shared = &self.shared;
+        shared.registry.insert(stats.clone());
+        shared.live.fetch_add(1, Ordering::AcqRel);
+        let task = Arc::new(MulticoreTask {
+            stats,
+            future: spin::Mutex::new(Some(future)),
+            scheduled: AtomicBool::new(true),
+            repoll: AtomicBool::new(false),
+            executor: Arc::downgrade(shared),
+        });
+        shared.schedule(task);
+    }
+
+    /// Número de tasks ainda não completas.
+    pub fn task_count(&self) -> usize {
+        self.shared.live.load(Ordering::Acquire)
+    }
+
+    /// Estado de todas as tasks: as vivas e as que completaram recentemente.
+    pub fn snapshot(&self) -> Vec<TaskInfo> {
+        self.shared.registry.snapshot()
+    }
+
+    /// Polls e roubos de cada CPU descoberta.
+    pub fn worker_stats(&self) -> Vec<WorkerStats> {
+        smp::cpus()
+            .iter()
+            .map(|cpu| WorkerStats {
+                cpu: cpu.index,
+                polls: self.shared.polls[cpu.index].load(Ordering::Relaxed),
+                steals: self.shared.steals[cpu.index].load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+
+    /// Inicia um worker em cada AP online livre.
+    fn start_ap_workers(&self) {
+        let current = percpu::cpu_index();
+        for cpu in smp::cpus()
+            .iter()
+            .filter(|cpu| cpu.online && cpu.index != current)
+        {
+            let shared = self.shared.clone();
+            shared.ap_workers.fetch_add(1, Ordering::AcqRel);
+            let result = smp::run_on(cpu.index, move || {
+                shared.work(|| shared.shutdown.load(Ordering::Acquire));
+                shared.ap_workers.fetch_sub(1, Ordering::AcqRel);
+            });
+            if result.is_err() {
+                // AP ocupada: a closure (e sua referência) já foi descartada
+                self.shared.ap_workers.fetch_sub(1, Ordering::AcqRel);
+            }
+        }
+    }
+
+    /// Executa as tasks em todas as CPUs online para sempre.
+    pub fn run(&self) -> ! {
+        self.start_ap_workers();
+        self.shared.work(|| false);
+        unreachable!("worker returned")
+    }
+
+    /// Executa as tasks em todas as CPUs online até todas completarem,
+    /// inclusive as criadas no caminho, e devolve as APs.
+    ///
+    /// Bloqueia para sempre se alguma task nunca completar.
+    pub fn run_until_complete(&self) {
+        let shared = &self.shared;
+        self.start_ap_workers();
+        shared.work(|| shared.live.load(Ordering::Acquire) == 0);
+
+        shared.shutdown.store(true, Ordering::Release);
+        shared.wake_all();
+        // Espera os workers das APs saírem (inclusive os que ainda nem
+        // começaram) antes de reaproveitar `shutdown`
+        while shared.ap_workers.load(Ordering::Acquire) != 0 {
+            core::hint::spin_loop();
+        }
+        shared.shutdown.store(false, Ordering::Release);
+    }
+}
+
+impl Default for MulticoreExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
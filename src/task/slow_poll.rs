@@ -0,0 +1,233 @@
+//! # Detecção de Polls Lentos
+//!
+//! ## O problema
+//!
+//! O executor é cooperativo: enquanto uma task está dentro de `poll`,
+//! nenhuma outra roda. Uma task que faz uma computação longa sem ceder
+//! congela o teclado e as bottom halves sem deixar nenhum rastro.
+//!
+//! ## Medição
+//!
+//! O executor mede cada poll com o TSC. Polls acima do limite
+//! (`set_threshold_us`) geram um aviso com o nome e o ID da task, e os
+//! `LONGEST_POLLS` polls mais longos desde o boot ficam registrados:
+//!
+//! ```text
+//! start(stats) ─→ task.poll() ─→ finish() ─┬─→ cycles > limite? → aviso
+//!      │                                   └─→ entre os mais longos? → registra
+//!      └─ CURRENT = stats, POLL_START = rdtsc
+//! ```
+//!
+//! ## Polls que nunca retornam
+//!
+//! Se a task não retornar, `finish()` nunca roda. Por isso o handler do
+//! timer chama `check_hung_poll()`, que compara `POLL_START` com o TSC e,
+//! passados `HUNG_POLL_SECS`, relata (uma vez por poll) a task e o RIP
+//! onde ela foi interrompida na serial.
+//!
+//! O estado do poll atual é por CPU (`cpu_local!`), então executores em
+//! CPUs diferentes são medidos separadamente; só a CPU que recebe o timer
+//! (a BSP) procura polls travados.
+//!
+//! A medição usa tempo de relógio: se a thread do executor for preemptada
+//! no meio de um poll, o tempo das outras threads entra na conta. O
+//! relato de poll travado só é feito se o timer interromper a própria
+//! thread que está no poll.
+//!
+//! Sem o TSC calibrado (`time::calibrate_tsc`) não há como converter o
+//! limite em ciclos: os polls ainda são medidos e registrados, mas nenhum
+//! aviso é emitido.
+
+use super::{info::TaskStats, TaskId};
+use crate::{cpu_local, println, serial::EmergencyWriter, thread, time};
+use alloc::vec::Vec;
+use core::{
+    fmt::Write,
+    panic::Location,
+    ptr,
+    sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, Ordering},
+};
+use x86_64::{instructions::interrupts, structures::idt::InterruptStackFrame};
+
+/// Limite padrão para avisos de poll lento.
+const DEFAULT_THRESHOLD_US: u64 = 10_000;
+/// Tempo em um único poll para considerá-lo travado.
+const HUNG_POLL_SECS: u64 = 2;
+/// Quantidade de polls mais longos registrados.
+pub const LONGEST_POLLS: usize = 8;
+
+/// Limite de avisos em microssegundos (0 = desabilitado).
+static THRESHOLD_US: AtomicU64 = AtomicU64::new(DEFAULT_THRESHOLD_US);
+/// Polls que excederam o limite desde o boot.
+static SLOW_POLLS: AtomicU64 = AtomicU64::new(0);
+
+cpu_local! {
+    /// Task sendo executada agora em cada CPU (nulo fora de um poll).
+    static CURRENT: AtomicPtr<TaskStats> = AtomicPtr::new(ptr::null_mut());
+    /// TSC no início do poll atual.
+    static POLL_START: AtomicU64 = AtomicU64::new(0);
+    /// Thread que executa o poll atual (`u64::MAX` = sem threads).
+    static POLL_THREAD: AtomicU64 = AtomicU64::new(u64::MAX);
+    /// Se o poll atual já foi relatado como travado.
+    static HANG_REPORTED: AtomicBool = AtomicBool::new(false);
+}
+
+/// Polls mais longos, em ordem decrescente de duração.
+static LONGEST: spin::Mutex<[Option<SlowPoll>; LONGEST_POLLS]> =
+    spin::Mutex::new([None; LONGEST_POLLS]);
+/// Duração do poll mais curto em `LONGEST` quando cheio (0 = há espaço).
+static LONGEST_FLOOR: AtomicU64 = AtomicU64::new(0);
+
+/// Um poll registrado entre os mais longos.
+#[derive(Debug, Clone, Copy)]
+pub struct SlowPoll {
+    pub id: TaskId,
+    pub name: Option<&'static str>,
+    /// Onde a task foi criada (`spawn`).
+    pub location: &'static Location<'static>,
+    /// Duração do poll em ciclos do TSC.
+    pub cycles: u64,
+}
+
+impl SlowPoll {
+    /// Duração do poll em microssegundos, se o TSC foi calibrado.
+    pub fn duration_us(&self) -> Option<u64> {
+        time::cycles_to_us(self.cycles)
+    }
+}
+
+/// Define o limite de avisos de poll lento (0 desabilita os avisos).
+pub fn set_threshold_us(us: u64) {
+    THRESHOLD_US.store(us, Ordering::Relaxed);
+}
+
+/// Limite atual de avisos de poll lento, em microssegundos.
+pub fn threshold_us() -> u64 {
+    THRESHOLD_US.load(Ordering::Relaxed)
+}
+
+/// Número de polls que excederam o limite desde o boot.
+pub fn slow_poll_count() -> u64 {
+    SLOW_POLLS.load(Ordering::Relaxed)
+}
+
+/// Os polls mais longos desde o boot, do mais longo ao mais curto.
+pub fn longest_polls() -> Vec<SlowPoll> {
+    interrupts::without_interrupts(|| LONGEST.lock().iter().flatten().copied().collect())
+}
+
+/// Medição de um poll em andamento, criada por `start`.
+pub(super) struct PollTimer {
+    start: u64,
+    /// Poll interrompido por este (executor aninhado), restaurado no fim.
+    previous: *mut TaskStats,
+    previous_start: u64,
+    previous_thread: u64,
+}
+
+/// Marca o início do poll de `stats`.
+///
+/// `stats` precisa continuar vivo até `PollTimer::finish`.
+pub(super) fn start(stats: &TaskStats) -> PollTimer {
+    let previous_start = POLL_START.get().load(Ordering::Relaxed);
+    let previous_thread = POLL_THREAD.get().swap(current_thread(), Ordering::Relaxed);
+    let start = time::rdtsc();
+    POLL_START.get().store(start, Ordering::Relaxed);
+    HANG_REPORTED.get().store(false, Ordering::Relaxed);
+    let previous = CURRENT.get().swap(stats as *const TaskStats as *mut TaskStats, Ordering::AcqRel);
+    PollTimer {
+        start,
+        previous,
+        previous_start,
+        previous_thread,
+    }
+}
+
+fn current_thread() -> u64 {
+    thread::current_id().map_or(u64::MAX, |id| id.as_u64())
+}
+
+impl PollTimer {
+    /// Marca o fim do poll, registra sua duração e retorna os ciclos gastos.
+    pub(super) fn finish(self, stats: &TaskStats) -> u64 {
+        let cycles = time::rdtsc() - self.start;
+        CURRENT.get().store(self.previous, Ordering::Release);
+        POLL_START.get().store(self.previous_start, Ordering::Relaxed);
+        POLL_THREAD.get().store(self.previous_thread, Ordering::Relaxed);
+
+        if cycles > LONGEST_FLOOR.load(Ordering::Relaxed) {
+            record_longest(stats, cycles);
+        }
+
+        let threshold = threshold_us();
+        if threshold != 0 && time::us_to_cycles(threshold).is_some_and(|limit| cycles > limit) {
+            SLOW_POLLS.fetch_add(1, Ordering::Relaxed);
+            println!(
+                "WARNING: slow poll: task {} ({}) took {} us (limit {} us), spawned at {}",
+                stats.id,
+                stats.name.unwrap_or("unnamed"),
+                time::cycles_to_us(cycles).unwrap_or(0),
+                threshold,
+                stats.location,
+            );
+        }
+        cycles
+    }
+}
+
+/// Insere o poll na tabela de mais longos, mantendo a ordem decrescente.
+fn record_longest(stats: &TaskStats, cycles: u64) {
+    interrupts::without_interrupts(|| {
+        let mut longest = LONGEST.lock();
+        let position = longest
+            .iter()
+            .position(|entry| entry.is_none_or(|entry| cycles > entry.cycles));
+        if let Some(position) = position {
+            longest.copy_within(position..LONGEST_POLLS - 1, position + 1);
+            longest[position] = Some(SlowPoll {
+                id: stats.id,
+                name: stats.name,
+                location: stats.location,
+                cycles,
+            });
+        }
+        let floor = longest[LONGEST_POLLS - 1].map_or(0, |entry| entry.cycles);
+        LONGEST_FLOOR.store(floor, Ordering::Relaxed);
+    });
+}
+
+/// Verifica se o poll atual está travado. Chamado pelo handler do timer.
+///
+/// Escreve direto na porta serial: o código interrompido pode segurar o
+/// lock de `SERIAL1` ou do `WRITER`.
+pub(crate) fn check_hung_poll(stack_frame: &InterruptStackFrame) {
+    let current = CURRENT.get().load(Ordering::Acquire);
+    if current.is_null() || HANG_REPORTED.get().load(Ordering::Relaxed) {
+        return;
+    }
+    // Uma thread preemptada no meio de um poll não está travada
+    if POLL_THREAD.get().load(Ordering::Relaxed) != current_thread() {
+        return;
+    }
+    let Some(limit) = time::us_to_cycles(HUNG_POLL_SECS * 1_000_000) else {
+        return;
+    };
+    let elapsed = time::rdtsc() - POLL_START.get().load(Ordering::Relaxed);
+    if elapsed < limit || HANG_REPORTED.get().swap(true, Ordering::Relaxed) {
+        return;
+    }
+
+    // A task interrompida está dentro de `poll`, então seu `TaskStats`
+    // (mantido vivo pelo executor até `finish`) ainda é válido.
+    let stats = unsafe { &*current };
+    let mut out = EmergencyWriter;
+    let _ = writeln!(
+        out,
+        "\nWARNING: task {} ({}) stuck in poll for {} ms",
+        stats.id,
+        stats.name.unwrap_or("unnamed"),
+        time::cycles_to_us(elapsed).unwrap_or(0) / 1000,
+    );
+    let _ = writeln!(out, "  RIP: {:?}", stack_frame.instruction_pointer);
+    let _ = writeln!(out, "  spawned at: {}", stats.location);
+}
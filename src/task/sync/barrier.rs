@@ -0,0 +1,107 @@
+//! Barreira: espera até N tasks chegarem ao mesmo ponto.
+//!
+//! `wait` é cancel-safe: descartar o future antes da liberação desfaz a
+//! chegada, então a barreira continua esperando `parties` tasks.
+
+use super::with_state;
+use alloc::vec::Vec;
+use core::{
+    future::poll_fn,
+    task::{Poll, Waker},
+};
+
+struct State {
+    /// Tasks que já chegaram na geração atual.
+    arrived: usize,
+    /// Incrementada cada vez que a barreira é liberada.
+    generation: u64,
+    waiters: Vec<Waker>,
+}
+
+/// Barreira reutilizável para um número fixo de tasks.
+pub struct Barrier {
+    parties: usize,
+    state: spin::Mutex<State>,
+}
+
+/// Resultado de `Barrier::wait`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BarrierWaitResult {
+    leader: bool,
+}
+
+impl BarrierWaitResult {
+    /// `true` para exatamente uma task por geração (a última a chegar).
+    pub fn is_leader(&self) -> bool {
+        self.leader
+    }
+}
+
+/// Desfaz a chegada se o future de `wait` for descartado antes da liberação.
+struct Arrival<'a> {
+    barrier: &'a Barrier,
+    generation: u64,
+}
+
+impl Drop for Arrival<'_> {
+    fn drop(&mut self) {
+        with_state(&self.barrier.state, |state| {
+            if state.generation == self.generation {
+                state.arrived -= 1;
+            }
+        });
+    }
+}
+
+impl Barrier {
+    /// Cria uma barreira para `parties` tasks (0 é tratado como 1).
+    pub const fn new(parties: usize) -> Self {
+        Barrier {
+            parties: if parties == 0 { 1 } else { parties },
+            state: spin::Mutex::new(State {
+                arrived: 0,
+                generation: 0,
+                waiters: Vec::new(),
+            }),
+        }
+    }
+
+    /// Aguarda até `parties` tasks chamarem `wait`.
+    pub async fn wait(&self) -> BarrierWaitResult {
+        let arrival = with_state(&self.state, |state| {
+            state.arrived += 1;
+            if state.arrived == self.parties {
+                state.arrived = 0;
+                state.generation += 1;
+                Err(core::mem::take(&mut state.waiters))
+            } else {
+                Ok(state.generation)
+            }
+        });
+
+        let arrival = match arrival {
+            Ok(generation) => Arrival {
+                barrier: self,
+                generation,
+            },
+            Err(waiters) => {
+                waiters.into_iter().for_each(Waker::wake);
+                return BarrierWaitResult { leader: true };
+            }
+        };
+
+        poll_fn(|cx| {
+            with_state(&self.state, |state| {
+                if state.generation != arrival.generation {
+                    return Poll::Ready(());
+                }
+                if !state.waiters.iter().any(|waker| waker.will_wake(cx.waker())) {
+                    state.waiters.push(cx.waker().clone());
+                }
+                Poll::Pending
+            })
+        })
+        .await;
+        BarrierWaitResult { leader: false }
+    }
+}
@@ -0,0 +1,52 @@
+//! # Primitivas de Sincronização Assíncronas
+//!
+//! ## Por que não `spin::Mutex`?
+//!
+//! Um spinlock espera em loop. No executor cooperativo só uma task roda
+//! por vez: se a task A segura o lock através de um `.await` e a task B
+//! tenta adquiri-lo, B gira para sempre e A nunca volta a rodar.
+//!
+//! ```text
+//! spin::Mutex:   B.lock() → loop { ocupado } ──→ executor travado (A nunca roda)
+//! sync::Mutex:   B.lock().await → Pending (waker na fila) → A roda, solta → wake(B)
+//! ```
+//!
+//! As primitivas deste módulo **estacionam** a task: registram o waker em
+//! uma fila de espera e retornam `Pending`. Quem libera o recurso acorda
+//! a próxima task da fila.
+//!
+//! ## Primitivas
+//!
+//! - **Semaphore**: N permissões, fila FIFO de espera (base das outras)
+//! - **Mutex**: Exclusão mútua, guard pode atravessar `.await`
+//! - **RwLock**: Vários leitores ou um escritor, sem starvation de escritores
+//! - **Notify**: Acorda uma ou todas as tasks aguardando um evento
+//! - **Barrier**: Espera até N tasks chegarem ao mesmo ponto
+//!
+//! ## Interrupções
+//!
+//! O estado interno é protegido por um spinlock segurado só por poucas
+//! instruções e com interrupções desabilitadas. Operações que não esperam
+//! (`Notify::notify_one`, `Semaphore::add_permits`, `try_*`) podem ser
+//! usadas em handlers de interrupção.
+
+use x86_64::instructions::interrupts;
+
+mod barrier;
+mod mutex;
+mod notify;
+mod rwlock;
+mod semaphore;
+
+pub use barrier::{Barrier, BarrierWaitResult};
+pub use mutex::{Mutex, MutexGuard};
+pub use notify::{Notified, Notify};
+pub use rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+pub use semaphore::{Acquire, Semaphore, SemaphorePermit};
+
+/// Acessa o estado interno de uma primitiva com interrupções desabilitadas.
+///
+/// Também usado pelos canais de `task::channel`.
+pub(super) fn with_state<T, R>(state: &spin::Mutex<T>, f: impl FnOnce(&mut T) -> R) -> R {
+    interrupts::without_interrupts(|| f(&mut state.lock()))
+}
@@ -0,0 +1,176 @@
+//! Notificação de eventos entre tasks.
+//!
+//! `notify_one()` acorda a task mais antiga aguardando `notified()`; se
+//! nenhuma estiver aguardando, guarda uma permissão e o próximo
+//! `notified()` completa imediatamente. `notify_waiters()` acorda todas as
+//! tasks aguardando no momento, sem guardar permissão.
+//!
+//! Um `Notified` entra na fila no primeiro poll.
+
+use super::with_state;
+use alloc::{collections::VecDeque, vec::Vec};
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+/// Como uma task na fila foi notificada.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Notification {
+    One,
+    All,
+}
+
+struct Waiter {
+    id: u64,
+    waker: Waker,
+    notified: Option<Notification>,
+}
+
+struct State {
+    /// Permissão guardada por um `notify_one()` sem tasks aguardando.
+    permit: bool,
+    waiters: VecDeque<Waiter>,
+    next_id: u64,
+}
+
+impl State {
+    /// Notifica a task mais antiga ainda não notificada.
+    fn notify_one(&mut self) -> Option<Waker> {
+        match self.waiters.iter_mut().find(|waiter| waiter.notified.is_none()) {
+            Some(waiter) => {
+                waiter.notified = Some(Notification::One);
+                Some(waiter.waker.clone())
+            }
+            None => {
+                self.permit = true;
+                None
+            }
+        }
+    }
+}
+
+/// Notifica tasks de que um evento aconteceu.
+pub struct Notify {
+    state: spin::Mutex<State>,
+}
+
+impl Default for Notify {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Notify {
+    pub const fn new() -> Self {
+        Notify {
+            state: spin::Mutex::new(State {
+                permit: false,
+                waiters: VecDeque::new(),
+                next_id: 0,
+            }),
+        }
+    }
+
+    /// Future que completa na próxima notificação.
+    pub fn notified(&self) -> Notified<'_> {
+        Notified { notify: self, id: None }
+    }
+
+    /// Acorda uma task aguardando, ou guarda uma permissão.
+    ///
+    /// Pode ser chamado de handlers de interrupção.
+    pub fn notify_one(&self) {
+        if let Some(waker) = with_state(&self.state, State::notify_one) {
+            waker.wake();
+        }
+    }
+
+    /// Acorda todas as tasks aguardando no momento.
+    ///
+    /// Pode ser chamado de handlers de interrupção.
+    pub fn notify_waiters(&self) {
+        let wakers: Vec<Waker> = with_state(&self.state, |state| {
+            state
+                .waiters
+                .iter_mut()
+                .filter(|waiter| waiter.notified.is_none())
+                .map(|waiter| {
+                    waiter.notified = Some(Notification::All);
+                    waiter.waker.clone()
+                })
+                .collect()
+        });
+        wakers.into_iter().for_each(Waker::wake);
+    }
+}
+
+/// Future retornado por `Notify::notified`.
+pub struct Notified<'a> {
+    notify: &'a Notify,
+    /// Posição na fila de espera, após o primeiro `Pending`.
+    id: Option<u64>,
+}
+
+impl Future for Notified<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        let id = self.id;
+        let pending = with_state(&self.notify.state, |state| match id {
+            None if state.permit => {
+                state.permit = false;
+                None
+            }
+            None => {
+                let id = state.next_id;
+                state.next_id += 1;
+                state.waiters.push_back(Waiter {
+                    id,
+                    waker: cx.waker().clone(),
+                    notified: None,
+                });
+                Some(id)
+            }
+            Some(id) => {
+                let position = state
+                    .waiters
+                    .iter()
+                    .position(|waiter| waiter.id == id)
+                    .expect("notify waiter missing");
+                if state.waiters[position].notified.is_some() {
+                    state.waiters.remove(position);
+                    None
+                } else {
+                    state.waiters[position].waker = cx.waker().clone();
+                    Some(id)
+                }
+            }
+        });
+
+        self.id = pending;
+        match pending {
+            None => Poll::Ready(()),
+            Some(_) => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for Notified<'_> {
+    fn drop(&mut self) {
+        let Some(id) = self.id else { return };
+        let waker = with_state(&self.notify.state, |state| {
+            let position = state.waiters.iter().position(|waiter| waiter.id == id)?;
+            let waiter = state.waiters.remove(position)?;
+            // Um `notify_one` recebido e não consumido passa para a próxima
+            match waiter.notified {
+                Some(Notification::One) => state.notify_one(),
+                _ => None,
+            }
+        });
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
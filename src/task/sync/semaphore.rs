@@ -0,0 +1,212 @@
+//! Semáforo assíncrono com fila de espera FIFO.
+//!
+//! Quem chega primeiro é atendido primeiro: enquanto houver tasks na fila,
+//! novas aquisições entram no fim dela mesmo que haja permissões livres.
+//! Isso evita que um pedido grande (ex: escritor do `RwLock`) espere para
+//! sempre atrás de pedidos pequenos.
+
+use super::with_state;
+use alloc::{collections::VecDeque, vec::Vec};
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+/// Uma task na fila de espera.
+struct Waiter {
+    id: u64,
+    permits: usize,
+    waker: Waker,
+    /// As permissões já foram reservadas para esta task.
+    granted: bool,
+}
+
+struct State {
+    permits: usize,
+    waiters: VecDeque<Waiter>,
+    next_id: u64,
+}
+
+impl State {
+    /// Reserva permissões para as tasks do início da fila, em ordem.
+    fn grant(&mut self, wake: &mut Vec<Waker>) {
+        for waiter in self.waiters.iter_mut().filter(|waiter| !waiter.granted) {
+            if waiter.permits > self.permits {
+                break;
+            }
+            self.permits -= waiter.permits;
+            waiter.granted = true;
+            wake.push(waiter.waker.clone());
+        }
+    }
+}
+
+/// Semáforo com um número limitado de permissões.
+pub struct Semaphore {
+    state: spin::Mutex<State>,
+}
+
+impl Semaphore {
+    /// Cria um semáforo com `permits` permissões disponíveis.
+    pub const fn new(permits: usize) -> Self {
+        Semaphore {
+            state: spin::Mutex::new(State {
+                permits,
+                waiters: VecDeque::new(),
+                next_id: 0,
+            }),
+        }
+    }
+
+    /// Número de permissões livres no momento.
+    pub fn available_permits(&self) -> usize {
+        with_state(&self.state, |state| state.permits)
+    }
+
+    /// Aguarda e adquire uma permissão.
+    pub fn acquire(&self) -> Acquire<'_> {
+        self.acquire_many(1)
+    }
+
+    /// Aguarda e adquire `permits` permissões de uma vez.
+    pub fn acquire_many(&self, permits: usize) -> Acquire<'_> {
+        Acquire {
+            semaphore: self,
+            permits,
+            id: None,
+        }
+    }
+
+    /// Adquire uma permissão sem esperar, se houver uma livre.
+    pub fn try_acquire(&self) -> Option<SemaphorePermit<'_>> {
+        self.try_acquire_many(1)
+    }
+
+    /// Adquire `permits` permissões sem esperar, se estiverem livres.
+    pub fn try_acquire_many(&self, permits: usize) -> Option<SemaphorePermit<'_>> {
+        with_state(&self.state, |state| {
+            if state.waiters.is_empty() && state.permits >= permits {
+                state.permits -= permits;
+                Some(SemaphorePermit {
+                    semaphore: self,
+                    permits,
+                })
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Devolve permissões ao semáforo, acordando tasks na fila.
+    ///
+    /// Pode ser chamado de handlers de interrupção.
+    pub fn add_permits(&self, permits: usize) {
+        let mut wake = Vec::new();
+        with_state(&self.state, |state| {
+            state.permits += permits;
+            state.grant(&mut wake);
+        });
+        wake.into_iter().for_each(Waker::wake);
+    }
+}
+
+/// Future retornado por `Semaphore::acquire`.
+///
+/// Se descartado enquanto espera, sai da fila (e devolve as permissões
+/// que já tivessem sido reservadas para ele).
+pub struct Acquire<'a> {
+    semaphore: &'a Semaphore,
+    permits: usize,
+    /// Posição na fila de espera, após o primeiro `Pending`.
+    id: Option<u64>,
+}
+
+impl<'a> Future for Acquire<'a> {
+    type Output = SemaphorePermit<'a>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<SemaphorePermit<'a>> {
+        let (semaphore, permits, id) = (self.semaphore, self.permits, self.id);
+        let ready = with_state(&semaphore.state, |state| match id {
+            None if state.waiters.is_empty() && state.permits >= permits => {
+                state.permits -= permits;
+                Ok(())
+            }
+            None => {
+                let id = state.next_id;
+                state.next_id += 1;
+                state.waiters.push_back(Waiter {
+                    id,
+                    permits,
+                    waker: cx.waker().clone(),
+                    granted: false,
+                });
+                Err(Some(id))
+            }
+            Some(id) => {
+                let position = state
+                    .waiters
+                    .iter()
+                    .position(|waiter| waiter.id == id)
+                    .expect("semaphore waiter missing");
+                if state.waiters[position].granted {
+                    state.waiters.remove(position);
+                    Ok(())
+                } else {
+                    state.waiters[position].waker = cx.waker().clone();
+                    Err(Some(id))
+                }
+            }
+        });
+
+        match ready {
+            Ok(()) => {
+                self.id = None;
+                Poll::Ready(SemaphorePermit { semaphore, permits })
+            }
+            Err(id) => {
+                self.id = id;
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for Acquire<'_> {
+    fn drop(&mut self) {
+        let Some(id) = self.id else { return };
+        let mut wake = Vec::new();
+        with_state(&self.semaphore.state, |state| {
+            if let Some(position) = state.waiters.iter().position(|waiter| waiter.id == id) {
+                let waiter = state.waiters.remove(position).unwrap();
+                if waiter.granted {
+                    state.permits += waiter.permits;
+                }
+            }
+            // Sair da fila pode liberar quem estava atrás
+            state.grant(&mut wake);
+        });
+        wake.into_iter().for_each(Waker::wake);
+    }
+}
+
+/// Permissões adquiridas; devolvidas ao semáforo no `drop`.
+pub struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+    permits: usize,
+}
+
+impl SemaphorePermit<'_> {
+    /// Consome as permissões sem devolvê-las ao semáforo.
+    pub fn forget(mut self) {
+        self.permits = 0;
+    }
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        if self.permits > 0 {
+            self.semaphore.add_permits(self.permits);
+        }
+    }
+}
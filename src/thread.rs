@@ -0,0 +1,266 @@
+//! # Threads do Kernel
+//!
+//! ## Por que threads?
+//!
+//! Tasks assíncronas só trocam de vez em `.await`: um trecho de código
+//! bloqueante (um laço de polling, uma computação longa) segura o
+//! executor inteiro. Threads têm stack e registradores próprios, e o
+//! timer as troca à força (**preempção**), então código bloqueante pode
+//! rodar em paralelo com o resto do kernel.
+//!
+//! ## Estrutura
+//!
+//! ```text
+//! ┌──────────────┐  spawn   ┌──────────────────────────────────┐
+//! │ thread::spawn│ ───────→ │ Scheduler                        │
+//! └──────────────┘          │  threads: ID → Thread            │
+//!                           │  ready:   [ID, ID, ...] (FIFO)   │
+//!                           │  idle (current: por CPU)         │
+//!                           └──────────────────────────────────┘
+//!                                │ switch(): salva RSP da atual,
+//!                                v           carrega RSP da próxima
+//!                           ┌──────────────┐
+//!                           │ Thread       │ stack própria (com guarda),
+//!                           │  rsp, state  │ registradores salvos na stack
+//!                           └──────────────┘
+//! ```
+//!
+//! - `context`: troca de contexto em assembly
+//! - `stack`: stacks com página de guarda
+//! - `scheduler`: fila round-robin, preempção pelo timer, sleep
+//! - `wait_queue`: bloqueio até uma condição (usado por `join`)
+//!
+//! ## Inicialização
+//!
+//! `init()` transforma o código em execução na thread `main` (que segue
+//! usando a stack do bootloader) e cria a thread `idle`. Requer heap e
+//! `memory::set_kernel_space`, para mapear stacks.
+//!
+//! ## Threads e o executor
+//!
+//! O executor de tasks roda dentro de uma thread como qualquer outro
+//! código; quando fica sem tasks ele chama `wait_for_interrupt()`, que
+//! cede a CPU para outras threads prontas em vez de fazer HLT direto.
+//!
+//! ```ignore
+//! let handle = thread::spawn(|| {
+//!     let mut executor = Executor::new();
+//!     executor.spawn(example_task());
+//!     executor.run_until_idle();
+//! });
+//! handle.join();
+//! ```
+
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use core::{
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use x86_64::instructions::interrupts;
+
+mod context;
+mod scheduler;
+pub mod stack;
+mod wait_queue;
+
+pub(crate) use scheduler::{preempt, tick};
+pub use stack::StackError;
+pub use wait_queue::WaitQueue;
+
+/// Identificador único de uma thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ThreadId(u64);
+
+impl ThreadId {
+    fn new() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        ThreadId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for ThreadId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// Estado de uma thread no escalonador.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadState {
+    /// Na fila de prontas.
+    Ready,
+    /// Executando na CPU.
+    Running,
+    /// Esperando em uma `WaitQueue`.
+    Blocked,
+    /// Esperando um tick em `sleep`.
+    Sleeping,
+    /// Terminou; a stack será liberada pela próxima thread.
+    Dead,
+}
+
+/// Cópia dos dados de uma thread, retornada por `list()`.
+#[derive(Debug, Clone, Copy)]
+pub struct ThreadInfo {
+    pub id: ThreadId,
+    pub name: &'static str,
+    pub state: ThreadState,
+}
+
+/// Inicializa o escalonador: o código atual vira a thread `main`.
+///
+/// Requer heap e `memory::set_kernel_space`.
+pub fn init() -> Result<(), StackError> {
+    let idle_stack = stack::Stack::allocate()?;
+    scheduler::init(idle_stack);
+    Ok(())
+}
+
+/// Retorna se `init()` já foi chamado.
+pub fn is_initialized() -> bool {
+    scheduler::is_initialized()
+}
+
+/// Resultado de uma thread, compartilhado com seu `JoinHandle`.
+struct Packet<T> {
+    result: spin::Mutex<Option<T>>,
+    finished: WaitQueue,
+}
+
+/// Handle para esperar o fim de uma thread e obter seu resultado.
+pub struct JoinHandle<T> {
+    id: ThreadId,
+    packet: Arc<Packet<T>>,
+}
+
+impl<T> JoinHandle<T> {
+    /// ID da thread.
+    pub fn id(&self) -> ThreadId {
+        self.id
+    }
+
+    /// Retorna se a thread já terminou.
+    pub fn is_finished(&self) -> bool {
+        interrupts::without_interrupts(|| self.packet.result.lock().is_some())
+    }
+
+    /// Bloqueia até a thread terminar e retorna seu resultado.
+    pub fn join(self) -> T {
+        let packet = &self.packet;
+        packet.finished.wait_until(|| packet.result.lock().is_some());
+        interrupts::without_interrupts(|| packet.result.lock().take())
+            .expect("thread result already taken")
+    }
+}
+
+/// Opções de uma nova thread.
+#[derive(Debug, Clone, Copy)]
+pub struct Builder {
+    name: &'static str,
+}
+
+impl Builder {
+    pub const fn new() -> Self {
+        Builder { name: "thread" }
+    }
+
+    /// Nome exibido em `list()`.
+    pub const fn name(mut self, name: &'static str) -> Self {
+        self.name = name;
+        self
+    }
+
+    /// Cria a thread, que entra no fim da fila de prontas.
+    pub fn spawn<F, T>(self, f: F) -> Result<JoinHandle<T>, StackError>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let stack = stack::Stack::allocate()?;
+        let packet = Arc::new(Packet {
+            result: spin::Mutex::new(None),
+            finished: WaitQueue::new(),
+        });
+        let thread_packet = packet.clone();
+        let entry = Box::new(move || {
+            let result = f();
+            interrupts::without_interrupts(|| *thread_packet.result.lock() = Some(result));
+            thread_packet.finished.notify_all();
+        });
+        let id = scheduler::spawn(self.name, stack, entry);
+        Ok(JoinHandle { id, packet })
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Cria uma thread executando `f`.
+///
+/// # Panics
+/// Entra em panic se não for possível alocar a stack (ver `Builder::spawn`).
+pub fn spawn<F, T>(f: F) -> JoinHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    Builder::new().spawn(f).expect("failed to spawn thread")
+}
+
+/// ID da thread atual, ou `None` antes de `init()`.
+pub fn current_id() -> Option<ThreadId> {
+    scheduler::current_id()
+}
+
+/// Cede a CPU para a próxima thread pronta, se houver.
+pub fn yield_now() {
+    if is_initialized() {
+        interrupts::without_interrupts(|| scheduler::switch(ThreadState::Ready));
+    }
+}
+
+/// Dorme por `ticks` ticks do timer (~55 ms cada).
+///
+/// Antes de `init()` espera com HLT.
+pub fn sleep(ticks: u64) {
+    let wake_at = crate::time::ticks() + ticks;
+    if !is_initialized() {
+        while crate::time::ticks() < wake_at {
+            x86_64::instructions::hlt();
+        }
+        return;
+    }
+    interrupts::without_interrupts(|| scheduler::sleep_until(wake_at));
+}
+
+/// Espera por uma interrupção sem segurar a CPU: cede para outras
+/// threads prontas ou, se não houver nenhuma, executa HLT.
+///
+/// Como `interrupts::enable_and_hlt`, deve ser chamada com interrupções
+/// desabilitadas e retorna com elas habilitadas. O escalonador só roda na
+/// BSP: nas APs é sempre HLT.
+pub fn wait_for_interrupt() {
+    if crate::percpu::cpu_index() == 0 && scheduler::has_ready() {
+        scheduler::switch(ThreadState::Ready);
+        interrupts::enable();
+    } else {
+        interrupts::enable_and_hlt();
+    }
+}
+
+/// Threads vivas, inclusive `main` e `idle`.
+pub fn list() -> Vec<ThreadInfo> {
+    scheduler::snapshot()
+}
+
+/// Número de trocas de contexto desde `init()`.
+pub fn switch_count() -> u64 {
+    scheduler::switch_count()
+}
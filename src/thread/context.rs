@@ -0,0 +1,82 @@
+//! Troca de contexto entre threads.
+//!
+//! Pela convenção de chamada System V, quem chama `switch` já salvou os
+//! registradores *caller-saved*; só os *callee-saved* (`rbx`, `rbp`,
+//! `r12`-`r15`) e o `RFLAGS` precisam ir para a stack da thread que sai.
+//! O único estado guardado no `Thread` é o RSP resultante:
+//!
+//! ```text
+//!  stack da thread (topo)      RSP salvo
+//!  ┌────────────────────┐          │
+//!  │ endereço de retorno│          │
+//!  │ rflags             │          │
+//!  │ rbp                │          │
+//!  │ rbx                │          │
+//!  │ r12 .. r15         │ ←────────┘
+//!  └────────────────────┘
+//! ```
+//!
+//! Uma thread nova recebe uma stack montada no mesmo formato por
+//! `initial_stack`, com o endereço de retorno apontando para `entry`.
+
+use x86_64::VirtAddr;
+
+/// `RFLAGS` inicial: só o bit 1 (sempre ligado); interrupções desabilitadas
+/// até a thread terminar a troca de contexto.
+const INITIAL_RFLAGS: u64 = 0x2;
+
+/// Registradores salvos por `switch` (rflags, rbp, rbx, r12-r15).
+const SAVED_REGISTERS: usize = 7;
+
+/// Salva o contexto atual em `*old_rsp` e retoma o contexto em `new_rsp`.
+///
+/// Retorna quando alguma outra thread trocar de volta para esta.
+///
+/// # Safety
+/// Deve ser chamada com interrupções desabilitadas, e `new_rsp` deve ser
+/// um RSP salvo por `switch` ou criado por `initial_stack`.
+#[unsafe(naked)]
+pub(super) unsafe extern "C" fn switch(old_rsp: *mut u64, new_rsp: u64) {
+    core::arch::naked_asm!(
+        "pushfq",
+        "push rbp",
+        "push rbx",
+        "push r12",
+        "push r13",
+        "push r14",
+        "push r15",
+        "mov [rdi], rsp",
+        "mov rsp, rsi",
+        "pop r15",
+        "pop r14",
+        "pop r13",
+        "pop r12",
+        "pop rbx",
+        "pop rbp",
+        "popfq",
+        "ret",
+    )
+}
+
+/// Monta a stack de uma thread nova, que começa executando `entry`.
+///
+/// Retorna o RSP a ser passado para `switch`.
+///
+/// # Safety
+/// `stack_top` deve ser o topo (alinhado em 16 bytes) de uma stack
+/// mapeada e sem uso.
+pub(super) unsafe fn initial_stack(stack_top: VirtAddr, entry: extern "C" fn() -> !) -> u64 {
+    let top = stack_top.as_mut_ptr::<u64>();
+    unsafe {
+        // Endereço de retorno falso (0) encerra backtraces e deixa o RSP
+        // na entrada de `entry` como se ela tivesse sido chamada
+        top.sub(1).write(0);
+        top.sub(2).write(entry as usize as u64);
+        top.sub(3).write(INITIAL_RFLAGS);
+        // rbp = 0 também encerra backtraces; demais registradores zerados
+        for slot in 4..=2 + SAVED_REGISTERS {
+            top.sub(slot).write(0);
+        }
+        top.sub(2 + SAVED_REGISTERS) as u64
+    }
+}
@@ -0,0 +1,358 @@
+//! Escalonador round-robin de threads.
+//!
+//! ## Estados
+//!
+//! ```text
+//!            spawn
+//!              │
+//!              v       switch        exit
+//!   ┌──────→ Ready ───────────→ Running ────→ Dead
+//!   │                              │
+//!   │ wake() / tick()              │ block_current() / sleep_until()
+//!   │                              v
+//!   └─────────────────────── Blocked / Sleeping
+//! ```
+//!
+//! Threads prontas ficam numa fila FIFO. A thread `idle` nunca entra na
+//! fila: ela só executa quando todas as outras estão bloqueadas, e faz
+//! HLT até alguma interrupção acordar uma delas.
+//!
+//! ## Preempção
+//!
+//! A cada tick do timer, `tick()` acorda as threads cujo `sleep` venceu e
+//! pede uma troca (`NEED_RESCHED`). O stub comum de IRQs chama `preempt()`
+//! depois do EOI, ainda dentro da interrupção e na stack da thread
+//! interrompida; a troca salva o contexto ali, e a thread continua o
+//! retorno da interrupção (`iretq`) quando voltar a executar.
+//!
+//! ## Threads terminadas
+//!
+//! Uma thread não pode liberar a stack em que está executando. `exit()`
+//! só a marca como `Dead`; a próxima thread a executar remove seu
+//! `Thread` (e devolve a stack) em `finish_switch()`.
+
+use super::{context, stack::Stack, ThreadId, ThreadInfo, ThreadState};
+use crate::{
+    cpu_local,
+    fpu::{self, FpuState},
+    percpu, time,
+};
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, VecDeque},
+    vec::Vec,
+};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use x86_64::instructions::interrupts;
+
+/// Bloco de controle de uma thread.
+struct Thread {
+    id: ThreadId,
+    name: &'static str,
+    state: ThreadState,
+    /// RSP salvo por `context::switch` enquanto a thread não executa.
+    rsp: u64,
+    /// `None` para a thread de boot, que usa a stack do bootloader.
+    /// Só é guardada para voltar à lista livre no `drop`.
+    _stack: Option<Stack>,
+    /// Código da thread, retirado por `thread_entry` ao começar.
+    entry: Option<Box<dyn FnOnce() + Send>>,
+    /// Tick em que uma thread `Sleeping` deve acordar.
+    wake_at: u64,
+    /// Entrou numa fila de espera e ainda vai chamar `block_current()`.
+    /// Um `wake()` nesse intervalo limpa a marca e o bloqueio é cancelado.
+    blocking: bool,
+    /// Registradores de FPU/SSE, salvos sob demanda (ver `fpu`).
+    fpu: FpuState,
+}
+
+struct Scheduler {
+    threads: BTreeMap<ThreadId, Box<Thread>>,
+    ready: VecDeque<ThreadId>,
+    idle: ThreadId,
+    /// Threads terminadas, liberadas pela próxima thread a executar.
+    dead: Vec<ThreadId>,
+}
+
+static SCHEDULER: spin::Mutex<Option<Scheduler>> = spin::Mutex::new(None);
+static INITIALIZED: AtomicBool = AtomicBool::new(false);
+/// Pedido de troca de thread feito pelo timer.
+static NEED_RESCHED: AtomicBool = AtomicBool::new(false);
+/// Trocas de contexto desde `init`.
+static SWITCHES: AtomicU64 = AtomicU64::new(0);
+
+cpu_local! {
+    /// Thread em execução em cada CPU. Só muda com o lock do escalonador,
+    /// mas pode ser lida sem ele.
+    static CURRENT: AtomicU64 = AtomicU64::new(0);
+}
+
+/// Thread em execução nesta CPU.
+fn current() -> ThreadId {
+    ThreadId(CURRENT.get().load(Ordering::Relaxed))
+}
+
+fn with_scheduler<R>(f: impl FnOnce(&mut Scheduler) -> R) -> R {
+    interrupts::without_interrupts(|| {
+        let mut scheduler = SCHEDULER.lock();
+        f(scheduler.as_mut().expect("thread::init not called"))
+    })
+}
+
+/// Registra o código em execução como a thread `main` e cria a `idle`.
+pub(super) fn init(idle_stack: Stack) {
+    let mut main = Box::new(Thread {
+        id: ThreadId::new(),
+        name: "main",
+        state: ThreadState::Running,
+        rsp: 0,
+        _stack: None,
+        entry: None,
+        wake_at: 0,
+        blocking: false,
+        fpu: FpuState::new(),
+    });
+    let idle = new_thread("idle", idle_stack, Box::new(idle_loop));
+
+    let mut scheduler = Scheduler {
+        threads: BTreeMap::new(),
+        ready: VecDeque::new(),
+        idle: idle.id,
+        dead: Vec::new(),
+    };
+    CURRENT.get().store(main.id.as_u64(), Ordering::Relaxed);
+    interrupts::without_interrupts(|| fpu::switch_to(&mut main.fpu));
+    scheduler.threads.insert(main.id, main);
+    scheduler.threads.insert(idle.id, idle);
+
+    interrupts::without_interrupts(|| *SCHEDULER.lock() = Some(scheduler));
+    INITIALIZED.store(true, Ordering::Release);
+}
+
+pub(super) fn is_initialized() -> bool {
+    INITIALIZED.load(Ordering::Acquire)
+}
+
+fn new_thread(name: &'static str, stack: Stack, entry: Box<dyn FnOnce() + Send>) -> Box<Thread> {
+    let rsp = unsafe { context::initial_stack(stack.top(), thread_entry) };
+    Box::new(Thread {
+        id: ThreadId::new(),
+        name,
+        state: ThreadState::Ready,
+        rsp,
+        _stack: Some(stack),
+        entry: Some(entry),
+        wake_at: 0,
+        blocking: false,
+        fpu: FpuState::new(),
+    })
+}
+
+/// Cria uma thread e a coloca no fim da fila de prontas.
+pub(super) fn spawn(name: &'static str, stack: Stack, entry: Box<dyn FnOnce() + Send>) -> ThreadId {
+    let thread = new_thread(name, stack, entry);
+    let id = thread.id;
+    with_scheduler(|scheduler| {
+        scheduler.threads.insert(id, thread);
+        scheduler.ready.push_back(id);
+    });
+    id
+}
+
+/// ID da thread em execução, ou `None` antes de `init`.
+pub(super) fn current_id() -> Option<ThreadId> {
+    is_initialized().then(current)
+}
+
+/// Se há threads prontas esperando a CPU.
+pub(super) fn has_ready() -> bool {
+    is_initialized() && with_scheduler(|scheduler| !scheduler.ready.is_empty())
+}
+
+/// Estado das threads vivas (inclusive `main` e `idle`).
+pub(super) fn snapshot() -> Vec<ThreadInfo> {
+    with_scheduler(|scheduler| {
+        scheduler
+            .threads
+            .values()
+            .filter(|thread| thread.state != ThreadState::Dead)
+            .map(|thread| ThreadInfo {
+                id: thread.id,
+                name: thread.name,
+                state: thread.state,
+            })
+            .collect()
+    })
+}
+
+/// Trocas de contexto desde `init`.
+pub(super) fn switch_count() -> u64 {
+    SWITCHES.load(Ordering::Relaxed)
+}
+
+/// Troca a thread atual pela próxima pronta, deixando a atual em `state`.
+///
+/// Com `state == Ready` e nenhuma outra thread pronta, retorna direto.
+/// Requer interrupções desabilitadas; retorna com elas desabilitadas.
+pub(super) fn switch(state: ThreadState) {
+    debug_assert!(!interrupts::are_enabled());
+    let (old_rsp, new_rsp) = {
+        let mut guard = SCHEDULER.lock();
+        let scheduler = guard.as_mut().expect("thread::init not called");
+        let current = current();
+        if state == ThreadState::Blocked {
+            let thread = scheduler.threads.get_mut(&current).expect("current thread missing");
+            // `wake()` chegou entre `prepare_block()` e aqui: não bloqueia
+            if !core::mem::take(&mut thread.blocking) {
+                return;
+            }
+        }
+        let next = match scheduler.ready.pop_front() {
+            Some(next) => next,
+            None if state == ThreadState::Ready => return,
+            None => scheduler.idle,
+        };
+
+        match state {
+            ThreadState::Ready if current != scheduler.idle => scheduler.ready.push_back(current),
+            ThreadState::Dead => scheduler.dead.push(current),
+            _ => {}
+        }
+        let old = scheduler.threads.get_mut(&current).expect("current thread missing");
+        old.state = state;
+        let old_rsp = &mut old.rsp as *mut u64;
+
+        let new = scheduler.threads.get_mut(&next).expect("ready thread missing");
+        new.state = ThreadState::Running;
+        CURRENT.get().store(next.as_u64(), Ordering::Relaxed);
+        fpu::switch_to(&mut new.fpu);
+        (old_rsp, new.rsp)
+    };
+
+    SWITCHES.fetch_add(1, Ordering::Relaxed);
+    percpu::count_context_switch();
+    // O `Box<Thread>` da thread atual só é removido depois que outra
+    // thread executar, então `old_rsp` continua válido durante a troca.
+    unsafe { context::switch(old_rsp, new_rsp) };
+    finish_switch();
+}
+
+/// Executado pela thread que acabou de ganhar a CPU: libera threads mortas.
+fn finish_switch() {
+    let reaped: Vec<Box<Thread>> = {
+        let mut guard = SCHEDULER.lock();
+        let scheduler = guard.as_mut().expect("thread::init not called");
+        if scheduler.dead.is_empty() {
+            return;
+        }
+        let dead = core::mem::take(&mut scheduler.dead);
+        dead.iter().filter_map(|id| scheduler.threads.remove(id)).collect()
+    };
+    // Devolve as stacks e o heap fora do lock do escalonador
+    drop(reaped);
+}
+
+/// Primeira função executada por uma thread nova (ver `context::initial_stack`).
+extern "C" fn thread_entry() -> ! {
+    finish_switch();
+    let entry = with_scheduler(|scheduler| {
+        scheduler.threads.get_mut(&current()).and_then(|thread| thread.entry.take())
+    });
+    interrupts::enable();
+    (entry.expect("thread started twice"))();
+    exit()
+}
+
+/// Termina a thread atual.
+fn exit() -> ! {
+    interrupts::disable();
+    switch(ThreadState::Dead);
+    unreachable!("dead thread rescheduled");
+}
+
+/// Marca a thread atual como prestes a bloquear.
+///
+/// Deve ser chamada antes de a thread ficar visível para quem a acorda
+/// (ex: com o lock da fila de espera); a partir daí um `wake()` não se
+/// perde, mesmo vindo de outra CPU antes de `block_current()`.
+pub(super) fn prepare_block() {
+    with_scheduler(|scheduler| {
+        if let Some(thread) = scheduler.threads.get_mut(&current()) {
+            thread.blocking = true;
+        }
+    });
+}
+
+/// Bloqueia a thread atual até `wake(id)`, a menos que ele já tenha
+/// ocorrido desde `prepare_block()`. Requer interrupções desabilitadas.
+pub(super) fn block_current() {
+    switch(ThreadState::Blocked);
+}
+
+/// Acorda uma thread bloqueada ou prestes a bloquear. Retorna `false` se
+/// ela não estava esperando.
+pub(super) fn wake(id: ThreadId) -> bool {
+    with_scheduler(|scheduler| match scheduler.threads.get_mut(&id) {
+        Some(thread) if thread.state == ThreadState::Blocked => {
+            thread.state = ThreadState::Ready;
+            scheduler.ready.push_back(id);
+            true
+        }
+        Some(thread) if thread.blocking => {
+            thread.blocking = false;
+            true
+        }
+        _ => false,
+    })
+}
+
+/// Dorme até o tick `wake_at`. Requer interrupções desabilitadas.
+pub(super) fn sleep_until(wake_at: u64) {
+    if time::ticks() >= wake_at {
+        return;
+    }
+    with_scheduler(|scheduler| {
+        if let Some(thread) = scheduler.threads.get_mut(&current()) {
+            thread.wake_at = wake_at;
+        }
+    });
+    switch(ThreadState::Sleeping);
+}
+
+/// Acorda threads cujo `sleep` venceu e pede preempção. Chamado pelo timer.
+pub(crate) fn tick() {
+    if !is_initialized() {
+        return;
+    }
+    let now = time::ticks();
+    with_scheduler(|scheduler| {
+        let Scheduler { threads, ready, .. } = scheduler;
+        for thread in threads.values_mut() {
+            if thread.state == ThreadState::Sleeping && thread.wake_at <= now {
+                thread.state = ThreadState::Ready;
+                ready.push_back(thread.id);
+            }
+        }
+    });
+    NEED_RESCHED.store(true, Ordering::Relaxed);
+}
+
+/// Troca de thread se o timer pediu. Chamado no fim do stub de IRQs, com
+/// interrupções desabilitadas.
+pub(crate) fn preempt() {
+    if is_initialized() && NEED_RESCHED.swap(false, Ordering::Relaxed) {
+        switch(ThreadState::Ready);
+    }
+}
+
+/// Corpo da thread `idle`: espera interrupções até haver threads prontas.
+fn idle_loop() {
+    loop {
+        interrupts::disable();
+        if has_ready() {
+            switch(ThreadState::Ready);
+        } else {
+            interrupts::enable_and_hlt();
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Stacks de threads com página de guarda.
+//!
+//! As stacks ficam numa região virtual própria, dividida em slots de
+//! `STACK_PAGES + 1` páginas. A página mais baixa de cada slot nunca é
+//! mapeada: uma thread que estoura a stack acessa essa página e gera um
+//! page fault (que vira double fault, tratado na stack da IST) em vez de
+//! corromper a stack vizinha.
+//!
+//! ```text
+//! STACKS_START
+//! ┌───────┬─────────────────┬───────┬─────────────────┬─────
+//! │ guard │ stack 0 (16 KB) │ guard │ stack 1 (16 KB) │ ...
+//! └───────┴─────────────────┴───────┴─────────────────┴─────
+//!            ↑ cresce para baixo  ↑ topo
+//! ```
+//!
+//! Slots liberados mantêm suas páginas mapeadas e são reutilizados pela
+//! próxima thread. Um slot novo é reservado com o lock de `SLOTS` e
+//! mapeado depois de soltá-lo, sem aninhar os dois locks.
+
+use crate::memory;
+use alloc::vec::Vec;
+use x86_64::{
+    instructions::interrupts,
+    structures::paging::{
+        mapper::MapToError, FrameAllocator, Mapper, Page, PageTableFlags, Size4KiB,
+    },
+    VirtAddr,
+};
+
+/// Início da região de stacks na memória virtual.
+pub const STACKS_START: u64 = 0x_5555_5555_0000;
+/// Páginas utilizáveis de cada stack (16 KB).
+pub const STACK_PAGES: u64 = 4;
+/// Número máximo de stacks (e de threads com stack própria).
+pub const MAX_STACKS: u64 = 256;
+
+const PAGE_SIZE: u64 = 4096;
+const SLOT_SIZE: u64 = (STACK_PAGES + 1) * PAGE_SIZE;
+
+/// Slots livres e quantos slots já foram reservados para mapeamento.
+struct Slots {
+    free: Vec<u64>,
+    /// Slots cujo mapeamento falhou, tentados de novo antes de um slot novo.
+    unmapped: Vec<u64>,
+    mapped: u64,
+}
+
+static SLOTS: spin::Mutex<Slots> = spin::Mutex::new(Slots {
+    free: Vec::new(),
+    unmapped: Vec::new(),
+    mapped: 0,
+});
+
+/// Erros ao alocar uma stack.
+#[derive(Debug)]
+pub enum StackError {
+    /// Todos os `MAX_STACKS` slots estão em uso.
+    Exhausted,
+    /// Falha ao mapear as páginas da stack.
+    MapFailed(MapToError<Size4KiB>),
+}
+
+/// Stack de uma thread. O slot volta para a lista livre no `drop`.
+#[derive(Debug)]
+pub struct Stack {
+    slot: u64,
+}
+
+impl Stack {
+    /// Aloca uma stack, reutilizando um slot livre se houver.
+    ///
+    /// Requer `memory::set_kernel_space` para mapear slots novos.
+    pub fn allocate() -> Result<Stack, StackError> {
+        // (slot, se ainda precisa ser mapeado)
+        let (slot, unmapped) = interrupts::without_interrupts(|| {
+            let mut slots = SLOTS.lock();
+            if let Some(slot) = slots.free.pop() {
+                return Ok((slot, false));
+            }
+            if let Some(slot) = slots.unmapped.pop() {
+                return Ok((slot, true));
+            }
+            if slots.mapped == MAX_STACKS {
+                return Err(StackError::Exhausted);
+            }
+            slots.mapped += 1;
+            Ok((slots.mapped - 1, true))
+        })?;
+        if unmapped {
+            if let Err(err) = map_slot(slot) {
+                interrupts::without_interrupts(|| SLOTS.lock().unmapped.push(slot));
+                return Err(StackError::MapFailed(err));
+            }
+        }
+        Ok(Stack { slot })
+    }
+
+    /// Topo da stack (endereço logo acima da última página, alinhado).
+    pub fn top(&self) -> VirtAddr {
+        slot_base(self.slot) + SLOT_SIZE
+    }
+
+    /// Página de guarda logo abaixo da stack.
+    pub fn guard_page(&self) -> Page {
+        Page::containing_address(slot_base(self.slot))
+    }
+}
+
+impl Drop for Stack {
+    fn drop(&mut self) {
+        interrupts::without_interrupts(|| SLOTS.lock().free.push(self.slot));
+    }
+}
+
+fn slot_base(slot: u64) -> VirtAddr {
+    VirtAddr::new(STACKS_START + slot * SLOT_SIZE)
+}
+
+/// Mapeia as páginas de um slot, deixando a primeira (guarda) de fora.
+///
+/// Páginas já mapeadas (de uma tentativa anterior que falhou no meio) são
+/// mantidas. Não há shootdown: as páginas não estavam presentes, então
+/// nenhuma CPU pode ter uma entrada delas na TLB.
+fn map_slot(slot: u64) -> Result<(), MapToError<Size4KiB>> {
+    let first = Page::containing_address(slot_base(slot) + PAGE_SIZE);
+    let last = Page::containing_address(slot_base(slot) + SLOT_SIZE - 1u64);
+    let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
+
+    memory::with_kernel_space(|space| {
+        for page in Page::range_inclusive(first, last) {
+            if space.mapper.translate_page(page).is_ok() {
+                continue;
+            }
+            let frame = space
+                .frame_allocator
+                .allocate_frame()
+                .ok_or(MapToError::FrameAllocationFailed)?;
+            unsafe {
+                space
+                    .mapper
+                    .map_to(page, frame, flags, &mut space.frame_allocator)?
+                    .ignore()
+            };
+        }
+        Ok(())
+    })
+}
+
+/// Verifica se `addr` está na página de guarda de algum slot de stack.
+///
+/// Usado pelo handler de double fault para identificar estouros de stack
+/// de threads.
+pub fn is_guard_page(addr: VirtAddr) -> bool {
+    let addr = addr.as_u64();
+    let end = STACKS_START + MAX_STACKS * SLOT_SIZE;
+    (STACKS_START..end).contains(&addr) && (addr - STACKS_START) % SLOT_SIZE < PAGE_SIZE
+}
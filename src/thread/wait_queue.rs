@@ -0,0 +1,89 @@
+//! Filas de espera para threads bloqueantes.
+//!
+//! Uma thread que precisa esperar uma condição entra na fila e sai do
+//! escalonador (`Blocked`); quem muda a condição chama `notify_one` ou
+//! `notify_all`, que devolvem as threads à fila de prontas:
+//!
+//! ```text
+//! wait_until(cond): cond()? ─ sim → retorna
+//!                     │ não
+//!                     └→ waiters.push(atual) → block_current() ─┐
+//!                                                              │
+//! notify_all(): waiters.pop() → wake(id) ──────────────────────┘ testa cond() de novo
+//! ```
+//!
+//! A condição é testada com interrupções desabilitadas, então um
+//! `notify` vindo de um handler de IRQ não se perde entre o teste e o
+//! bloqueio. Um `notify` de outra CPU pode tirar a thread da fila antes de
+//! ela bloquear; por isso a thread é marcada como "prestes a bloquear"
+//! (`scheduler::prepare_block`) com o lock da fila, e o `wake` nesse
+//! intervalo cancela o bloqueio. Antes de `thread::init` a espera é feita
+//! com HLT.
+
+use super::{scheduler, ThreadId};
+use alloc::collections::VecDeque;
+use x86_64::instructions::interrupts;
+
+/// Fila de threads esperando uma condição.
+pub struct WaitQueue {
+    waiters: spin::Mutex<VecDeque<ThreadId>>,
+}
+
+impl WaitQueue {
+    pub const fn new() -> Self {
+        WaitQueue {
+            waiters: spin::Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Bloqueia a thread atual até `condition` retornar `true`.
+    ///
+    /// `condition` roda com interrupções desabilitadas e não deve bloquear.
+    pub fn wait_until(&self, mut condition: impl FnMut() -> bool) {
+        interrupts::without_interrupts(|| {
+            while !condition() {
+                match scheduler::current_id() {
+                    Some(id) => {
+                        {
+                            let mut waiters = self.waiters.lock();
+                            scheduler::prepare_block();
+                            waiters.push_back(id);
+                        }
+                        scheduler::block_current();
+                    }
+                    None => {
+                        interrupts::enable_and_hlt();
+                        interrupts::disable();
+                    }
+                }
+            }
+        })
+    }
+
+    /// Acorda a thread que espera há mais tempo. Retorna se havia alguma.
+    pub fn notify_one(&self) -> bool {
+        interrupts::without_interrupts(|| {
+            let mut waiters = self.waiters.lock();
+            while let Some(id) = waiters.pop_front() {
+                if scheduler::wake(id) {
+                    return true;
+                }
+            }
+            false
+        })
+    }
+
+    /// Acorda todas as threads da fila. Retorna quantas foram acordadas.
+    pub fn notify_all(&self) -> usize {
+        interrupts::without_interrupts(|| {
+            let mut waiters = self.waiters.lock();
+            waiters.drain(..).filter(|&id| scheduler::wake(id)).count()
+        })
+    }
+}
+
+impl Default for WaitQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
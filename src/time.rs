@@ -0,0 +1,121 @@
+//! # Contagem de Tempo
+//!
+//! ## Fontes de tempo
+//!
+//! - **Ticks do timer**: O PIT (IRQ 0) dispara ~18.2 vezes por segundo.
+//!   Cada interrupção incrementa `TICKS`, que serve como relógio monotônico
+//!   de baixa resolução.
+//! - **TSC (Time Stamp Counter)**: Contador de ciclos da CPU lido com
+//!   `rdtsc`. Alta resolução, usado para medir latências curtas.
+//!
+//! ```text
+//! IRQ 0 → timer_interrupt_handler → time::tick() → TICKS += 1
+//! ```
+//!
+//! ## Calibração do TSC
+//!
+//! A frequência do TSC não é informada pelo hardware de forma portável.
+//! `calibrate_tsc()` conta quantos ciclos passam numa janela de 10 ms do
+//! HPET, quando ele foi inicializado (`hpet::init`), ou entre ticks do PIT,
+//! cuja frequência é conhecida (1.193.182 Hz / 65536).
+
+use core::sync::atomic::{AtomicU64, Ordering};
+use x86_64::instructions::{hlt, interrupts};
+
+/// Frequência do oscilador do PIT em Hz.
+const PIT_BASE_FREQUENCY: u64 = 1_193_182;
+/// Divisor padrão do PIT (não reprogramado pelo kernel).
+const PIT_DIVISOR: u64 = 65536;
+/// Ticks usados na calibração do TSC pelo PIT (~110 ms).
+const CALIBRATION_TICKS: u64 = 2;
+/// Janela da calibração do TSC pelo HPET.
+const HPET_CALIBRATION_US: u64 = 10_000;
+
+/// Número de interrupções do timer desde o boot.
+static TICKS: AtomicU64 = AtomicU64::new(0);
+
+/// Incrementa o contador de ticks. Chamado pelo handler do timer.
+pub(crate) fn tick() {
+    TICKS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Retorna o número de ticks do timer desde o boot.
+pub fn ticks() -> u64 {
+    TICKS.load(Ordering::Relaxed)
+}
+
+/// Lê o Time Stamp Counter (ciclos da CPU).
+pub fn rdtsc() -> u64 {
+    unsafe { core::arch::x86_64::_rdtsc() }
+}
+
+/// Frequência estimada do TSC em Hz (0 = não calibrado).
+static TSC_FREQUENCY: AtomicU64 = AtomicU64::new(0);
+
+/// Espera pelo início do próximo tick do timer.
+fn wait_next_tick() -> u64 {
+    let start = ticks();
+    while ticks() == start {
+        hlt();
+    }
+    ticks()
+}
+
+/// Mede a frequência do TSC contra o HPET (ou os ticks do PIT) e a armazena.
+///
+/// A medida só vale enquanto a frequência não muda, o que é garantido
+/// com `cpu::features().invariant_tsc`.
+///
+/// Sem HPET, requer interrupções habilitadas (bloqueia por ~110 ms).
+pub fn calibrate_tsc() -> u64 {
+    if crate::hpet::is_initialized() {
+        let frequency = crate::hpet::measure_frequency(HPET_CALIBRATION_US, rdtsc);
+        TSC_FREQUENCY.store(frequency, Ordering::Relaxed);
+        return frequency;
+    }
+    assert!(interrupts::are_enabled(), "TSC calibration requires interrupts");
+    let first_tick = wait_next_tick();
+    let start = rdtsc();
+    while ticks() < first_tick + CALIBRATION_TICKS {
+        hlt();
+    }
+    let cycles = rdtsc() - start;
+    let frequency = cycles * PIT_BASE_FREQUENCY / (PIT_DIVISOR * CALIBRATION_TICKS);
+    TSC_FREQUENCY.store(frequency, Ordering::Relaxed);
+    frequency
+}
+
+/// Frequência do TSC em Hz, se já calibrada.
+pub fn tsc_frequency() -> Option<u64> {
+    match TSC_FREQUENCY.load(Ordering::Relaxed) {
+        0 => None,
+        frequency => Some(frequency),
+    }
+}
+
+/// Converte ciclos do TSC em microssegundos, se o TSC foi calibrado.
+pub fn cycles_to_us(cycles: u64) -> Option<u64> {
+    tsc_frequency().map(|hz| (cycles as u128 * 1_000_000 / hz as u128) as u64)
+}
+
+/// Converte ticks do timer em microssegundos.
+pub fn ticks_to_us(ticks: u64) -> u64 {
+    (ticks as u128 * PIT_DIVISOR as u128 * 1_000_000 / PIT_BASE_FREQUENCY as u128) as u64
+}
+
+/// Converte microssegundos em ciclos do TSC, se o TSC foi calibrado.
+pub fn us_to_cycles(us: u64) -> Option<u64> {
+    tsc_frequency().map(|hz| (us as u128 * hz as u128 / 1_000_000) as u64)
+}
+
+/// Espera ativa de `us` microssegundos, medida pelo TSC.
+///
+/// # Panics
+/// Entra em panic se o TSC não foi calibrado.
+pub fn delay_us(us: u64) {
+    let cycles = us_to_cycles(us).expect("TSC not calibrated");
+    let start = rdtsc();
+    while rdtsc() - start < cycles {
+        core::hint::spin_loop();
+    }
+}
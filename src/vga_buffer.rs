@@ -39,11 +39,10 @@
 //!
 //! [VGA Text Mode](https://os.phil-opp.com/vga-text-mode/) - Blog OS
 
+use crate::spinlock::{IrqSpinlock, IrqSpinlockGuard};
 use core::fmt::{Arguments, Result, Write};
 use lazy_static::lazy_static;
-use spin::Mutex;
 use volatile::Volatile;
-use x86_64::instructions::interrupts;
 
 /// Cores disponíveis no VGA text mode (4 bits cada).
 #[allow(dead_code)]
@@ -165,12 +164,18 @@ impl Write for Writer {
 }
 
 // Writer global thread-safe (spinlock) inicializado com lazy_static.
+//
+// `IrqSpinlock` desabilita interrupções enquanto o lock está preso, então
+// handlers (ex: `keyboard::add_scancode`) podem usar `println!`.
 lazy_static! {
-    pub static ref WRITER: Mutex<Writer> = Mutex::new(Writer {
-        column_position: 0,
-        color_code: ColorCode::new(Color::Green, Color::Black),
-        buffer: unsafe { &mut *(0xb8000 as *mut Buffer) },
-    });
+    pub static ref WRITER: IrqSpinlock<Writer> = IrqSpinlock::new(
+        "WRITER",
+        Writer {
+            column_position: 0,
+            color_code: ColorCode::new(Color::Green, Color::Black),
+            buffer: unsafe { &mut *(0xb8000 as *mut Buffer) },
+        }
+    );
 }
 
 /// Macro para print sem newline.
@@ -186,23 +191,25 @@ macro_rules! println {
     ($($arg:tt)*) => ($crate::print!("{}\n", format_args!($($arg)*)));
 }
 
+/// Acesso ao `WRITER` para panic handlers, mesmo que o panic tenha
+/// acontecido com o lock preso (ver `IrqSpinlock::emergency_lock`).
+pub fn lock_for_panic() -> IrqSpinlockGuard<'static, Writer> {
+    unsafe { WRITER.emergency_lock() }
+}
+
 #[doc(hidden)]
 pub fn _print(args: Arguments) {
-    interrupts::without_interrupts(|| {
-        WRITER.lock().write_fmt(args).unwrap();
-    });
+    WRITER.lock().write_fmt(args).unwrap();
 }
 
 /// Testa se println escreve corretamente no buffer VGA.
 #[test_case]
 fn test_println_output() {
     let s = "Test string on single line";
-    interrupts::without_interrupts(|| {
-        let mut writer = WRITER.lock();
-        writeln!(writer, "\n{}", s).expect("writeln failed");
-        for (i, c) in s.chars().enumerate() {
-            let screen_char = writer.buffer.chars[BUFFER_HEIGHT - 2][i].read();
-            assert_eq!(char::from(screen_char.ascii_character), c);
-        }
-    });
+    let mut writer = WRITER.lock();
+    writeln!(writer, "\n{}", s).expect("writeln failed");
+    for (i, c) in s.chars().enumerate() {
+        let screen_char = writer.buffer.chars[BUFFER_HEIGHT - 2][i].read();
+        assert_eq!(char::from(screen_char.ascii_character), c);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,224 @@
+//! # Watchdog de Travamento (NMI)
+//!
+//! ## O problema
+//!
+//! Se o kernel entra em loop com interrupções desabilitadas (por exemplo,
+//! um deadlock no `WRITER` ou no `SERIAL1`, que desabilitam interrupções),
+//! nenhuma IRQ é entregue e nada mais roda: o sistema congela em silêncio.
+//!
+//! ## A solução
+//!
+//! A única interrupção que não pode ser mascarada é a **NMI**. O watchdog
+//! programa uma fonte periódica de NMIs e, a cada NMI, verifica se o tick
+//! do timer avançou desde a anterior:
+//!
+//! ```text
+//! Contador de performance estoura → LVT (modo NMI) → nmi_handler
+//!                                                       │
+//!                               ticks avançaram? ─ sim ─┤→ recarrega e retorna
+//!                                       │ não            │
+//!                                       v                │
+//!                     N NMIs seguidas → relatório na serial (RIP, locks)
+//! ```
+//!
+//! ## Fonte de NMIs
+//!
+//! O timer do APIC não tem campo de modo de entrega na LVT (é sempre
+//! "fixed"), então usamos o **contador de performance 0** contando ciclos
+//! não-ociosos (`UnHalted Core Cycles`) com a entrada da LVT em modo NMI.
+//! Ciclos não contam durante `hlt`, então uma CPU ociosa não gera NMIs,
+//! mas uma CPU presa em loop gera.
+//!
+//! Requer *architectural performance monitoring* (CPUID.0Ah). No QEMU isso
+//! exige KVM (`-enable-kvm -cpu host`); sem suporte, `init()` retorna erro.
+//!
+//! ## NMIs externas
+//!
+//! NMIs que não vieram do contador (ex: comando `nmi` do monitor do QEMU)
+//! geram o mesmo relatório, o que permite inspecionar um kernel travado.
+
+use crate::{
+    apic, backtrace, cpu,
+    serial::{EmergencyWriter, SERIAL1},
+    spinlock::IrqSpinlock,
+    time,
+    vga_buffer::WRITER,
+};
+use core::{
+    fmt::Write,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+};
+use x86_64::{registers::model_specific::Msr, structures::idt::InterruptStackFrame};
+
+/// Período entre NMIs do watchdog, em segundos aproximados.
+const NMI_PERIOD_SECS: u64 = 1;
+/// NMIs seguidas sem avanço do tick para considerar o kernel travado.
+const STALL_THRESHOLD: u64 = 3;
+
+/// MSRs de monitoramento de performance (Intel, arquitetural).
+const IA32_PMC0: u32 = 0xC1;
+const IA32_PERFEVTSEL0: u32 = 0x186;
+const IA32_PERF_GLOBAL_STATUS: u32 = 0x38E;
+const IA32_PERF_GLOBAL_CTRL: u32 = 0x38F;
+const IA32_PERF_GLOBAL_OVF_CTRL: u32 = 0x390;
+
+/// Evento "UnHalted Core Cycles" (event 0x3C, umask 0x00).
+const EVENT_UNHALTED_CORE_CYCLES: u64 = 0x3C;
+const EVTSEL_USR: u64 = 1 << 16;
+const EVTSEL_OS: u64 = 1 << 17;
+const EVTSEL_INT: u64 = 1 << 20;
+const EVTSEL_EN: u64 = 1 << 22;
+
+/// Maior valor inicial aceito por escritas em `IA32_PMC0` (32 bits com sinal).
+const MAX_PERIOD_CYCLES: u64 = (1 << 31) - 1;
+
+/// Erros de inicialização do watchdog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchdogError {
+    /// CPU sem Local APIC.
+    NoApic,
+    /// CPU sem contadores de performance arquiteturais.
+    NoPerfCounters,
+    /// O TSC não foi calibrado (`time::calibrate_tsc`).
+    TscNotCalibrated,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// Ciclos entre NMIs (valor recarregado no contador).
+static PERIOD_CYCLES: AtomicU64 = AtomicU64::new(0);
+/// Tick observado na última NMI do watchdog.
+static LAST_TICKS: AtomicU64 = AtomicU64::new(0);
+/// NMIs seguidas sem avanço do tick.
+static STALLED_NMIS: AtomicU64 = AtomicU64::new(0);
+/// Total de NMIs recebidas.
+static NMI_COUNT: AtomicU64 = AtomicU64::new(0);
+/// Frame pointer do handler na última NMI (dentro da stack da IST).
+static LAST_NMI_RBP: AtomicU64 = AtomicU64::new(0);
+
+/// Verifica suporte a contadores de performance arquiteturais.
+fn perf_counters_supported() -> bool {
+    let features = cpu::features();
+    // Versão 2+ é necessária para as MSRs globais (status e overflow)
+    features.perfmon_version >= 2 && features.perfmon_counters >= 1 && features.perfmon_cycles
+}
+
+/// Carrega o contador com `-period`, para estourar após `period` ciclos.
+fn reload_counter() {
+    let period = PERIOD_CYCLES.load(Ordering::Relaxed);
+    unsafe { Msr::new(IA32_PMC0).write(period.wrapping_neg() & 0xFFFF_FFFF) };
+}
+
+/// Inicia o watchdog na CPU atual.
+///
+/// Requer `apic::init()` e `time::calibrate_tsc()`.
+pub fn init() -> Result<(), WatchdogError> {
+    if !apic::is_supported() || !apic::is_initialized() {
+        return Err(WatchdogError::NoApic);
+    }
+    if !perf_counters_supported() {
+        return Err(WatchdogError::NoPerfCounters);
+    }
+    let tsc_hz = time::tsc_frequency().ok_or(WatchdogError::TscNotCalibrated)?;
+
+    // Ciclos não-ociosos aproximados pelo TSC; limitado ao máximo do contador
+    let period = (tsc_hz * NMI_PERIOD_SECS).min(MAX_PERIOD_CYCLES);
+    PERIOD_CYCLES.store(period, Ordering::Relaxed);
+    LAST_TICKS.store(time::ticks(), Ordering::Relaxed);
+
+    unsafe {
+        Msr::new(IA32_PERFEVTSEL0).write(0);
+        reload_counter();
+        apic::set_lvt_perf_counter(apic::DELIVERY_MODE_NMI);
+        Msr::new(IA32_PERFEVTSEL0).write(
+            EVENT_UNHALTED_CORE_CYCLES | EVTSEL_USR | EVTSEL_OS | EVTSEL_INT | EVTSEL_EN,
+        );
+        let mut global_ctrl = Msr::new(IA32_PERF_GLOBAL_CTRL);
+        let value = global_ctrl.read();
+        global_ctrl.write(value | 1);
+    }
+
+    ENABLED.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Retorna se o watchdog está ativo.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Número de NMIs recebidas desde o boot.
+pub fn nmi_count() -> u64 {
+    NMI_COUNT.load(Ordering::Relaxed)
+}
+
+/// Frame pointer do handler de NMI na última NMI recebida.
+///
+/// Permite conferir que a NMI rodou na stack da IST (`gdt::NMI_IST_INDEX`).
+pub fn last_nmi_frame_pointer() -> u64 {
+    LAST_NMI_RBP.load(Ordering::Relaxed)
+}
+
+/// Verifica (e limpa) o estouro do contador do watchdog.
+fn take_counter_overflow() -> bool {
+    if !is_enabled() {
+        return false;
+    }
+    unsafe {
+        let overflowed = Msr::new(IA32_PERF_GLOBAL_STATUS).read() & 1 != 0;
+        if overflowed {
+            Msr::new(IA32_PERF_GLOBAL_OVF_CTRL).write(1);
+            reload_counter();
+            // Alguns processadores mascaram a entrada da LVT ao entregar a NMI
+            apic::set_lvt_perf_counter(apic::DELIVERY_MODE_NMI);
+        }
+        overflowed
+    }
+}
+
+/// Tratamento de NMI, chamado pelo handler em `interrupts.rs`.
+///
+/// `handler_rbp` é o frame pointer do handler, usado no backtrace.
+pub(crate) fn handle_nmi(stack_frame: &InterruptStackFrame, handler_rbp: u64) {
+    LAST_NMI_RBP.store(handler_rbp, Ordering::Relaxed);
+    NMI_COUNT.fetch_add(1, Ordering::Relaxed);
+
+    if !take_counter_overflow() {
+        report("NMI received", stack_frame, handler_rbp);
+        return;
+    }
+
+    let now = time::ticks();
+    if LAST_TICKS.swap(now, Ordering::Relaxed) != now {
+        STALLED_NMIS.store(0, Ordering::Relaxed);
+        return;
+    }
+
+    // Relata uma vez por travamento, ao atingir o limite
+    if STALLED_NMIS.fetch_add(1, Ordering::Relaxed) + 1 == STALL_THRESHOLD {
+        report("WATCHDOG: kernel hung (timer tick not advancing)", stack_frame, handler_rbp);
+    }
+}
+
+/// Descreve o estado de um lock sem bloquear.
+fn write_lock_state<T: ?Sized>(out: &mut EmergencyWriter, lock: &IrqSpinlock<T>) {
+    let _ = match lock.owner() {
+        Some(owner) => writeln!(out, "  {}: held by {}", lock.name(), owner),
+        None if lock.is_locked() => writeln!(out, "  {}: held", lock.name()),
+        None => writeln!(out, "  {}: free", lock.name()),
+    };
+}
+
+/// Escreve o relatório na serial sem usar o lock de `SERIAL1`.
+fn report(reason: &str, stack_frame: &InterruptStackFrame, handler_rbp: u64) {
+    let mut out = EmergencyWriter;
+    let _ = writeln!(out, "\n{}", reason);
+    let _ = writeln!(out, "  RIP: {:?}", stack_frame.instruction_pointer);
+    let _ = writeln!(out, "  RSP: {:?}", stack_frame.stack_pointer);
+    let _ = writeln!(out, "  ticks: {}", time::ticks());
+    write_lock_state(&mut out, &WRITER);
+    write_lock_state(&mut out, &SERIAL1);
+    let _ = writeln!(out, "  Backtrace:");
+    backtrace::trace_exception(stack_frame, handler_rbp, |frame| {
+        let _ = writeln!(out, "    {}", frame);
+    });
+}
@@ -0,0 +1,349 @@
+//! Testes de integração para a leitura das tabelas ACPI do QEMU.
+
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(rust_os::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::{format, vec, vec::Vec};
+use bootloader::{entry_point, BootInfo};
+use core::panic::PanicInfo;
+use rust_os::{
+    acpi::{self, AcpiError, Signature},
+    allocator,
+    memory::{self, BootInfoFrameAllocator},
+};
+use x86_64::{PhysAddr, VirtAddr};
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    rust_os::init();
+
+    let phys_mem_offset = VirtAddr::new(boot_info.physical_memory_offset);
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+
+    test_main();
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    rust_os::test_panic_handler(info)
+}
+
+/// Testa que o RSDP é encontrado abaixo de 1 MiB (EBDA ou área de BIOS).
+#[test_case]
+fn rsdp_found() {
+    let rsdp = acpi::rsdp().unwrap();
+    assert!(rsdp.address.as_u64() < 0x100000);
+    assert!(!rsdp.oem_id().is_empty());
+}
+
+/// Testa que as tabelas padrão do QEMU estão listadas e são válidas.
+#[test_case]
+fn standard_tables_are_valid() {
+    let tables = acpi::tables().unwrap();
+    for signature in [Signature::MADT, Signature::FADT, Signature::HPET].iter() {
+        let table = tables
+            .iter()
+            .find(|table| table.header.signature == *signature)
+            .unwrap_or_else(|| panic!("{} not listed", signature));
+        assert_eq!(table.status, Ok(()));
+    }
+    assert!(tables.iter().all(|table| table.status.is_ok()));
+}
+
+/// Testa o MADT: CPUs, I/O APIC e o override da IRQ 0.
+#[test_case]
+fn madt_parsed() {
+    let madt = acpi::madt().unwrap();
+    assert_eq!(madt.local_apics.len(), 2);
+    assert_eq!(madt.local_apic_address.as_u64(), 0xFEE0_0000);
+    assert!(madt.pcat_compatible);
+    assert_eq!(madt.io_apics.len(), 1);
+    assert_eq!(madt.io_apics[0].gsi_base, 0);
+    // O PIT (IRQ 0) fica ligado à GSI 2 no QEMU
+    assert!(madt
+        .interrupt_overrides
+        .iter()
+        .any(|irq| irq.source == 0 && irq.gsi == 2));
+}
+
+/// Testa os campos do FADT que o QEMU preenche.
+#[test_case]
+fn fadt_parsed() {
+    let fadt = acpi::fadt().unwrap();
+    assert_eq!(fadt.sci_interrupt, 9);
+    assert!(fadt.pm_timer_block != 0);
+    assert!(fadt.pm1a_control_block != 0);
+    assert!(fadt.dsdt.as_u64() != 0);
+    assert_eq!(
+        acpi::find_table(b"DSDT"),
+        Err(AcpiError::NotFound(Signature(*b"DSDT")))
+    );
+}
+
+/// Testa a tabela do HPET.
+#[test_case]
+fn hpet_table_parsed() {
+    let hpet = acpi::hpet().unwrap();
+    assert_eq!(hpet.base_address.as_u64(), 0xFED0_0000);
+    assert!(hpet.comparators >= 2);
+}
+
+/// Testa que o MCFG ausente (máquina `pc`, sem PCIe) vira um erro.
+#[test_case]
+fn missing_mcfg_is_an_error() {
+    assert_eq!(acpi::mcfg(), Err(AcpiError::NotFound(Signature::MCFG)));
+}
+
+/// Testa o limite usado para rejeitar tabelas fora da memória mapeada.
+#[test_case]
+fn tables_must_be_in_mapped_memory() {
+    assert!(memory::is_phys_mapped(PhysAddr::new(0xE0000), 0x20000));
+    assert!(memory::is_phys_mapped(acpi::rsdp().unwrap().address, 36));
+    assert!(!memory::is_phys_mapped(PhysAddr::new(1 << 50), 36));
+    assert!(!memory::is_phys_mapped(PhysAddr::new(0x1000), u64::MAX));
+}
+
+/// Testa o resumo impresso no boot.
+#[test_case]
+fn summary_lists_tables() {
+    let summary = format!("{}", acpi::parse().unwrap());
+    assert!(summary.contains("MADT: 2 CPUs"));
+    assert!(summary.contains("FACP at"));
+    assert!(summary.contains("MCFG:"));
+}
+
+/// Ajusta o byte `at` para a soma de `bytes` dar 0 módulo 256.
+fn fix_checksum(bytes: &mut [u8], at: usize) {
+    bytes[at] = 0;
+    let sum = bytes.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte));
+    bytes[at] = sum.wrapping_neg();
+}
+
+/// Monta uma tabela com cabeçalho, `body` e checksum válidos.
+fn table(signature: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![0; 36];
+    bytes[..4].copy_from_slice(signature);
+    bytes[8] = 2;
+    bytes[10..16].copy_from_slice(b"RUSTOS");
+    bytes.extend_from_slice(body);
+    let length = bytes.len() as u32;
+    set_length(&mut bytes, length);
+    bytes
+}
+
+/// Troca o tamanho declarado no cabeçalho, mantendo o checksum válido.
+fn set_length(bytes: &mut [u8], length: u32) {
+    bytes[4..8].copy_from_slice(&length.to_le_bytes());
+    fix_checksum(bytes, 9);
+}
+
+/// Corpo de um MADT: LAPIC em 0xFEE0_0000, PICs presentes, uma CPU e o
+/// override da IRQ 0 (termina no offset 62).
+fn madt_body() -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&0xFEE0_0000u32.to_le_bytes());
+    body.extend_from_slice(&1u32.to_le_bytes());
+    body.extend_from_slice(&[0, 8, 0, 0, 1, 0, 0, 0]);
+    body.extend_from_slice(&[2, 10, 0, 0, 2, 0, 0, 0, 0, 0]);
+    body
+}
+
+/// Testa o parser do MADT com um buffer válido.
+#[test_case]
+fn parse_madt_from_bytes() {
+    let madt = acpi::parse_madt(&table(b"APIC", &madt_body())).unwrap();
+    assert_eq!(madt.local_apic_address.as_u64(), 0xFEE0_0000);
+    assert!(madt.pcat_compatible);
+    assert_eq!(madt.local_apics.len(), 1);
+    assert_eq!(madt.interrupt_overrides[0].gsi, 2);
+}
+
+/// Testa que "online capable" (flags = 2) só inclui a CPU a partir da
+/// revisão 5 do MADT.
+#[test_case]
+fn online_capable_needs_madt_revision_5() {
+    let mut body = madt_body();
+    body[12] = 2;
+    let mut bytes = table(b"APIC", &body);
+    assert!(acpi::parse_madt(&bytes).unwrap().local_apics.is_empty());
+
+    bytes[8] = 5;
+    fix_checksum(&mut bytes, 9);
+    assert_eq!(acpi::parse_madt(&bytes).unwrap().local_apics.len(), 1);
+}
+
+/// Testa que um byte alterado é detectado pelo checksum.
+#[test_case]
+fn corrupted_table_has_bad_checksum() {
+    let mut bytes = table(b"APIC", &madt_body());
+    bytes[50] ^= 0x40;
+    assert_eq!(
+        acpi::parse_madt(&bytes),
+        Err(AcpiError::BadChecksum(Signature::MADT))
+    );
+}
+
+/// Testa tamanhos declarados abaixo do mínimo, além do buffer e buffers
+/// menores que o cabeçalho.
+#[test_case]
+fn bad_lengths_are_rejected() {
+    let mut bytes = table(b"APIC", &madt_body());
+    set_length(&mut bytes, 40);
+    assert_eq!(
+        acpi::parse_madt(&bytes),
+        Err(AcpiError::BadLength {
+            signature: Signature::MADT,
+            length: 40
+        })
+    );
+
+    let mut bytes = table(b"APIC", &madt_body());
+    set_length(&mut bytes, 70);
+    assert_eq!(
+        acpi::parse_madt(&bytes),
+        Err(AcpiError::BadLength {
+            signature: Signature::MADT,
+            length: 70
+        })
+    );
+
+    assert_eq!(
+        acpi::parse_sdt(&[0; 10], Signature::HPET, 36),
+        Err(AcpiError::BadLength {
+            signature: Signature::HPET,
+            length: 10
+        })
+    );
+    assert_eq!(
+        acpi::parse_fadt(&table(b"FACP", &[0; 40])),
+        Err(AcpiError::BadLength {
+            signature: Signature::FADT,
+            length: 76
+        })
+    );
+}
+
+/// Testa que a assinatura precisa ser a esperada.
+#[test_case]
+fn wrong_signature_is_rejected() {
+    assert_eq!(
+        acpi::parse_madt(&table(b"FACP", &madt_body())),
+        Err(AcpiError::BadSignature {
+            expected: Signature::MADT,
+            found: Signature::FADT
+        })
+    );
+}
+
+/// Testa entradas do MADT com tamanho 0 ou que passam do fim da tabela.
+#[test_case]
+fn malformed_madt_entries_are_rejected() {
+    let bad_entry = Err(AcpiError::BadEntry {
+        signature: Signature::MADT,
+        offset: 62,
+    });
+
+    let mut body = madt_body();
+    body.extend_from_slice(&[0x7F, 0]);
+    assert_eq!(acpi::parse_madt(&table(b"APIC", &body)), bad_entry);
+
+    let mut body = madt_body();
+    body.extend_from_slice(&[1, 12, 0, 0]);
+    assert_eq!(acpi::parse_madt(&table(b"APIC", &body)), bad_entry);
+
+    let mut body = madt_body();
+    body.push(0);
+    assert_eq!(acpi::parse_madt(&table(b"APIC", &body)), bad_entry);
+}
+
+/// Testa o MCFG com uma entrada válida e com uma entrada incompleta.
+#[test_case]
+fn mcfg_entries_must_be_complete() {
+    let mut body = vec![0; 8];
+    body.extend_from_slice(&0xB000_0000u64.to_le_bytes());
+    body.extend_from_slice(&[0, 0, 0, 0xFF, 0, 0, 0, 0]);
+    let entries = acpi::parse_mcfg(&table(b"MCFG", &body)).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].base_address.as_u64(), 0xB000_0000);
+    assert_eq!(entries[0].end_bus, 0xFF);
+
+    body.extend_from_slice(&[0; 4]);
+    assert_eq!(
+        acpi::parse_mcfg(&table(b"MCFG", &body)),
+        Err(AcpiError::BadEntry {
+            signature: Signature::MCFG,
+            offset: 60
+        })
+    );
+}
+
+/// Testa o FADT mínimo (ACPI 1.0) a partir de bytes.
+#[test_case]
+fn parse_fadt_from_bytes() {
+    let mut body = vec![0; 80];
+    body[40 - 36..44 - 36].copy_from_slice(&0x1000u32.to_le_bytes());
+    body[46 - 36] = 9;
+    let fadt = acpi::parse_fadt(&table(b"FACP", &body)).unwrap();
+    assert_eq!(fadt.sci_interrupt, 9);
+    assert_eq!(fadt.dsdt.as_u64(), 0x1000);
+    assert_eq!(fadt.reset_register, None);
+}
+
+/// Testa que o HPET em portas de I/O não é aceito.
+#[test_case]
+fn hpet_in_io_space_is_unsupported() {
+    let mut body = vec![0; 20];
+    body[4] = acpi::ADDRESS_SPACE_IO;
+    assert_eq!(
+        acpi::parse_hpet(&table(b"HPET", &body)),
+        Err(AcpiError::UnsupportedAddressSpace {
+            signature: Signature::HPET,
+            space: acpi::ADDRESS_SPACE_IO
+        })
+    );
+}
+
+/// Testa os checksums do RSDP (1.0 e estendido do 2.0) e a assinatura.
+#[test_case]
+fn rsdp_checksums_are_checked() {
+    let address = PhysAddr::new(0xE0000);
+    let mut bytes = [0u8; 36];
+    bytes[..8].copy_from_slice(b"RSD PTR ");
+    bytes[9..15].copy_from_slice(b"RUSTOS");
+    bytes[16..20].copy_from_slice(&0x7FE_0000u32.to_le_bytes());
+    fix_checksum(&mut bytes[..20], 8);
+    let rsdp = acpi::parse_rsdp(address, &bytes[..20]).unwrap();
+    assert_eq!(rsdp.rsdt_address.as_u64(), 0x7FE_0000);
+    assert_eq!(rsdp.xsdt_address, None);
+
+    let mut corrupted = bytes;
+    corrupted[16] ^= 1;
+    assert_eq!(
+        acpi::parse_rsdp(address, &corrupted[..20]),
+        Err(AcpiError::BadRsdpChecksum)
+    );
+
+    // Revisão 2 com o primeiro checksum certo e o estendido errado
+    bytes[15] = 2;
+    bytes[20] = 36;
+    fix_checksum(&mut bytes[..20], 8);
+    bytes[35] = 1;
+    assert_eq!(
+        acpi::parse_rsdp(address, &bytes),
+        Err(AcpiError::BadRsdpChecksum)
+    );
+
+    assert_eq!(
+        acpi::parse_rsdp(address, b"RSD PTX \0\0\0\0\0\0\0\0\0\0\0\0"),
+        Err(AcpiError::NoRsdp)
+    );
+}
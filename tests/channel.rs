@@ -0,0 +1,255 @@
+//! Testes de integração para os canais de `task::channel`.
+
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(rust_os::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::{boxed::Box, rc::Rc, vec::Vec};
+use bootloader::{entry_point, BootInfo};
+use core::{
+    cell::{Cell, RefCell},
+    future::{poll_fn, Future},
+    panic::PanicInfo,
+};
+use futures_util::{SinkExt, StreamExt};
+use rust_os::{
+    allocator,
+    memory::{self, BootInfoFrameAllocator},
+    task::{
+        channel::{broadcast, mpsc, oneshot, TrySendError},
+        executor::Executor,
+    },
+};
+use x86_64::VirtAddr;
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    rust_os::init();
+
+    let phys_mem_offset = VirtAddr::new(boot_info.physical_memory_offset);
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+
+    test_main();
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    rust_os::test_panic_handler(info)
+}
+
+/// Testa que o canal limitado faz o sender esperar por vagas.
+#[test_case]
+fn bounded_mpsc_applies_backpressure() {
+    let mut executor = Executor::new();
+    let (mut sender, mut receiver) = mpsc::channel(2);
+    let sent = Rc::new(RefCell::new(0));
+
+    let counter = sent.clone();
+    executor.spawn(async move {
+        for value in 0..5 {
+            sender.send(value).await.unwrap();
+            *counter.borrow_mut() += 1;
+        }
+    });
+    executor.run_until_idle();
+    assert_eq!(*sent.borrow(), 2);
+
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let output = received.clone();
+    executor.spawn(async move {
+        while let Some(value) = receiver.recv().await {
+            output.borrow_mut().push(value);
+        }
+    });
+    executor.run_until_idle();
+
+    // O canal fechou quando o sender terminou e foi descartado
+    assert_eq!(*received.borrow(), [0, 1, 2, 3, 4]);
+    assert_eq!(executor.task_count(), 0);
+}
+
+/// Testa `try_send` (como num handler de interrupção) e o fechamento.
+#[test_case]
+fn try_send_reports_full_and_closed() {
+    let (sender, mut receiver) = mpsc::channel(1);
+
+    assert_eq!(sender.try_send(1), Ok(()));
+    assert_eq!(sender.try_send(2), Err(TrySendError::Full(2)));
+    assert_eq!(receiver.try_recv(), Ok(1));
+    drop(receiver);
+    assert_eq!(sender.try_send(3), Err(TrySendError::Closed(3)));
+}
+
+/// Testa que cada vaga liberada acorda um único sender, na ordem da espera.
+#[test_case]
+fn freed_slot_wakes_one_sender() {
+    let mut executor = Executor::new();
+    let (sender, mut receiver) = mpsc::channel(1);
+    let polls = Rc::new(Cell::new(0));
+    sender.try_send(0).unwrap();
+
+    for value in 1..4 {
+        let mut sender = sender.clone();
+        let polls = polls.clone();
+        executor.spawn(async move {
+            let mut send = Box::pin(sender.send(value));
+            poll_fn(|cx| {
+                polls.set(polls.get() + 1);
+                send.as_mut().poll(cx)
+            })
+            .await
+            .unwrap();
+        });
+    }
+    executor.run_until_idle();
+    assert_eq!(polls.get(), 3);
+
+    for expected in 0..3 {
+        assert_eq!(receiver.try_recv(), Ok(expected));
+        executor.run_until_idle();
+        // Só o sender acordado foi executado de novo
+        assert_eq!(polls.get(), 4 + expected);
+    }
+    assert_eq!(receiver.try_recv(), Ok(3));
+    assert_eq!(executor.task_count(), 0);
+}
+
+/// Testa que um sender acordado que perde a vaga para um `try_send`
+/// continua sendo o primeiro da fila.
+#[test_case]
+fn woken_sender_keeps_its_turn() {
+    let mut executor = Executor::new();
+    let (sender, mut receiver) = mpsc::channel(1);
+    sender.try_send(0).unwrap();
+
+    for value in 1..3 {
+        let mut sender = sender.clone();
+        executor.spawn(async move { sender.send(value).await.unwrap() });
+    }
+    executor.run_until_idle();
+
+    // A vaga liberada acorda o sender 1, mas o `try_send` a ocupa antes
+    assert_eq!(receiver.try_recv(), Ok(0));
+    sender.try_send(10).unwrap();
+    executor.run_until_idle();
+
+    assert_eq!(receiver.try_recv(), Ok(10));
+    executor.run_until_idle();
+    assert_eq!(receiver.try_recv(), Ok(1));
+    executor.run_until_idle();
+    assert_eq!(receiver.try_recv(), Ok(2));
+    assert_eq!(executor.task_count(), 0);
+}
+
+/// Testa o canal sem limite usando `Sink` e `Stream`.
+#[test_case]
+fn unbounded_mpsc_sink_and_stream() {
+    let mut executor = Executor::new();
+    let (sender, receiver) = mpsc::unbounded();
+    let sum = Rc::new(RefCell::new(0));
+
+    for offset in [0, 100] {
+        let mut sender = sender.clone();
+        executor.spawn(async move {
+            for value in 1..=3 {
+                sender.send(offset + value).await.unwrap();
+            }
+            SinkExt::close(&mut sender).await.unwrap();
+        });
+    }
+    drop(sender);
+    let output = sum.clone();
+    executor.spawn(async move {
+        *output.borrow_mut() = receiver.fold(0, |sum, value| async move { sum + value }).await;
+    });
+    executor.run_until_idle();
+
+    assert_eq!(*sum.borrow(), 312);
+}
+
+/// Testa o envio de uma resposta por `oneshot`.
+#[test_case]
+fn oneshot_delivers_single_value() {
+    let mut executor = Executor::new();
+    let result = Rc::new(RefCell::new(None));
+
+    let (reply, response) = oneshot::channel();
+    let output = result.clone();
+    executor.spawn(async move {
+        *output.borrow_mut() = Some(response.await);
+    });
+    executor.run_until_idle();
+    assert_eq!(*result.borrow(), None);
+
+    reply.send(7).unwrap();
+    executor.run_until_idle();
+    assert_eq!(*result.borrow(), Some(Ok(7)));
+
+    let (reply, response) = oneshot::channel::<u32>();
+    drop(reply);
+    let output = result.clone();
+    executor.spawn(async move {
+        *output.borrow_mut() = Some(response.await);
+    });
+    executor.run_until_idle();
+    assert_eq!(*result.borrow(), Some(Err(oneshot::RecvError)));
+}
+
+/// Testa que cada receiver de `broadcast` vê todas as mensagens e que
+/// receivers atrasados são avisados das perdidas.
+#[test_case]
+fn broadcast_fans_out_and_reports_lag() {
+    let mut executor = Executor::new();
+    let (sender, mut first) = broadcast::channel(4);
+    let mut second = sender.subscribe();
+    let seen = Rc::new(RefCell::new(Vec::new()));
+
+    let output = seen.clone();
+    executor.spawn(async move {
+        while let Ok(value) = first.recv().await {
+            output.borrow_mut().push(value);
+        }
+    });
+    executor.run_until_idle();
+
+    for batch in [0..3, 3..6] {
+        for value in batch {
+            assert_eq!(sender.send(value), Ok(2));
+        }
+        executor.run_until_idle();
+    }
+    assert_eq!(*seen.borrow(), [0, 1, 2, 3, 4, 5]);
+
+    // `second` não leu nada: as 2 mais antigas foram descartadas
+    assert_eq!(second.try_recv(), Err(broadcast::RecvError::Lagged(2)));
+    assert_eq!(second.try_recv(), Ok(Some(2)));
+
+    drop(sender);
+    executor.run_until_idle();
+    assert_eq!(executor.task_count(), 0);
+    let rest: Vec<_> = collect_remaining(&mut executor, second);
+    assert_eq!(rest, [Ok(3), Ok(4), Ok(5)]);
+}
+
+/// Coleta um stream de `broadcast` até o canal fechar.
+fn collect_remaining(
+    executor: &mut Executor,
+    receiver: broadcast::Receiver<i32>,
+) -> Vec<Result<i32, broadcast::RecvError>> {
+    let items = Rc::new(RefCell::new(Vec::new()));
+    let output = items.clone();
+    executor.spawn(async move {
+        *output.borrow_mut() = receiver.collect().await;
+    });
+    executor.run_until_idle();
+    items.take()
+}
@@ -0,0 +1,95 @@
+//! Testes de integração para o trabalho adiado (`interrupts::deferred`).
+//!
+//! Ficam fora da lib porque a fila é uma `ArrayQueue` alocada no heap. A
+//! task consumidora só pode ser iniciada uma vez, então os testes dependem
+//! da ordem em que aparecem neste arquivo.
+
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(rust_os::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use bootloader::{entry_point, BootInfo};
+use core::{
+    panic::PanicInfo,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use rust_os::{
+    allocator,
+    interrupts::deferred::{self, WorkItem},
+    memory::{self, BootInfoFrameAllocator},
+    task::executor::Executor,
+};
+use x86_64::VirtAddr;
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    rust_os::init();
+
+    let phys_mem_offset = VirtAddr::new(boot_info.physical_memory_offset);
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+
+    test_main();
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    rust_os::test_panic_handler(info)
+}
+
+/// Dígitos dos itens executados, na ordem de execução (ex.: 123).
+static ORDER: AtomicUsize = AtomicUsize::new(0);
+
+fn record(digit: usize) {
+    let order = ORDER.load(Ordering::Relaxed);
+    ORDER.store(order * 10 + digit, Ordering::Relaxed);
+}
+
+fn noop(_arg: usize) {}
+
+/// Testa que itens enfileirados antes da task consumidora são recusados.
+#[test_case]
+fn schedule_before_consumer_fails() {
+    let dropped = deferred::dropped();
+    assert!(deferred::schedule(WorkItem::new(noop, 0)).is_err());
+    assert_eq!(deferred::dropped(), dropped + 1);
+}
+
+/// Testa que a task executa os itens na ordem em que foram enfileirados.
+#[test_case]
+fn work_runs_in_schedule_order() {
+    let mut executor = Executor::new();
+    executor.spawn(deferred::process_deferred_work());
+    // Primeiro poll: inicializa a fila e registra o waker
+    executor.run_until_idle();
+
+    for digit in 1..=3 {
+        deferred::schedule(WorkItem::new(record, digit)).unwrap();
+    }
+    assert_eq!(ORDER.load(Ordering::Relaxed), 0);
+    executor.run_until_idle();
+    assert_eq!(ORDER.load(Ordering::Relaxed), 123);
+}
+
+/// Testa que a fila cheia devolve o item e incrementa o contador de descartes.
+#[test_case]
+fn full_queue_drops_work() {
+    // Sem executor: ninguém consome a fila deixada pelo teste anterior
+    let dropped = deferred::dropped();
+    let mut accepted = 0;
+    while deferred::schedule(WorkItem::new(noop, accepted)).is_ok() {
+        accepted += 1;
+        assert!(accepted <= 1024, "queue never filled up");
+    }
+
+    assert!(accepted > 0);
+    assert_eq!(deferred::dropped(), dropped + 1);
+}
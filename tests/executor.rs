@@ -0,0 +1,454 @@
+//! Testes de integração para o executor de tasks assíncronas.
+
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(rust_os::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::{rc::Rc, string::String, sync::Arc, task::Wake, vec::Vec};
+use bootloader::{entry_point, BootInfo};
+use core::{
+    cell::RefCell,
+    future::{self, Future},
+    panic::PanicInfo,
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{Context, Poll, Waker},
+};
+use rust_os::{
+    allocator,
+    memory::{self, BootInfoFrameAllocator},
+    task::{
+        self, coop, executor::Executor, info, keyboard, slow_poll, Builder, CancellationToken,
+        JoinError, Priority, TaskState,
+    },
+    time,
+};
+use x86_64::VirtAddr;
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    rust_os::init();
+
+    let phys_mem_offset = VirtAddr::new(boot_info.physical_memory_offset);
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+
+    test_main();
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    rust_os::test_panic_handler(info)
+}
+
+/// Testa que o JoinHandle resolve para o resultado da task.
+#[test_case]
+fn join_handle_returns_result() {
+    let mut executor = Executor::new();
+    let result = Rc::new(RefCell::new(None));
+
+    let handle = executor.spawn(async { 6 * 7 });
+    let output = result.clone();
+    executor.spawn(async move {
+        *output.borrow_mut() = Some(handle.await.unwrap());
+    });
+    executor.run_until_idle();
+
+    assert_eq!(*result.borrow(), Some(42));
+}
+
+/// Testa que tasks aguardam umas às outras em cadeia.
+#[test_case]
+fn join_handles_chain() {
+    let mut executor = Executor::new();
+    let order = Rc::new(RefCell::new(Vec::new()));
+
+    let first_order = order.clone();
+    let first = executor.spawn(async move {
+        first_order.borrow_mut().push(1);
+        1
+    });
+    let second_order = order.clone();
+    let second = executor.spawn(async move {
+        let value = first.await.unwrap();
+        second_order.borrow_mut().push(2);
+        value + 1
+    });
+    let final_order = order.clone();
+    executor.spawn(async move {
+        let value = second.await.unwrap();
+        final_order.borrow_mut().push(value * 10);
+    });
+    executor.run_until_idle();
+
+    assert_eq!(*order.borrow(), [1, 2, 20]);
+}
+
+/// Testa que uma task desacoplada continua rodando.
+#[test_case]
+fn detached_task_still_runs() {
+    let mut executor = Executor::new();
+    let ran = Rc::new(RefCell::new(false));
+
+    let flag = ran.clone();
+    let handle = executor.spawn(async move {
+        *flag.borrow_mut() = true;
+    });
+    handle.detach();
+    executor.run_until_idle();
+
+    assert!(*ran.borrow());
+}
+
+/// Testa que uma task em execução cria outras tasks via `task::spawn`.
+#[test_case]
+fn task_spawns_tasks() {
+    static SUM: AtomicU64 = AtomicU64::new(0);
+    let mut executor = Executor::new();
+
+    executor.spawn(async {
+        let handles: Vec<_> = (1..=4)
+            .map(|n| task::spawn(async move { n * 10 }))
+            .collect();
+        for handle in handles {
+            SUM.fetch_add(handle.await.unwrap(), Ordering::Relaxed);
+        }
+    });
+    executor.run_until_idle();
+
+    assert_eq!(SUM.load(Ordering::Relaxed), 100);
+}
+
+/// Testa que um `Spawner` clonado agenda tasks fora do executor.
+#[test_case]
+fn spawner_queues_tasks() {
+    static RAN: AtomicU64 = AtomicU64::new(0);
+    let mut executor = Executor::new();
+    let spawner = executor.spawner();
+
+    let nested = spawner.clone();
+    spawner.spawn(async move {
+        RAN.fetch_add(1, Ordering::Relaxed);
+        nested.spawn(async {
+            RAN.fetch_add(1, Ordering::Relaxed);
+        });
+    });
+    assert_eq!(RAN.load(Ordering::Relaxed), 0);
+    executor.run_until_idle();
+
+    assert_eq!(RAN.load(Ordering::Relaxed), 2);
+}
+
+/// Marca uma flag ao ser descartado.
+struct DropFlag(&'static AtomicU64);
+
+impl Drop for DropFlag {
+    fn drop(&mut self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Testa que `abort()` descarta o future e notifica quem aguarda.
+#[test_case]
+fn abort_drops_future_and_notifies_joiner() {
+    static DROPPED: AtomicU64 = AtomicU64::new(0);
+    let mut executor = Executor::new();
+    let result = Rc::new(RefCell::new(None));
+
+    let handle = executor.spawn(async {
+        let _flag = DropFlag(&DROPPED);
+        future::pending::<()>().await;
+    });
+    let abort = handle.abort_handle();
+    let output = result.clone();
+    executor.spawn(async move {
+        *output.borrow_mut() = Some(handle.await);
+    });
+    executor.run_until_idle();
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 0);
+
+    abort.abort();
+    executor.run_until_idle();
+
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 1);
+    assert_eq!(*result.borrow(), Some(Err(JoinError::Cancelled)));
+    assert_eq!(executor.task_count(), 0);
+}
+
+/// Testa que a task do teclado pode ser cancelada e criada de novo.
+#[test_case]
+fn keypresses_task_is_cancellable() {
+    let mut executor = Executor::new();
+
+    for _ in 0..2 {
+        let handle = executor.spawn(keyboard::print_keypresses());
+        executor.run_until_idle();
+        assert!(!handle.is_finished());
+
+        handle.abort();
+        executor.run_until_idle();
+        assert!(handle.is_finished());
+    }
+    assert_eq!(executor.task_count(), 0);
+}
+
+/// Testa o cancelamento cooperativo com `CancellationToken`.
+#[test_case]
+fn cancellation_token_stops_tasks() {
+    static STOPPED: AtomicU64 = AtomicU64::new(0);
+    let mut executor = Executor::new();
+    let token = CancellationToken::new();
+
+    for _ in 0..3 {
+        let token = token.clone();
+        executor.spawn(async move {
+            token.cancelled().await;
+            STOPPED.fetch_add(1, Ordering::Relaxed);
+        });
+    }
+    let worker = token.clone();
+    let finished = executor.spawn(async move {
+        worker.run_until_cancelled(future::pending::<()>()).await
+    });
+    executor.run_until_idle();
+    assert_eq!(STOPPED.load(Ordering::Relaxed), 0);
+
+    token.cancel();
+    executor.run_until_idle();
+
+    assert_eq!(STOPPED.load(Ordering::Relaxed), 3);
+    assert!(finished.is_finished());
+    assert_eq!(executor.task_count(), 0);
+}
+
+/// Testa que descartar `cancelled()` remove o waker registrado no token.
+#[test_case]
+fn dropped_cancelled_future_releases_waker() {
+    struct NoopWake;
+    impl Wake for NoopWake {
+        fn wake(self: Arc<Self>) {}
+    }
+    let wake = Arc::new(NoopWake);
+    let waker = Waker::from(wake.clone());
+    let mut cx = Context::from_waker(&waker);
+    let token = CancellationToken::new();
+
+    for _ in 0..3 {
+        let mut cancelled = token.cancelled();
+        assert!(Pin::new(&mut cancelled).poll(&mut cx).is_pending());
+        // `wake`, `waker` e o clone guardado pelo token
+        assert_eq!(Arc::strong_count(&wake), 3);
+    }
+    assert_eq!(Arc::strong_count(&wake), 2);
+}
+
+/// Testa nome, local de criação, estado e contagem de polls em `snapshot`.
+#[test_case]
+fn snapshot_tracks_task_state() {
+    let mut executor = Executor::new();
+    let gate = CancellationToken::new();
+
+    let waiter = gate.clone();
+    let handle = Builder::new()
+        .name("worker")
+        .priority(Priority::Background)
+        .spawn_on(&mut executor, async move { waiter.cancelled().await });
+    let id = handle.id();
+    let find = |executor: &Executor| {
+        executor.snapshot().into_iter().find(|t| t.id == id).expect("task missing from snapshot")
+    };
+
+    let info = find(&executor);
+    assert_eq!(info.name, Some("worker"));
+    assert_eq!(info.priority, Priority::Background);
+    assert_eq!(info.state, TaskState::Queued);
+    assert_eq!(info.polls, 0);
+    assert!(info.location.file().ends_with("executor.rs"));
+
+    executor.run_until_idle();
+    let info = find(&executor);
+    assert_eq!(info.state, TaskState::Pending);
+    assert_eq!(info.polls, 1);
+
+    gate.cancel();
+    let info = find(&executor);
+    assert_eq!(info.state, TaskState::Queued);
+
+    executor.run_until_idle();
+    let info = find(&executor);
+    assert_eq!(info.state, TaskState::Completed);
+    assert_eq!(info.polls, 2);
+    assert!(info.poll_cycles > 0);
+
+    let mut table = String::new();
+    info::write_task_table(&mut table, &executor.snapshot()).unwrap();
+    assert!(table.contains("worker"));
+    assert!(table.contains("completed"));
+}
+
+/// Testa que um poll acima do limite é contado e registrado entre os
+/// mais longos.
+#[test_case]
+fn slow_poll_is_detected() {
+    if time::tsc_frequency().is_none() {
+        time::calibrate_tsc();
+    }
+    let busy_cycles = time::us_to_cycles(5_000).unwrap();
+    let previous_threshold = slow_poll::threshold_us();
+    slow_poll::set_threshold_us(1_000);
+    let slow_before = slow_poll::slow_poll_count();
+
+    let mut executor = Executor::new();
+    let handle = Builder::new().name("busy").spawn_on(&mut executor, async move {
+        let start = time::rdtsc();
+        while time::rdtsc() - start < busy_cycles {
+            core::hint::spin_loop();
+        }
+    });
+    let id = handle.id();
+    executor.spawn(async {});
+    executor.run_until_idle();
+    slow_poll::set_threshold_us(previous_threshold);
+
+    assert_eq!(slow_poll::slow_poll_count(), slow_before + 1);
+    let longest = slow_poll::longest_polls();
+    let entry = longest.iter().find(|poll| poll.id == id).expect("slow poll not recorded");
+    assert_eq!(entry.name, Some("busy"));
+    assert!(entry.cycles >= busy_cycles);
+    assert!(longest.windows(2).all(|pair| pair[0].cycles >= pair[1].cycles));
+
+    let info = executor.snapshot().into_iter().find(|t| t.id == id).unwrap();
+    assert_eq!(info.max_poll_cycles, entry.cycles);
+}
+
+/// Testa que tasks de maior prioridade rodam primeiro.
+#[test_case]
+fn higher_priority_runs_first() {
+    let mut executor = Executor::new();
+    let order = Rc::new(RefCell::new(Vec::new()));
+
+    for (priority, id) in [
+        (Priority::Background, 3),
+        (Priority::Normal, 2),
+        (Priority::High, 1),
+    ] {
+        let order = order.clone();
+        executor.spawn_with_priority(priority, async move {
+            order.borrow_mut().push(id);
+        });
+    }
+    executor.run_until_idle();
+
+    assert_eq!(*order.borrow(), [1, 2, 3]);
+}
+
+/// Testa que `yield_now` alterna entre tasks de mesma prioridade e que
+/// uma task `High` criada no meio passa à frente.
+#[test_case]
+fn yield_now_interleaves_tasks() {
+    static ORDER: spin::Mutex<Vec<u32>> = spin::Mutex::new(Vec::new());
+    let mut executor = Executor::new();
+
+    executor.spawn(async {
+        ORDER.lock().push(1);
+        task::yield_now().await;
+        task::spawn_with_priority(Priority::High, async {
+            ORDER.lock().push(10);
+        });
+        task::yield_now().await;
+        ORDER.lock().push(3);
+    });
+    executor.spawn(async {
+        ORDER.lock().push(2);
+        task::yield_now().await;
+        ORDER.lock().push(4);
+    });
+    executor.run_until_idle();
+
+    assert_eq!(*ORDER.lock(), [1, 2, 10, 4, 3]);
+}
+
+/// Testa que o orçamento por poll força a task a ceder.
+#[test_case]
+fn poll_budget_forces_yield() {
+    let mut executor = Executor::new();
+    let polls = Rc::new(RefCell::new(Vec::new()));
+
+    let record = polls.clone();
+    executor.spawn(future::poll_fn(move |cx| {
+        let mut proceeded = 0;
+        while coop::poll_proceed(cx).is_ready() {
+            proceeded += 1;
+            if proceeded > coop::POLL_BUDGET {
+                break;
+            }
+        }
+        record.borrow_mut().push(proceeded);
+        if record.borrow().len() == 2 {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }));
+    executor.run_until_idle();
+
+    // Com o orçamento esgotado a task se acordou e rodou de novo
+    assert_eq!(*polls.borrow(), [coop::POLL_BUDGET, coop::POLL_BUDGET]);
+    assert_eq!(executor.task_count(), 0);
+}
+
+/// Testa milhares de tasks (em ondas maiores que a antiga fila de 100)
+/// com wakes repetidos: nenhum wake causa panic e wakes redundantes são
+/// coalescidos em um único poll.
+#[test_case]
+fn stress_many_tasks_with_redundant_wakes() {
+    const WAVES: u64 = 16;
+    const TASKS_PER_WAVE: u64 = 128;
+    const ROUNDS: u64 = 3;
+    const WAKES_PER_ROUND: usize = 50;
+
+    let mut executor = Executor::new();
+    let wakers: Rc<RefCell<Vec<Waker>>> = Rc::new(RefCell::new(Vec::new()));
+    let round = Rc::new(RefCell::new(0));
+    let polls = Rc::new(RefCell::new(0));
+
+    for _ in 0..WAVES {
+        *round.borrow_mut() = 0;
+        for _ in 0..TASKS_PER_WAVE {
+            let (wakers, round, polls) = (wakers.clone(), round.clone(), polls.clone());
+            executor
+                .spawn(future::poll_fn(move |cx| {
+                    *polls.borrow_mut() += 1;
+                    if *round.borrow() == ROUNDS {
+                        return Poll::Ready(());
+                    }
+                    wakers.borrow_mut().push(cx.waker().clone());
+                    Poll::Pending
+                }))
+                .detach();
+        }
+        *polls.borrow_mut() = 0;
+        executor.run_until_idle();
+        assert_eq!(*polls.borrow(), TASKS_PER_WAVE);
+
+        for current in 1..=ROUNDS {
+            *round.borrow_mut() = current;
+            *polls.borrow_mut() = 0;
+            let pending: Vec<Waker> = wakers.borrow_mut().drain(..).collect();
+            for _ in 0..WAKES_PER_ROUND {
+                pending.iter().for_each(Waker::wake_by_ref);
+            }
+            executor.run_until_idle();
+            assert_eq!(*polls.borrow(), TASKS_PER_WAVE);
+        }
+        assert_eq!(executor.task_count(), 0);
+    }
+}
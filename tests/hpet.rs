@@ -0,0 +1,180 @@
+//! Testes de integração para o HPET e as calibrações feitas com ele.
+//!
+//! Os comparadores entregam interrupções por FSB, que o QEMU só anuncia
+//! com `-global hpet.msi=on`. Sem FSB, os testes de comparadores são
+//! pulados.
+
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(rust_os::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use bootloader::{entry_point, BootInfo};
+use core::{
+    panic::PanicInfo,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use rust_os::{
+    allocator, apic,
+    hpet::{self, Comparator, HpetError},
+    memory::{self, BootInfoFrameAllocator},
+    serial_print, time,
+};
+use x86_64::{structures::idt::InterruptStackFrame, VirtAddr};
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    rust_os::init();
+
+    let phys_mem_offset = VirtAddr::new(boot_info.physical_memory_offset);
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+    memory::set_kernel_space(mapper, frame_allocator);
+    apic::init().expect("Local APIC initialization failed");
+    hpet::init().expect("HPET initialization failed");
+    time::calibrate_tsc();
+
+    test_main();
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    rust_os::test_panic_handler(info)
+}
+
+/// Espera até `done` ou falha depois de `timeout_us` (medido pelo HPET).
+fn wait_for(timeout_us: u64, done: impl Fn() -> bool) {
+    let start = hpet::counter();
+    while !done() {
+        assert!(
+            hpet::elapsed(start) < hpet::ns_to_ticks(timeout_us * 1000),
+            "timed out"
+        );
+        core::hint::spin_loop();
+    }
+}
+
+/// Reivindica um comparador, ou `None` se nenhum tem FSB (o teste é
+/// pulado).
+fn claim_or_skip(handler: fn(&InterruptStackFrame)) -> Option<Comparator> {
+    match Comparator::claim(handler) {
+        Ok(comparator) => Some(comparator),
+        Err(HpetError::FsbUnsupported) => {
+            serial_print!("(no FSB comparator, skipped) ");
+            None
+        }
+        Err(err) => panic!("claim failed: {:?}", err),
+    }
+}
+
+/// Testa que o HPET tem uma frequência válida e comparadores.
+#[test_case]
+fn hpet_is_present() {
+    let hz = hpet::frequency().unwrap();
+    assert!(hz >= 10_000_000, "HPET at {} Hz", hz);
+    assert!(hpet::comparator_count() >= 2);
+}
+
+/// Testa que o contador avança e que `delay_us` espera o tempo pedido.
+#[test_case]
+fn counter_advances() {
+    let start = hpet::counter();
+    hpet::delay_us(1000);
+    let elapsed_ns = hpet::ticks_to_ns(hpet::elapsed(start));
+    assert!(
+        elapsed_ns >= 1_000_000 && elapsed_ns < 5_000_000,
+        "{} ns",
+        elapsed_ns
+    );
+}
+
+/// Testa que o TSC calibrado pelo HPET concorda com o próprio HPET.
+#[test_case]
+fn tsc_calibrated_against_hpet() {
+    let start = hpet::counter();
+    let tsc = time::rdtsc();
+    hpet::delay_us(5000);
+    let hpet_us = hpet::ticks_to_ns(hpet::elapsed(start)) / 1000;
+    let tsc_us = time::cycles_to_us(time::rdtsc() - tsc).unwrap();
+    assert!(
+        tsc_us.abs_diff(hpet_us) < 500,
+        "TSC {} us, HPET {} us",
+        tsc_us,
+        hpet_us
+    );
+}
+
+/// Testa a calibração do timer do APIC.
+#[test_case]
+fn apic_timer_calibrated() {
+    let hz = apic::calibrate_timer().unwrap();
+    assert!(hz > 0);
+    assert_eq!(apic::timer_frequency(), Some(hz));
+}
+
+static ONESHOT_FIRED: AtomicU64 = AtomicU64::new(0);
+
+fn oneshot_handler(_stack_frame: &InterruptStackFrame) {
+    ONESHOT_FIRED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Testa que um comparador one-shot dispara uma única vez.
+#[test_case]
+fn oneshot_comparator_fires_once() {
+    let comparator = match claim_or_skip(oneshot_handler) {
+        Some(comparator) => comparator,
+        None => return,
+    };
+    comparator.oneshot_ns(1_000_000);
+    wait_for(100_000, || ONESHOT_FIRED.load(Ordering::Relaxed) == 1);
+    hpet::delay_us(5000);
+    assert_eq!(ONESHOT_FIRED.load(Ordering::Relaxed), 1);
+    assert_eq!(comparator.fired(), 1);
+}
+
+fn periodic_handler(_stack_frame: &InterruptStackFrame) {}
+
+/// Testa um comparador periódico e que `stop` o para.
+#[test_case]
+fn periodic_comparator_fires_repeatedly() {
+    let comparator = match claim_or_skip(periodic_handler) {
+        Some(comparator) => comparator,
+        None => return,
+    };
+    assert!(comparator.supports_periodic());
+    comparator.periodic_ns(1_000_000).unwrap();
+    wait_for(100_000, || comparator.fired() >= 5);
+    comparator.stop();
+    hpet::delay_us(2000);
+    let fired = comparator.fired();
+    hpet::delay_us(5000);
+    assert_eq!(comparator.fired(), fired);
+}
+
+/// Testa que os comparadores são exclusivos até o drop.
+#[test_case]
+fn comparators_are_exclusive() {
+    let mut claimed = match claim_or_skip(periodic_handler) {
+        Some(comparator) => alloc::vec![comparator],
+        None => return,
+    };
+    loop {
+        match Comparator::claim(periodic_handler) {
+            Ok(comparator) => claimed.push(comparator),
+            Err(err) => {
+                assert_eq!(err, HpetError::NoFreeComparator);
+                break;
+            }
+        }
+    }
+    let index = claimed[0].index();
+    claimed.clear();
+    assert_eq!(Comparator::claim(periodic_handler).unwrap().index(), index);
+}
@@ -0,0 +1,62 @@
+//! Teste de integração: pegar de novo um `IrqSpinlock` na mesma CPU entra
+//! em panic em vez de travar, e o panic handler consegue imprimir mesmo
+//! com `SERIAL1` preso.
+
+#![no_std]
+#![no_main]
+
+use core::{
+    fmt::{self, Write},
+    panic::PanicInfo,
+};
+use rust_os::{exit_qemu, serial, serial::SERIAL1, serial_print, QemuExitCode};
+
+#[unsafe(no_mangle)]
+pub extern "C" fn _start() -> ! {
+    rust_os::init();
+    serial_print!("lock_reentrancy::lock_twice...\t");
+
+    let _first = SERIAL1.lock();
+    let _second = SERIAL1.lock();
+
+    let _ = writeln!(serial::lock_for_panic(), "[test did not panic]");
+    exit_qemu(QemuExitCode::Failed);
+    loop {}
+}
+
+/// Guarda o começo da mensagem de panic (sem heap).
+struct MessageBuffer {
+    bytes: [u8; 256],
+    len: usize,
+}
+
+impl Write for MessageBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let n = s.len().min(self.bytes.len() - self.len);
+        self.bytes[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        Ok(())
+    }
+}
+
+/// Panic handler customizado - o teste DEVE entrar em panic por deadlock.
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    let mut message = MessageBuffer {
+        bytes: [0; 256],
+        len: 0,
+    };
+    let _ = write!(message, "{}", info.message());
+    let message = core::str::from_utf8(&message.bytes[..message.len]).unwrap_or("");
+
+    // SERIAL1 continua preso pelo `_first` de `_start`
+    let mut serial = serial::lock_for_panic();
+    if message.starts_with("deadlock: lock `SERIAL1` acquired again") {
+        let _ = writeln!(serial, "[ok]");
+        exit_qemu(QemuExitCode::Success);
+    } else {
+        let _ = writeln!(serial, "[failed]\n\nunexpected panic: {}", message);
+        exit_qemu(QemuExitCode::Failed);
+    }
+    loop {}
+}
@@ -0,0 +1,265 @@
+//! Testes de integração para o boot das application processors.
+//!
+//! Os testes rodam com `-smp 2` (ver `test-args` no `Cargo.toml`).
+
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(rust_os::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use bootloader::{entry_point, BootInfo};
+use core::{
+    panic::PanicInfo,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+};
+use rust_os::{
+    acpi, allocator, apic,
+    ipi::{self, Target},
+    memory::{self, tlb::TlbShootdown, BootInfoFrameAllocator},
+    percpu, smp,
+    spinlock::IrqSpinlock,
+    task::{self, MulticoreExecutor},
+    thread, time,
+};
+use x86_64::{
+    structures::paging::{FrameAllocator, Mapper, Page, PageTableFlags, PhysFrame, Size4KiB},
+    VirtAddr,
+};
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    rust_os::init();
+
+    let phys_mem_offset = VirtAddr::new(boot_info.physical_memory_offset);
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+    memory::set_kernel_space(mapper, frame_allocator);
+    thread::init().expect("thread initialization failed");
+    apic::init().expect("Local APIC initialization failed");
+    time::calibrate_tsc();
+    smp::init().expect("SMP initialization failed");
+
+    test_main();
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    rust_os::test_panic_handler(info)
+}
+
+/// Testa que o MADT lista a BSP.
+#[test_case]
+fn madt_lists_bsp() {
+    let madt = acpi::madt().expect("MADT not found");
+    assert!(madt.local_apics.iter().any(|cpu| cpu.apic_id == apic::id()));
+}
+
+/// Testa que todas as CPUs do MADT ficaram online.
+#[test_case]
+fn all_cpus_online() {
+    let cpus = smp::cpus();
+    assert!(cpus.len() >= 2);
+    assert!(cpus.iter().all(|cpu| cpu.online && !cpu.failed));
+    assert_eq!(smp::cpu_count(), cpus.len());
+}
+
+/// Testa que a BSP é a CPU 0.
+#[test_case]
+fn bsp_is_cpu_zero() {
+    assert_eq!(smp::current_cpu(), 0);
+    assert_eq!(smp::cpus()[0].apic_id, apic::id());
+}
+
+/// Testa que as tasks do executor multi-core rodam em todas as CPUs.
+#[test_case]
+fn multicore_uses_every_cpu() {
+    static CPUS_SEEN: AtomicU64 = AtomicU64::new(0);
+
+    let executor = MulticoreExecutor::new();
+    let busy_cycles = time::us_to_cycles(2_000).unwrap();
+    for _ in 0..64 {
+        executor.spawn(async move {
+            CPUS_SEEN.fetch_or(1 << percpu::cpu_index(), Ordering::Relaxed);
+            let start = time::rdtsc();
+            while time::rdtsc() - start < busy_cycles {
+                core::hint::spin_loop();
+            }
+        });
+    }
+    executor.run_until_complete();
+
+    assert_eq!(executor.task_count(), 0);
+    let seen = CPUS_SEEN.load(Ordering::Relaxed);
+    assert_eq!(seen.count_ones() as usize, smp::cpu_count());
+    let polls: u64 = executor
+        .worker_stats()
+        .iter()
+        .map(|worker| worker.polls)
+        .sum();
+    assert!(polls >= 64);
+}
+
+/// Testa que tasks criadas num worker (fila local dele) são roubadas e
+/// executadas pelas outras CPUs.
+#[test_case]
+fn multicore_steals_local_work() {
+    static PARENT_CPU: AtomicU64 = AtomicU64::new(0);
+    static CPUS_SEEN: AtomicU64 = AtomicU64::new(0);
+
+    let executor = MulticoreExecutor::new();
+    let spawner = executor.clone();
+    let busy_cycles = time::us_to_cycles(2_000).unwrap();
+    executor.spawn(async move {
+        PARENT_CPU.store(percpu::cpu_index() as u64, Ordering::Relaxed);
+        for _ in 0..32 {
+            spawner.spawn(async move {
+                CPUS_SEEN.fetch_or(1 << percpu::cpu_index(), Ordering::Relaxed);
+                let start = time::rdtsc();
+                while time::rdtsc() - start < busy_cycles {
+                    core::hint::spin_loop();
+                }
+            });
+        }
+    });
+    executor.run_until_complete();
+
+    let seen = CPUS_SEEN.load(Ordering::Relaxed);
+    assert_eq!(seen.count_ones() as usize, smp::cpu_count());
+    // O injector estava vazio: as outras CPUs só tiveram tasks roubando
+    let parent = PARENT_CPU.load(Ordering::Relaxed) as usize;
+    for worker in executor.worker_stats() {
+        if worker.cpu != parent {
+            assert!(worker.steals > 0, "CPU {} never stole", worker.cpu);
+        }
+    }
+}
+
+/// Testa muitas tasks que cedem a vez e criam outras tasks.
+#[test_case]
+fn multicore_many_tasks() {
+    static SUM: AtomicU64 = AtomicU64::new(0);
+
+    let executor = MulticoreExecutor::new();
+    let handles: Vec<_> = (0..500u64)
+        .map(|i| {
+            let spawner = executor.clone();
+            executor.spawn(async move {
+                task::yield_now().await;
+                let child = spawner.spawn(async move { i * 2 });
+                task::yield_now().await;
+                SUM.fetch_add(child.await.unwrap(), Ordering::Relaxed);
+            })
+        })
+        .collect();
+    executor.run_until_complete();
+
+    assert_eq!(SUM.load(Ordering::Relaxed), (0..500).map(|i| i * 2).sum());
+    assert!(handles.iter().all(|handle| handle.is_finished()));
+    assert_eq!(executor.task_count(), 0);
+}
+
+/// Testa que `ipi::call` executa a função em todas as CPUs pedidas.
+#[test_case]
+fn ipi_call_runs_on_targets() {
+    static CPUS_SEEN: AtomicU64 = AtomicU64::new(0);
+
+    let before = ipi::calls_handled();
+    ipi::call(Target::All, &|| {
+        CPUS_SEEN.fetch_or(1 << percpu::cpu_index(), Ordering::Relaxed);
+    });
+    let seen = CPUS_SEEN.load(Ordering::Relaxed);
+    assert_eq!(seen.count_ones() as usize, smp::cpu_count());
+    assert_eq!(ipi::calls_handled() - before, smp::cpu_count() as u64 - 1);
+
+    CPUS_SEEN.store(0, Ordering::Relaxed);
+    ipi::call(Target::Cpu(1), &|| {
+        CPUS_SEEN.fetch_or(1 << percpu::cpu_index(), Ordering::Relaxed);
+    });
+    assert_eq!(CPUS_SEEN.load(Ordering::Relaxed), 1 << 1);
+}
+
+/// Testa que outra CPU não usa a tradução antiga depois de um remapeamento.
+#[test_case]
+fn tlb_shootdown_reaches_other_cpus() {
+    static VALUE: AtomicU64 = AtomicU64::new(0);
+
+    let page: Page<Size4KiB> = Page::containing_address(VirtAddr::new(0x_6666_6666_0000));
+    let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
+    let frame = |value: u64| -> PhysFrame {
+        let frame = memory::with_kernel_space(|space| space.frame_allocator.allocate_frame())
+            .expect("out of frames");
+        let ptr = memory::phys_to_virt(frame.start_address()).as_mut_ptr::<u64>();
+        unsafe { ptr.write_volatile(value) };
+        frame
+    };
+    let read_on_cpu1 = || {
+        ipi::call(Target::Cpu(1), &|| {
+            let value = unsafe { page.start_address().as_ptr::<u64>().read_volatile() };
+            VALUE.store(value, Ordering::Relaxed);
+        });
+        VALUE.load(Ordering::Relaxed)
+    };
+
+    let (first, second) = (frame(1), frame(2));
+    memory::with_kernel_space(|space| unsafe {
+        space
+            .mapper
+            .map_to(page, first, flags, &mut space.frame_allocator)
+            .unwrap()
+            .flush();
+    });
+    assert_eq!(read_on_cpu1(), 1);
+
+    let mut shootdown = TlbShootdown::new();
+    memory::with_kernel_space(|space| unsafe {
+        space.mapper.unmap(page).unwrap().1.ignore();
+        space
+            .mapper
+            .map_to(page, second, flags, &mut space.frame_allocator)
+            .unwrap()
+            .ignore();
+    });
+    shootdown.add(page);
+    shootdown.finish();
+    assert_eq!(read_on_cpu1(), 2);
+    assert_eq!(
+        unsafe { page.start_address().as_ptr::<u64>().read_volatile() },
+        2
+    );
+}
+
+/// Testa que uma CPU esperando um `IrqSpinlock` preso por outra é contada
+/// como contenção e que o dono registrado é a CPU que segura o lock.
+#[test_case]
+fn irq_spinlock_contention() {
+    static LOCK: IrqSpinlock<u64> = IrqSpinlock::new("test", 0);
+    static DONE: AtomicBool = AtomicBool::new(false);
+
+    let mut value = LOCK.lock();
+    assert_eq!(LOCK.owner().unwrap().cpu, 0);
+    smp::run_on(1, || {
+        *LOCK.lock() += 1;
+        DONE.store(true, Ordering::Release);
+    })
+    .unwrap();
+    // A IPI de `run_on` chega à AP mesmo com interrupções desabilitadas aqui
+    while LOCK.stats().contentions == 0 {
+        core::hint::spin_loop();
+    }
+    *value += 1;
+    drop(value);
+
+    while !DONE.load(Ordering::Acquire) {
+        core::hint::spin_loop();
+    }
+    assert_eq!(*LOCK.lock(), 2);
+    assert_eq!(LOCK.stats().contentions, 1);
+}
@@ -0,0 +1,191 @@
+//! Testes de integração para as primitivas de `task::sync`.
+
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(rust_os::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::{rc::Rc, vec::Vec};
+use bootloader::{entry_point, BootInfo};
+use core::{cell::RefCell, panic::PanicInfo};
+use rust_os::{
+    allocator,
+    memory::{self, BootInfoFrameAllocator},
+    task::{
+        self,
+        executor::Executor,
+        sync::{Barrier, Mutex, Notify, RwLock, Semaphore},
+    },
+};
+use x86_64::VirtAddr;
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    rust_os::init();
+
+    let phys_mem_offset = VirtAddr::new(boot_info.physical_memory_offset);
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+
+    test_main();
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    rust_os::test_panic_handler(info)
+}
+
+/// Testa que o guard do `Mutex` atravessa `.await` sem travar o executor.
+#[test_case]
+fn mutex_held_across_await() {
+    static COUNTER: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+    let mut executor = Executor::new();
+
+    for id in 0..3 {
+        executor.spawn(async move {
+            let mut guard = COUNTER.lock().await;
+            guard.push(id);
+            task::yield_now().await;
+            guard.push(id);
+        });
+    }
+    executor.run_until_idle();
+
+    // Cada task completou sua seção crítica sem intercalar
+    assert_eq!(*COUNTER.try_lock().unwrap(), [0, 0, 1, 1, 2, 2]);
+}
+
+/// Testa leitores simultâneos e o escritor exclusivo do `RwLock`.
+#[test_case]
+fn rwlock_readers_and_writer() {
+    let mut executor = Executor::new();
+    let lock = Rc::new(RwLock::new(0));
+    let log = Rc::new(RefCell::new(Vec::new()));
+
+    let (reader_lock, reader_log) = (lock.clone(), log.clone());
+    executor.spawn(async move {
+        let first = reader_lock.read().await;
+        let second = reader_lock.try_read().expect("readers are shared");
+        reader_log.borrow_mut().push(*first + *second);
+        task::yield_now().await;
+        reader_log.borrow_mut().push(-1);
+    });
+    let (writer_lock, writer_log) = (lock.clone(), log.clone());
+    executor.spawn(async move {
+        let mut value = writer_lock.write().await;
+        *value = 10;
+        writer_log.borrow_mut().push(*value);
+    });
+    executor.run_until_idle();
+
+    // O escritor esperou os leitores soltarem o lock
+    assert_eq!(*log.borrow(), [0, -1, 10]);
+    assert_eq!(*lock.try_read().unwrap(), 10);
+}
+
+/// Testa que o `Semaphore` limita o número de tasks na seção crítica.
+#[test_case]
+fn semaphore_limits_concurrency() {
+    static SEMAPHORE: Semaphore = Semaphore::new(2);
+    let mut executor = Executor::new();
+    let active = Rc::new(RefCell::new(0));
+    let max_active = Rc::new(RefCell::new(0));
+
+    for _ in 0..6 {
+        let (active, max_active) = (active.clone(), max_active.clone());
+        executor.spawn(async move {
+            let _permit = SEMAPHORE.acquire().await;
+            *active.borrow_mut() += 1;
+            let current = *active.borrow();
+            max_active.replace_with(|max| (*max).max(current));
+            task::yield_now().await;
+            *active.borrow_mut() -= 1;
+        });
+    }
+    executor.run_until_idle();
+
+    assert_eq!(*max_active.borrow(), 2);
+    assert_eq!(SEMAPHORE.available_permits(), 2);
+}
+
+/// Testa `notify_one` (com permissão guardada) e `notify_waiters`.
+#[test_case]
+fn notify_wakes_waiters() {
+    let mut executor = Executor::new();
+    let notify = Rc::new(Notify::new());
+    let woken = Rc::new(RefCell::new(0));
+
+    for _ in 0..3 {
+        let (notify, woken) = (notify.clone(), woken.clone());
+        executor.spawn(async move {
+            notify.notified().await;
+            *woken.borrow_mut() += 1;
+        });
+    }
+    executor.run_until_idle();
+    assert_eq!(*woken.borrow(), 0);
+
+    notify.notify_one();
+    executor.run_until_idle();
+    assert_eq!(*woken.borrow(), 1);
+
+    notify.notify_waiters();
+    executor.run_until_idle();
+    assert_eq!(*woken.borrow(), 3);
+
+    // Sem ninguém aguardando, `notify_one` guarda uma permissão
+    notify.notify_one();
+    let late = notify.clone();
+    let handle = executor.spawn(async move { late.notified().await });
+    executor.run_until_idle();
+    assert!(handle.is_finished());
+}
+
+/// Testa que a `Barrier` libera todas as tasks juntas, com um líder.
+#[test_case]
+fn barrier_releases_all_parties() {
+    static BARRIER: Barrier = Barrier::new(3);
+    let mut executor = Executor::new();
+    let passed = Rc::new(RefCell::new(Vec::new()));
+
+    for _ in 0..3 {
+        let passed = passed.clone();
+        executor.spawn(async move {
+            let result = BARRIER.wait().await;
+            passed.borrow_mut().push(result.is_leader());
+        });
+    }
+    executor.run_until_idle();
+
+    let passed = passed.borrow();
+    assert_eq!(passed.len(), 3);
+    assert_eq!(passed.iter().filter(|&&leader| leader).count(), 1);
+}
+
+/// Testa que uma task cancelada em `wait` não conta como chegada.
+#[test_case]
+fn barrier_wait_is_cancel_safe() {
+    static BARRIER: Barrier = Barrier::new(2);
+    let mut executor = Executor::new();
+
+    let aborted = executor.spawn(async { BARRIER.wait().await });
+    executor.run_until_idle();
+    aborted.abort();
+    executor.run_until_idle();
+
+    let first = executor.spawn(async { BARRIER.wait().await });
+    executor.run_until_idle();
+    assert!(!first.is_finished());
+
+    let second = executor.spawn(async { BARRIER.wait().await });
+    executor.run_until_idle();
+    assert!(first.is_finished());
+    assert!(second.is_finished());
+}
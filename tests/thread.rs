@@ -0,0 +1,215 @@
+//! Testes de integração para as threads do kernel.
+
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(rust_os::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::rc::Rc;
+use bootloader::{entry_point, BootInfo};
+use core::{
+    cell::Cell,
+    panic::PanicInfo,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+};
+use rust_os::{
+    allocator, fpu,
+    memory::{self, BootInfoFrameAllocator},
+    simd,
+    task::executor::Executor,
+    thread::{self, stack, ThreadState, WaitQueue},
+    time,
+};
+use x86_64::VirtAddr;
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    rust_os::init();
+
+    let phys_mem_offset = VirtAddr::new(boot_info.physical_memory_offset);
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+    memory::set_kernel_space(mapper, frame_allocator);
+    thread::init().expect("thread initialization failed");
+
+    test_main();
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    rust_os::test_panic_handler(info)
+}
+
+/// Testa que `join` retorna o resultado da thread.
+#[test_case]
+fn join_returns_result() {
+    let handle = thread::Builder::new()
+        .name("answer")
+        .spawn(|| 6 * 7)
+        .unwrap();
+    assert_eq!(handle.join(), 42);
+}
+
+/// Testa que o timer preempta uma thread que nunca cede a CPU.
+#[test_case]
+fn busy_thread_is_preempted() {
+    static FLAG: AtomicBool = AtomicBool::new(false);
+
+    // A primeira thread só termina depois que a segunda executar
+    let spinner = thread::spawn(|| {
+        while !FLAG.load(Ordering::Acquire) {
+            core::hint::spin_loop();
+        }
+    });
+    let setter = thread::spawn(|| FLAG.store(true, Ordering::Release));
+
+    spinner.join();
+    setter.join();
+}
+
+/// Testa que `sleep` espera pelo menos os ticks pedidos.
+#[test_case]
+fn sleep_waits_for_ticks() {
+    let handle = thread::spawn(|| {
+        let start = time::ticks();
+        thread::sleep(2);
+        time::ticks() - start
+    });
+    assert!(handle.join() >= 2);
+}
+
+/// Testa que uma thread bloqueada numa `WaitQueue` só volta com `notify`.
+#[test_case]
+fn wait_queue_blocks_until_notified() {
+    static QUEUE: WaitQueue = WaitQueue::new();
+    static VALUE: AtomicU64 = AtomicU64::new(0);
+
+    let consumer = thread::spawn(|| {
+        QUEUE.wait_until(|| VALUE.load(Ordering::Acquire) != 0);
+        VALUE.load(Ordering::Acquire)
+    });
+    let id = consumer.id();
+
+    let state = || thread::list().into_iter().find(|t| t.id == id).unwrap().state;
+    while state() != ThreadState::Blocked {
+        thread::yield_now();
+    }
+
+    VALUE.store(7, Ordering::Release);
+    assert_eq!(QUEUE.notify_all(), 1);
+    assert_eq!(consumer.join(), 7);
+}
+
+/// Testa o executor de tasks rodando dentro de uma thread.
+#[test_case]
+fn executor_runs_in_thread() {
+    let handle = thread::spawn(|| {
+        let mut executor = Executor::new();
+        let sum = Rc::new(Cell::new(0));
+        for i in 1..=10 {
+            let sum = sum.clone();
+            executor.spawn(async move { sum.set(sum.get() + i) });
+        }
+        executor.run_until_idle();
+        sum.get()
+    });
+    assert_eq!(handle.join(), 55);
+}
+
+/// Testa que threads terminadas devolvem suas stacks.
+#[test_case]
+fn finished_threads_release_stacks() {
+    let baseline = thread::list().len();
+    for i in 0..stack::MAX_STACKS + 10 {
+        assert_eq!(thread::spawn(move || i).join(), i);
+    }
+    // A última thread pode ter sido preemptada antes de terminar
+    thread::yield_now();
+    assert_eq!(thread::list().len(), baseline);
+}
+
+// Rotinas em assembly, pela ABI C: o teste precisa de um valor que fica
+// em XMM2 entre as chamadas, atravessando as trocas de thread, o que as
+// rotinas de `simd` (que entram em `kernel_fpu_begin`) não permitem.
+core::arch::global_asm!(
+    ".global thread_test_set_xmm2",
+    "thread_test_set_xmm2:",
+    "movq xmm2, rdi",
+    "ret",
+    ".global thread_test_add_xmm2",
+    "thread_test_add_xmm2:",
+    "movq xmm3, rdi",
+    "addsd xmm2, xmm3",
+    "movq rax, xmm2",
+    "ret",
+);
+
+unsafe extern "C" {
+    fn thread_test_set_xmm2(bits: u64);
+    fn thread_test_add_xmm2(bits: u64) -> u64;
+}
+
+/// Coloca `value` em XMM2.
+fn set_xmm2(value: f64) {
+    unsafe { thread_test_set_xmm2(value.to_bits()) };
+}
+
+/// Lê XMM2 somando `increment` com `addsd`.
+fn add_xmm2(increment: f64) -> f64 {
+    f64::from_bits(unsafe { thread_test_add_xmm2(increment.to_bits()) })
+}
+
+/// Testa que cada thread mantém seus registradores SSE entre trocas de
+/// contexto (salvos e restaurados sob demanda via `#NM`).
+#[test_case]
+fn sse_state_is_per_thread() {
+    let before = fpu::lazy_switches();
+    let handles: alloc::vec::Vec<_> = [1.0, 1000.0]
+        .iter()
+        .map(|&start| {
+            thread::spawn(move || {
+                set_xmm2(start);
+                let mut expected = start;
+                for _ in 0..50 {
+                    thread::yield_now();
+                    expected += 0.5;
+                    assert_eq!(add_xmm2(0.5), expected);
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join();
+    }
+    assert!(fpu::lazy_switches() - before >= 50);
+}
+
+/// Testa contas com `simd` em várias threads, com trocas de contexto entre
+/// as chamadas e vetores maiores que `simd::CHUNK`.
+#[test_case]
+fn simd_in_threads() {
+    let handles: alloc::vec::Vec<_> = (1..=4)
+        .map(|scale| {
+            thread::spawn(move || {
+                let x: alloc::vec::Vec<f64> = (0..3000).map(|i| (i % 4) as f64).collect();
+                let mut y = alloc::vec![0.0; x.len()];
+                for _ in 0..scale {
+                    simd::axpy(1.0, &x, &mut y);
+                    thread::yield_now();
+                }
+                simd::dot(&x, &y)
+            })
+        })
+        .collect();
+    // x · x = 750 * (0 + 1 + 4 + 9), e y = scale * x
+    for (scale, handle) in (1..=4).zip(handles) {
+        assert_eq!(handle.join(), (scale * 10500) as f64);
+    }
+}
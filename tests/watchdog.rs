@@ -0,0 +1,60 @@
+//! Testes de integração para o handler de NMI do watchdog.
+
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(rust_os::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use bootloader::{entry_point, BootInfo};
+use core::panic::PanicInfo;
+use rust_os::{
+    allocator, apic, gdt,
+    memory::{self, BootInfoFrameAllocator},
+    watchdog,
+};
+use x86_64::VirtAddr;
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    rust_os::init();
+
+    let phys_mem_offset = VirtAddr::new(boot_info.physical_memory_offset);
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+    apic::init().expect("Local APIC initialization failed");
+
+    test_main();
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    rust_os::test_panic_handler(info)
+}
+
+/// Testa que uma NMI enviada pelo ICR do Local APIC é tratada na stack da
+/// IST e retorna.
+#[test_case]
+fn self_nmi_runs_on_ist_stack() {
+    let before = watchdog::nmi_count();
+    apic::send_nmi(apic::id());
+
+    // O ICR só confirma o envio; a entrega acontece logo depois
+    for _ in 0..1_000_000 {
+        if watchdog::nmi_count() > before {
+            break;
+        }
+        core::hint::spin_loop();
+    }
+    assert_eq!(watchdog::nmi_count(), before + 1);
+
+    let top = gdt::bsp_ist_top(gdt::NMI_IST_INDEX).as_u64();
+    let rbp = watchdog::last_nmi_frame_pointer();
+    assert!(rbp < top && rbp >= top - gdt::IST_STACK_SIZE as u64);
+}
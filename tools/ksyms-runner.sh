@@ -0,0 +1,46 @@
+#!/bin/sh
+# Runner do cargo: grava a tabela de símbolos na seção `.ksyms` do kernel
+# (ver src/backtrace.rs) e repassa a execução para o `bootimage runner`.
+#
+# Requer o componente `llvm-tools-preview` (llvm-nm, llvm-objdump, llvm-objcopy).
+set -e
+
+kernel="$1"
+sysroot=$(rustc --print sysroot)
+host=$(rustc -vV | sed -n 's/^host: //p')
+tools="$sysroot/lib/rustlib/$host/bin"
+
+size=$("$tools/llvm-objdump" -h "$kernel" | awk '$2 == ".ksyms" { print $3 }')
+if [ -z "$size" ]; then
+    echo "ksyms: section .ksyms not found in $kernel, skipping" >&2
+    exec bootimage runner "$@"
+fi
+size=$((0x$size))
+
+table=$(mktemp)
+trap 'rm -f "$table" "$table.fit"' EXIT
+
+# Só símbolos de código, ordenados por endereço e sem o hash do mangling legado
+{
+    printf '# ksyms\n'
+    "$tools/llvm-nm" --defined-only --demangle --numeric-sort "$kernel" \
+        | awk '$2 ~ /^[tTwW]$/' \
+        | sed -E 's/::h[0-9a-f]{16}$//'
+} > "$table"
+
+if [ "$(wc -c < "$table")" -gt "$size" ]; then
+    echo "ksyms: symbol table truncated to $size bytes" >&2
+    # Mantém só as linhas completas que cabem na seção
+    LC_ALL=C awk -v max="$size" '{ total += length($0) + 1; if (total > max) exit; print }' \
+        "$table" > "$table.fit"
+    mv "$table.fit" "$table"
+fi
+# Completa com zeros até o tamanho da seção (o parser para no primeiro 0)
+truncate -s "$size" "$table"
+
+"$tools/llvm-objcopy" --update-section .ksyms="$table" "$kernel"
+
+# O `exec` substitui o shell, então o trap de EXIT não roda depois dele
+rm -f "$table"
+trap - EXIT
+exec bootimage runner "$@"